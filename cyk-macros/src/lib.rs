@@ -0,0 +1,320 @@
+//! The `grammar!` macro of the `cyk` crate.
+//!
+//! ```ignore
+//! let grammar = grammar! {
+//!     start S;
+//!     S -> A B | 'x';
+//!     A -> 'a';
+//!     B -> 'b' | 'c';
+//! };
+//! ```
+//!
+//! Names are non-terminals and char or single-char string literals are
+//! terminals; an empty alternative is the empty string. Starting the input
+//! with `static NAME;` (optionally `pub`) declares a lazily built static
+//! instead of evaluating to a `CNF`. Mistakes are reported at compile time,
+//! pointing at the offending token or alternative.
+//!
+//! The macro emits constant tables: the names, with their indexes as the
+//! ids of the grammar's symbols, and the productions over those ids. A `CNF`
+//! holds hash sets, so it is still put together from the tables when the
+//! macro is evaluated, or on first use of a static, in a symbol table of its
+//! own rather than the process-wide one. A grammar that is not in Chomsky
+//! normal form is converted then, by `Cfg::to_cnf` through the process-wide
+//! table, so its helper symbols are only known at that point.
+
+extern crate proc_macro;
+
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Error, Ident, LitChar, LitStr, Token, Visibility};
+
+use std::collections::HashSet;
+
+#[proc_macro]
+pub fn grammar(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    match syn::parse2::<Input>(input.into()).and_then(expand) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+enum Element {
+    NonTerminal(Ident),
+    Terminal(String, Span),
+}
+
+struct Alternative {
+    elements: Vec<Element>,
+    /// The tokens of the alternative, which errors about it point at.
+    tokens: TokenStream,
+    /// Where an empty alternative is.
+    span: Span,
+}
+
+impl Alternative {
+    fn error(&self, message: &str) -> Error {
+        if self.tokens.is_empty() {
+            Error::new(self.span, message)
+        } else {
+            Error::new_spanned(&self.tokens, message)
+        }
+    }
+}
+
+struct Rule {
+    left: Ident,
+    alternatives: Vec<Alternative>,
+}
+
+struct Input {
+    name: Option<(Visibility, Ident)>,
+    start: Option<Ident>,
+    rules: Vec<Rule>,
+}
+
+fn parse_element(input: ParseStream) -> syn::Result<Element> {
+    if input.peek(Ident) {
+        return Ok(Element::NonTerminal(input.parse()?));
+    }
+
+    let (value, span) = if input.peek(LitChar) {
+        let literal: LitChar = input.parse()?;
+        (literal.value().to_string(), literal.span())
+    } else {
+        let literal: LitStr = input.parse()?;
+        (literal.value(), literal.span())
+    };
+
+    if value.chars().count() != 1 {
+        return Err(Error::new(span, "a terminal must be a single char"));
+    }
+    Ok(Element::Terminal(value, span))
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut name = None;
+        if input.peek(Token![static]) || input.peek(Token![pub]) {
+            let visibility: Visibility = input.parse()?;
+            input.parse::<Token![static]>()?;
+            let ident: Ident = input.parse()?;
+            input.parse::<Token![;]>()?;
+            name = Some((visibility, ident));
+        }
+
+        let mut start = None;
+        if input.peek(Ident) && !input.peek2(Token![->]) {
+            let keyword: Ident = input.parse()?;
+            if keyword != "start" {
+                return Err(Error::new(keyword.span(), "expected `start` or a rule"));
+            }
+            start = Some(input.parse()?);
+            input.parse::<Token![;]>()?;
+        }
+
+        let mut rules = Vec::new();
+        while !input.is_empty() {
+            let left: Ident = input.parse()?;
+            input.parse::<Token![->]>()?;
+
+            let mut alternatives = Vec::new();
+            loop {
+                let span = input.span();
+                let mut elements = Vec::new();
+                let mut tokens = TokenStream::new();
+                while !input.peek(Token![|]) && !input.peek(Token![;]) && !input.is_empty() {
+                    let fork = input.fork();
+                    elements.push(parse_element(input)?);
+                    while fork.cursor() != input.cursor() {
+                        fork.parse::<proc_macro2::TokenTree>()?
+                            .to_tokens(&mut tokens);
+                    }
+                }
+                alternatives.push(Alternative {
+                    elements,
+                    tokens,
+                    span,
+                });
+
+                if input.parse::<Option<Token![|]>>()?.is_none() {
+                    break;
+                }
+            }
+            input.parse::<Token![;]>()?;
+
+            rules.push(Rule { left, alternatives });
+        }
+
+        Ok(Input { name, start, rules })
+    }
+}
+
+fn push_error(errors: &mut Option<Error>, error: Error) {
+    match errors {
+        Some(errors) => errors.combine(error),
+        None => *errors = Some(error),
+    }
+}
+
+fn expand(input: Input) -> syn::Result<TokenStream> {
+    let first = match input.rules.first() {
+        Some(rule) => rule.left.clone(),
+        None => return Err(Error::new(Span::call_site(), "the grammar has no rules")),
+    };
+    let start = input.start.unwrap_or(first);
+
+    let mut non_terminals: Vec<String> = Vec::new();
+    for rule in &input.rules {
+        let name = rule.left.to_string();
+        if !non_terminals.contains(&name) {
+            non_terminals.push(name);
+        }
+    }
+
+    let mut errors: Option<Error> = None;
+    if !non_terminals.contains(&start.to_string()) {
+        let message = format!("start symbol `{}` has no rules", start);
+        push_error(&mut errors, Error::new(start.span(), message));
+    }
+
+    let mut terminals: Vec<String> = Vec::new();
+    let mut seen: HashSet<(String, Vec<String>)> = HashSet::new();
+    let mut productions: Vec<(String, Vec<String>)> = Vec::new();
+
+    for rule in &input.rules {
+        let left = rule.left.to_string();
+
+        for alternative in &rule.alternatives {
+            for element in &alternative.elements {
+                match element {
+                    Element::NonTerminal(ident) if !non_terminals.contains(&ident.to_string()) => {
+                        let message = format!("non-terminal `{}` has no rules", ident);
+                        push_error(&mut errors, Error::new(ident.span(), message));
+                    }
+                    Element::Terminal(value, span) if non_terminals.contains(value) => {
+                        let message =
+                            format!("terminal `{}` is also the name of a non-terminal", value);
+                        push_error(&mut errors, Error::new(*span, message));
+                    }
+                    Element::Terminal(value, _) if !terminals.contains(value) => {
+                        terminals.push(value.clone());
+                    }
+                    _ => {}
+                }
+            }
+
+            let names: Vec<String> = alternative
+                .elements
+                .iter()
+                .map(|element| match element {
+                    Element::NonTerminal(ident) => ident.to_string(),
+                    Element::Terminal(value, _) => value.clone(),
+                })
+                .collect();
+            if !seen.insert((left.clone(), names.clone())) {
+                push_error(&mut errors, alternative.error("duplicate alternative"));
+                continue;
+            }
+            productions.push((left.clone(), names));
+        }
+    }
+
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+
+    let mut names = non_terminals.clone();
+    names.extend(terminals);
+    let index = |name: &String| names.iter().position(|other| other == name).unwrap() as u32;
+    let count = non_terminals.len();
+    let start = index(&start.to_string());
+    let productions = productions.iter().map(|(left, right)| {
+        let left = index(left);
+        let right = right.iter().map(index);
+        quote! { (#left, &[#(#right),*]) }
+    });
+
+    let grammar = quote! {
+        {
+            const TABLES: ::cyk::GrammarTables = ::cyk::GrammarTables {
+                names: &[#(#names),*],
+                non_terminals: #count,
+                start: #start,
+                productions: &[#(#productions),*],
+            };
+            TABLES.build()
+        }
+    };
+
+    Ok(match input.name {
+        Some((visibility, name)) => quote! {
+            ::cyk::lazy_static! {
+                #visibility static ref #name: ::cyk::CNF = #grammar;
+            }
+        },
+        None => grammar,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn errors(tokens: TokenStream) -> Vec<String> {
+        match syn::parse2::<Input>(tokens).and_then(expand) {
+            Ok(_) => vec![],
+            Err(error) => error.into_iter().map(|error| error.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn accept_cnf() {
+        assert!(errors(quote! {
+            start S;
+            S -> A B | 'x';
+            A -> 'a';
+            B -> "b" | 'c';
+        })
+        .is_empty());
+        assert!(errors(quote! {
+            pub static GRAMMAR;
+            S -> S S | 'a';
+        })
+        .is_empty());
+    }
+
+    #[test]
+    fn reject_mistakes() {
+        assert_eq!(
+            errors(quote! {
+                start T;
+                S -> A C | A | 'a' 'b' | 'a' | 'a';
+                A -> "ab" | 'S';
+            }),
+            vec!["a terminal must be a single char".to_string(),]
+        );
+        assert_eq!(
+            errors(quote! {
+                start T;
+                S -> A C | A | 'a' 'b' | 'a' | 'a';
+                A -> 'S';
+            }),
+            vec![
+                "start symbol `T` has no rules".to_string(),
+                "non-terminal `C` has no rules".to_string(),
+                "duplicate alternative".to_string(),
+                "terminal `S` is also the name of a non-terminal".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn accept_conversions() {
+        assert!(errors(quote! {
+            S -> '(' S ')' S | ;
+        })
+        .is_empty());
+    }
+}
@@ -0,0 +1,52 @@
+use cyk::{Grammar, Parser, SymbolTable};
+use cyk_macros::grammar;
+
+grammar! {
+    static BALANCED;
+    start S;
+    S -> L R | L T | S S;
+    T -> S R;
+    L -> '(';
+    R -> ')';
+}
+
+#[test]
+fn build_grammar() {
+    let grammar = grammar! {
+        Number -> Number Digit | '0' | '1';
+        Digit -> "0" | "1";
+    };
+
+    assert_eq!(
+        grammar.symbols().get("Number"),
+        Some(grammar.start_symbol())
+    );
+    assert_eq!(grammar.symbols().len(), 4);
+    assert_eq!(SymbolTable::global().get("Number"), None);
+    assert_eq!(grammar.productions().len(), 5);
+    assert!(Parser::new(&grammar).parse("1010").is_ok());
+    assert!(Parser::new(&grammar).parse("102").is_err());
+}
+
+#[test]
+fn build_static_grammar() {
+    let mut parser = Parser::new(&*BALANCED);
+    assert!(parser.parse("(()())").is_ok());
+    assert!(parser.parse("(()").is_err());
+}
+
+#[test]
+fn convert_grammar() {
+    let grammar = grammar! {
+        Nested -> '(' Nested ')' Nested | ;
+    };
+
+    let mut parser = Parser::new(&grammar);
+    assert!(parser.parse("(()())").is_ok());
+    assert!(parser.parse("(()").is_err());
+    assert!(grammar.validate().is_ok());
+    for name in &["Nested", "Nested_1", "<(>"] {
+        assert!(grammar.symbols().get(name).is_some());
+        assert_eq!(SymbolTable::global().get(name), None);
+    }
+}
@@ -0,0 +1,217 @@
+use super::{EvalError, Grammar, MissingAction, ParseObserver, Parser, Production, Symbol, Tree};
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+type Action<T> = Box<dyn Fn(&str, Vec<T>) -> T>;
+
+/// Semantic actions keyed by production, evaluated bottom-up over a tree.
+///
+/// An action receives the text its node matched and the values of the node's
+/// non-terminal children: two for `A -> B C`, none for `A -> t`.
+pub struct Actions<T> {
+    productions: HashMap<Production, Action<T>>,
+    symbols: HashMap<Symbol, Action<T>>,
+}
+
+impl<T> Default for Actions<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Actions<T> {
+    pub fn new() -> Self {
+        Actions {
+            productions: HashMap::new(),
+            symbols: HashMap::new(),
+        }
+    }
+
+    /// Registers the action for one production.
+    pub fn rule<F: Fn(&str, Vec<T>) -> T + 'static>(
+        mut self,
+        production: Production,
+        action: F,
+    ) -> Self {
+        self.productions.insert(production, Box::new(action));
+        self
+    }
+
+    /// Registers the action for `A -> B C`.
+    pub fn binary<F: Fn(&str, Vec<T>) -> T + 'static>(
+        self,
+        left: Symbol,
+        first: Symbol,
+        second: Symbol,
+        action: F,
+    ) -> Self {
+        self.rule(Production::Binary(left, first, second), action)
+    }
+
+    /// Registers the action for `A -> t`.
+    pub fn terminal<F: Fn(&str, Vec<T>) -> T + 'static>(
+        self,
+        left: Symbol,
+        terminal: Symbol,
+        action: F,
+    ) -> Self {
+        self.rule(Production::Terminal(left, terminal), action)
+    }
+
+    /// Registers the action used for every production of `symbol` that has
+    /// no action of its own.
+    pub fn symbol<F: Fn(&str, Vec<T>) -> T + 'static>(mut self, symbol: Symbol, action: F) -> Self {
+        self.symbols.insert(symbol, Box::new(action));
+        self
+    }
+
+    fn get(&self, production: Production) -> Option<&Action<T>> {
+        self.productions
+            .get(&production)
+            .or_else(|| self.symbols.get(&production.left()))
+    }
+
+    /// Evaluates `tree`, which was parsed from `src`, and returns the value of
+    /// its root.
+    pub fn evaluate(&self, tree: &Tree, src: &str) -> Result<T, MissingAction> {
+        let mut values: Vec<T> = Vec::new();
+
+        for id in tree.post_order(tree.root()) {
+            let production = match tree.production(id) {
+                Some(production) => production,
+                None => continue,
+            };
+            let action = self.get(production).ok_or(MissingAction(production))?;
+
+            let arity = match production {
+                Production::Binary(_, _, _) => 2,
+                Production::Terminal(_, _) => 0,
+            };
+            let children = values.split_off(values.len() - arity);
+            values.push(action(tree[id].text(src), children));
+        }
+
+        Ok(values.pop().unwrap())
+    }
+}
+
+impl<'a, G: Grammar + Debug + Clone, O: ParseObserver> Parser<'a, G, O> {
+    /// Parses `string` and evaluates the resulting tree with `actions`.
+    pub fn parse_with<T>(&mut self, string: &'a str, actions: &Actions<T>) -> Result<T, EvalError> {
+        let tree = self.parse(string).map_err(EvalError::Parse)?;
+        actions.evaluate(&tree, string).map_err(EvalError::Action)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    fn number_grammar() -> CNF {
+        cnf_grammar! {
+            Start("Number");
+            NonTerminals[
+                "Number", "N1", "Integer", "Fraction",
+                "T1", "Scale", "N2", "T2", "Digit", "Sign"
+            ];
+            Terminals[
+                "0", "1", "2", "3", "4", "5", "6",
+                "7", "8", "9", ".", "e", "+", "-"
+            ];
+            Rules [
+                "Number" => [
+                    ["Integer", "Digit"],
+                    ["N1", "Scale"],
+                    ["Integer", "Fraction"]
+                ],
+                "N1" => [
+                    ["Integer", "Fraction"]
+                ],
+                "Integer" => [
+                    ["Integer", "Digit"]
+                ],
+                "Fraction" => [
+                    ["T1", "Integer"]
+                ],
+                "Scale" => [
+                    ["N2", "Integer"]
+                ],
+                "N2" => [
+                    ["T2", "Sign"]
+                ],
+            ];
+            TerminalRules [
+                "Number" => [
+                    "0", "1", "2","3", "4", "5",
+                    "6", "7", "8", "9",
+                ],
+                "Integer" => [
+                    "0", "1", "2","3", "4", "5",
+                    "6", "7", "8", "9",
+                ],
+                "T1" => [
+                    "."
+                ],
+                "T2" => [
+                    "e"
+                ],
+                "Digit" => [
+                    "0", "1", "2","3", "4", "5",
+                    "6", "7", "8", "9",
+                ],
+                "Sign" => [
+                    "+", "-"
+                ]
+            ]
+        }
+    }
+
+    fn number_actions() -> Actions<f64> {
+        let s = Symbol::intern;
+        Actions::new()
+            .binary(s("Number"), s("N1"), s("Scale"), |_, v| {
+                v[0] * 10f64.powf(v[1])
+            })
+            .binary(s("Number"), s("Integer"), s("Fraction"), |_, v| v[0] + v[1])
+            .binary(s("N1"), s("Integer"), s("Fraction"), |_, v| v[0] + v[1])
+            .symbol(s("Number"), |text, _| text.parse().unwrap())
+            .symbol(s("Integer"), |text, _| text.parse().unwrap())
+            .symbol(s("Digit"), |text, _| text.parse().unwrap())
+            .symbol(s("Fraction"), |text, _| {
+                format!("0{}", text).parse().unwrap()
+            })
+            .symbol(s("Scale"), |_, v| v[0] * v[1])
+            .symbol(s("N2"), |_, v| v[1])
+            .terminal(s("Sign"), s("-"), |_, _| -1.0)
+            .terminal(s("Sign"), s("+"), |_, _| 1.0)
+            .symbol(s("T1"), |_, _| 0.0)
+            .symbol(s("T2"), |_, _| 0.0)
+    }
+
+    #[test]
+    fn evaluate_number() {
+        let grammar = number_grammar();
+        let actions = number_actions();
+        let mut parser = Parser::new(&grammar);
+
+        let close =
+            |value: Result<f64, EvalError>, expected: f64| (value.unwrap() - expected).abs() < 1e-9;
+        assert!(close(parser.parse_with("3.51e+1", &actions), 35.1));
+        assert!(close(parser.parse_with("2.5e-1", &actions), 0.25));
+        assert!(close(parser.parse_with("12.5", &actions), 12.5));
+        assert!(matches!(
+            parser.parse_with("1.", &actions),
+            Err(EvalError::Parse(_))
+        ));
+
+        let partial = Actions::new().symbol(Symbol::intern("Number"), |_, _| 0.0);
+        assert_eq!(
+            parser.parse_with("12", &partial),
+            Err(EvalError::Action(MissingAction(Production::Terminal(
+                Symbol::intern("Integer"),
+                Symbol::intern("1")
+            ))))
+        );
+    }
+}
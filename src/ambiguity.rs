@@ -0,0 +1,319 @@
+use super::{
+    DisplaySymbols, Enumeration, Grammar, Parser, Production, Symbol, SymbolTable, Tree, CNF,
+};
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+
+/// A pattern in the rules of a grammar that often makes it ambiguous.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AmbiguitySource {
+    /// `A -> A A`: as soon as `A` derives anything, `x x x` has two trees.
+    SelfConcatenation(Symbol),
+    /// The same production is listed more than once.
+    DuplicateRule(Production),
+    /// Several non-terminals derive the same pair `B C`, so a sentence
+    /// reaching it can often be read through either.
+    SharedPair(Symbol, Symbol, Vec<Symbol>),
+}
+
+impl DisplaySymbols for AmbiguitySource {
+    fn fmt_symbols(&self, symbols: &SymbolTable, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmbiguitySource::SelfConcatenation(symbol) => {
+                let symbol = symbol.display(symbols);
+                write!(
+                    f,
+                    "{} -> {} {} concatenates {} with itself",
+                    symbol, symbol, symbol, symbol
+                )
+            }
+            AmbiguitySource::DuplicateRule(production) => {
+                write!(
+                    f,
+                    "{} is listed more than once",
+                    production.display(symbols)
+                )
+            }
+            AmbiguitySource::SharedPair(first, second, lefts) => {
+                let (first, second) = (first.display(symbols), second.display(symbols));
+                write!(f, "{} {} is derived by", first, second)?;
+                for left in lefts {
+                    write!(f, " {}", left.display(symbols))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The shortest ambiguous sentence found, with two of its parse trees.
+#[derive(Clone, Debug)]
+pub struct Ambiguity {
+    sentence: String,
+    trees: (Tree, Tree),
+}
+
+impl Ambiguity {
+    pub fn sentence(&self) -> &str {
+        &self.sentence
+    }
+
+    pub fn trees(&self) -> (&Tree, &Tree) {
+        (&self.trees.0, &self.trees.1)
+    }
+}
+
+/// The result of looking for ambiguity in a grammar: the rule patterns that
+/// usually cause it, and the shortest sentence up to `max_length` terminals
+/// that has more than one parse tree.
+///
+/// The patterns are only hints; a grammar is known to be ambiguous when a
+/// sentence is found. Finding none says nothing about longer sentences.
+#[derive(Clone, Debug)]
+pub struct AmbiguityReport {
+    sources: Vec<AmbiguitySource>,
+    ambiguity: Option<Ambiguity>,
+    max_length: usize,
+    symbols: SymbolTable,
+}
+
+impl AmbiguityReport {
+    pub fn new<G: Grammar + Debug + Clone>(grammar: &G, max_length: usize) -> Self {
+        AmbiguityReport {
+            sources: sources(grammar),
+            ambiguity: shortest_ambiguity(grammar, max_length),
+            max_length,
+            symbols: grammar.symbols().clone(),
+        }
+    }
+
+    pub fn sources(&self) -> &[AmbiguitySource] {
+        &self.sources
+    }
+
+    pub fn ambiguity(&self) -> Option<&Ambiguity> {
+        self.ambiguity.as_ref()
+    }
+
+    pub fn is_ambiguous(&self) -> bool {
+        self.ambiguity.is_some()
+    }
+}
+
+impl fmt::Display for AmbiguityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for source in &self.sources {
+            writeln!(f, "warning: {}", source.display(&self.symbols))?;
+        }
+
+        match &self.ambiguity {
+            Some(ambiguity) => {
+                writeln!(f, "ambiguous: {:?} has two parse trees", ambiguity.sentence)?;
+                writeln!(f, "  {}", ambiguity.trees.0.to_bracketed(&self.symbols))?;
+                writeln!(f, "  {}", ambiguity.trees.1.to_bracketed(&self.symbols))
+            }
+            None => writeln!(f, "no ambiguous sentence up to length {}", self.max_length),
+        }
+    }
+}
+
+fn sorted(mut symbols: Vec<Symbol>, table: &SymbolTable) -> Vec<Symbol> {
+    symbols.sort_by_key(|symbol| table.resolve(*symbol).map(str::to_string));
+    symbols.dedup();
+    symbols
+}
+
+/// Returns the rule patterns of `grammar` that usually make it ambiguous,
+/// sorted by kind and then by name.
+fn sources<G: Grammar>(grammar: &G) -> Vec<AmbiguitySource> {
+    let table = grammar.symbols();
+    let name = |symbol: Symbol| table.resolve(symbol).unwrap_or("").to_string();
+
+    let mut seen: HashMap<Production, usize> = HashMap::new();
+    let mut pairs: HashMap<(Symbol, Symbol), Vec<Symbol>> = HashMap::new();
+    for rule in grammar.rules().iter() {
+        for branch in rule.branches() {
+            let production = Production::Binary(rule.start(), branch.left(), branch.right());
+            *seen.entry(production).or_insert(0) += 1;
+            pairs
+                .entry((branch.left(), branch.right()))
+                .or_default()
+                .push(rule.start());
+        }
+    }
+    for rule in grammar.terminal_rules().iter() {
+        for &terminal in rule.symbols() {
+            *seen
+                .entry(Production::Terminal(rule.start(), terminal))
+                .or_insert(0) += 1;
+        }
+    }
+
+    let mut concatenations: Vec<Symbol> = seen
+        .keys()
+        .filter_map(|production| match *production {
+            Production::Binary(left, first, second) if left == first && first == second => {
+                Some(left)
+            }
+            _ => None,
+        })
+        .collect();
+    concatenations = sorted(concatenations, table);
+
+    let mut duplicates: Vec<Production> = seen
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(production, _)| production)
+        .collect();
+    duplicates.sort_by_cached_key(|production| match *production {
+        Production::Binary(left, first, second) => vec![name(left), name(first), name(second)],
+        Production::Terminal(left, terminal) => vec![name(left), name(terminal)],
+    });
+
+    let mut shared: Vec<((Symbol, Symbol), Vec<Symbol>)> = pairs
+        .into_iter()
+        .map(|(pair, lefts)| (pair, sorted(lefts, table)))
+        .filter(|(_, lefts)| lefts.len() > 1)
+        .collect();
+    shared.sort_by_cached_key(|((first, second), _)| (name(*first), name(*second)));
+
+    let mut result: Vec<AmbiguitySource> = Vec::new();
+    result.extend(
+        concatenations
+            .into_iter()
+            .map(AmbiguitySource::SelfConcatenation),
+    );
+    result.extend(duplicates.into_iter().map(AmbiguitySource::DuplicateRule));
+    result.extend(
+        shared
+            .into_iter()
+            .map(|((first, second), lefts)| AmbiguitySource::SharedPair(first, second, lefts)),
+    );
+    result
+}
+
+/// Looks for the shortest sentence with two parse trees, first comparing
+/// the numbers of sentences and of derivations of each length to skip the
+/// lengths where every sentence has a single tree.
+fn shortest_ambiguity<G: Grammar + Debug + Clone>(
+    grammar: &G,
+    max_length: usize,
+) -> Option<Ambiguity> {
+    let mut language = Enumeration::new(grammar, max_length);
+
+    for length in 1..=max_length {
+        if language.derivations(length) <= language.strings(length) as u64 {
+            continue;
+        }
+
+        for sentence in language.sentences_of(length) {
+            let mut parser = Parser::new(grammar);
+            if parser.parse(&sentence).is_err() || parser.chart().count_trees() < 2 {
+                continue;
+            }
+
+            let mut trees = parser.chart().trees(2).into_iter();
+            if let (Some(first), Some(second)) = (trees.next(), trees.next()) {
+                return Some(Ambiguity {
+                    sentence,
+                    trees: (first, second),
+                });
+            }
+        }
+    }
+
+    None
+}
+
+impl CNF {
+    /// Looks for ambiguity in the sentences up to `max_length` terminals.
+    pub fn ambiguity(&self, max_length: usize) -> AmbiguityReport {
+        AmbiguityReport::new(self, max_length)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn find_ambiguity() {
+        let sums = Cfg::parse("S -> S S | 'a' | 'b';")
+            .unwrap()
+            .as_cnf()
+            .unwrap();
+        let report = sums.ambiguity(5);
+        let s = sums.symbols().get("S").unwrap();
+        assert_eq!(report.sources(), &[AmbiguitySource::SelfConcatenation(s)]);
+
+        let ambiguity = report.ambiguity().unwrap();
+        assert_eq!(ambiguity.sentence(), "aaa");
+        let (first, second) = ambiguity.trees();
+        let symbols = sums.symbols();
+        assert_eq!(first.to_bracketed(symbols), "(S (S (S a) (S a)) (S a))");
+        assert_eq!(second.to_bracketed(symbols), "(S (S a) (S (S a) (S a)))");
+
+        let shared =
+            Cfg::parse("S -> X D | Y D; X -> A B; Y -> A B; A -> 'a'; B -> 'b'; D -> 'd';")
+                .unwrap()
+                .as_cnf()
+                .unwrap();
+        let report = shared.ambiguity(4);
+        assert_eq!(report.ambiguity().unwrap().sentence(), "abd");
+        let symbol = |name| shared.symbols().get(name).unwrap();
+        assert_eq!(
+            report.sources(),
+            &[AmbiguitySource::SharedPair(
+                symbol("A"),
+                symbol("B"),
+                vec![symbol("X"), symbol("Y")]
+            )]
+        );
+
+        let unambiguous = Cfg::parse("S -> A S | 'a'; A -> 'a';")
+            .unwrap()
+            .as_cnf()
+            .unwrap();
+        let report = unambiguous.ambiguity(6);
+        assert!(!report.is_ambiguous());
+        assert!(report.sources().is_empty());
+    }
+
+    #[test]
+    fn find_duplicate_rules() {
+        let mut symbols = SymbolTable::new();
+        let (s, a, t) = (
+            symbols.intern("S"),
+            symbols.intern("A"),
+            symbols.intern("a"),
+        );
+        let mut rules = Rules::new();
+        let mut terminal_rules = TerminalRules::new();
+        for _ in 0..2 {
+            rules.insert(s, vec![RuleRight::new(a, a)].into_iter().collect());
+            terminal_rules.insert(a, vec![t].into_iter().collect());
+        }
+        let grammar = CNF::new(
+            s,
+            vec![t].into_iter().collect(),
+            vec![s, a].into_iter().collect(),
+            rules,
+            terminal_rules,
+        )
+        .with_symbols(symbols.clone());
+
+        let report = grammar.ambiguity(4);
+        assert_eq!(
+            report.sources(),
+            &[
+                AmbiguitySource::DuplicateRule(Production::Terminal(a, t)),
+                AmbiguitySource::DuplicateRule(Production::Binary(s, a, a)),
+            ]
+        );
+        assert_eq!(
+            report.sources()[1].display(&symbols).to_string(),
+            "S -> A A is listed more than once"
+        );
+    }
+}
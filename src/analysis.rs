@@ -0,0 +1,416 @@
+use super::{
+    Cfg, DisplaySymbols, Grammar, HashSet, RuleRight, Rules, Symbol, SymbolTable, TerminalRules,
+    CNF,
+};
+
+use std::fmt;
+
+/// Returns the non-terminals that derive at least one terminal string.
+pub(crate) fn productive_symbols<G: Grammar>(grammar: &G) -> HashSet<Symbol> {
+    let mut result: HashSet<Symbol> = grammar
+        .terminal_rules()
+        .iter()
+        .filter(|rule| rule.symbols().next().is_some())
+        .map(|rule| rule.start())
+        .collect();
+
+    loop {
+        let mut changed = false;
+
+        for rule in grammar.rules().iter() {
+            if !result.contains(&rule.start())
+                && rule.branches().any(|branch| {
+                    result.contains(&branch.left()) && result.contains(&branch.right())
+                })
+            {
+                result.insert(rule.start());
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return result;
+        }
+    }
+}
+
+/// Returns the symbols, terminals included, that occur in some sentential
+/// form of the start symbol.
+pub(crate) fn reachable_symbols<G: Grammar>(grammar: &G) -> HashSet<Symbol> {
+    let mut result: HashSet<Symbol> = HashSet::new();
+    let mut stack = vec![grammar.start_symbol()];
+
+    while let Some(symbol) = stack.pop() {
+        if !result.insert(symbol) {
+            continue;
+        }
+
+        for rule in grammar.rules().iter().filter(|rule| rule.start() == symbol) {
+            for branch in rule.branches() {
+                stack.push(branch.left());
+                stack.push(branch.right());
+            }
+        }
+        for rule in grammar.terminal_rules().iter() {
+            if rule.start() == symbol {
+                stack.extend(rule.symbols());
+            }
+        }
+    }
+
+    result
+}
+
+fn sorted(symbols: impl Iterator<Item = Symbol>, table: &SymbolTable) -> Vec<Symbol> {
+    let mut symbols: Vec<Symbol> = symbols.collect();
+    symbols.sort_by_cached_key(|symbol| table.resolve(*symbol).map(str::to_string));
+    symbols
+}
+
+/// A report of the symbols that take no part in any sentence of a grammar.
+///
+/// Every list is sorted by name. A non-terminal without rules is also
+/// non-productive, so it shows up in both lists.
+#[derive(Clone, Debug)]
+pub struct GrammarAnalysis {
+    unreachable: Vec<Symbol>,
+    non_productive: Vec<Symbol>,
+    unused_terminals: Vec<Symbol>,
+    without_rules: Vec<Symbol>,
+    symbols: SymbolTable,
+}
+
+/// Two analyses are equal when they list the same symbols, whatever the
+/// tables the names were taken from.
+impl PartialEq for GrammarAnalysis {
+    fn eq(&self, other: &Self) -> bool {
+        self.unreachable == other.unreachable
+            && self.non_productive == other.non_productive
+            && self.unused_terminals == other.unused_terminals
+            && self.without_rules == other.without_rules
+    }
+}
+
+impl Eq for GrammarAnalysis {}
+
+impl GrammarAnalysis {
+    pub fn new<G: Grammar>(grammar: &G) -> Self {
+        let productive = productive_symbols(grammar);
+        let reachable = reachable_symbols(grammar);
+
+        let mut used: HashSet<Symbol> = HashSet::new();
+        let mut defined: HashSet<Symbol> = HashSet::new();
+        for rule in grammar.rules().iter() {
+            if rule.branches().next().is_some() {
+                defined.insert(rule.start());
+            }
+        }
+        for rule in grammar.terminal_rules().iter() {
+            used.extend(rule.symbols());
+            if rule.symbols().next().is_some() {
+                defined.insert(rule.start());
+            }
+        }
+
+        let non_terminals = grammar.non_terminals();
+        let table = grammar.symbols();
+        GrammarAnalysis {
+            unreachable: sorted(
+                non_terminals
+                    .iter()
+                    .copied()
+                    .filter(|symbol| !reachable.contains(symbol)),
+                table,
+            ),
+            non_productive: sorted(
+                non_terminals
+                    .iter()
+                    .copied()
+                    .filter(|symbol| !productive.contains(symbol)),
+                table,
+            ),
+            unused_terminals: sorted(
+                grammar
+                    .terminals()
+                    .iter()
+                    .copied()
+                    .filter(|symbol| !used.contains(symbol)),
+                table,
+            ),
+            without_rules: sorted(
+                non_terminals
+                    .iter()
+                    .copied()
+                    .filter(|symbol| !defined.contains(symbol)),
+                table,
+            ),
+            symbols: table.clone(),
+        }
+    }
+
+    /// Non-terminals that no derivation of the start symbol reaches.
+    pub fn unreachable(&self) -> &[Symbol] {
+        &self.unreachable
+    }
+
+    /// Non-terminals that derive no terminal string.
+    pub fn non_productive(&self) -> &[Symbol] {
+        &self.non_productive
+    }
+
+    /// Terminals that no terminal rule produces.
+    pub fn unused_terminals(&self) -> &[Symbol] {
+        &self.unused_terminals
+    }
+
+    /// Non-terminals declared without any rule.
+    pub fn without_rules(&self) -> &[Symbol] {
+        &self.without_rules
+    }
+
+    /// Whether every declared symbol can take part in a sentence.
+    pub fn is_reduced(&self) -> bool {
+        self.unreachable.is_empty()
+            && self.non_productive.is_empty()
+            && self.unused_terminals.is_empty()
+            && self.without_rules.is_empty()
+    }
+}
+
+impl fmt::Display for GrammarAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lists = [
+            ("unreachable", &self.unreachable),
+            ("non-productive", &self.non_productive),
+            ("unused terminals", &self.unused_terminals),
+            ("without rules", &self.without_rules),
+        ];
+
+        for (name, symbols) in lists.iter() {
+            write!(f, "{}:", name)?;
+            for symbol in symbols.iter() {
+                write!(f, " {}", symbol.display(&self.symbols))?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Cfg {
+    /// Analyzes the productions as written, since converting them to
+    /// Chomsky normal form drops the symbols the analysis reports.
+    pub fn analyze(&self) -> GrammarAnalysis {
+        let productions = self.productions();
+        let terminals = self.terminals();
+
+        let mut productive: HashSet<Symbol> = HashSet::new();
+        loop {
+            let mut changed = false;
+            for (left, right) in productions {
+                if !productive.contains(left)
+                    && right
+                        .iter()
+                        .all(|symbol| terminals.contains(symbol) || productive.contains(symbol))
+                {
+                    productive.insert(*left);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let mut reachable: HashSet<Symbol> = HashSet::new();
+        let mut stack = vec![self.start()];
+        while let Some(symbol) = stack.pop() {
+            if reachable.insert(symbol) {
+                for (_, right) in productions.iter().filter(|(left, _)| *left == symbol) {
+                    stack.extend(right);
+                }
+            }
+        }
+
+        let used: HashSet<Symbol> = productions
+            .iter()
+            .flat_map(|(_, right)| right.iter().copied())
+            .collect();
+        let defined: HashSet<Symbol> = productions.iter().map(|(left, _)| *left).collect();
+        let non_terminals = self.non_terminals();
+        let table = self.symbols();
+        let select = |symbols: &HashSet<Symbol>, known: &HashSet<Symbol>| {
+            sorted(
+                symbols
+                    .iter()
+                    .copied()
+                    .filter(|symbol| !known.contains(symbol)),
+                table,
+            )
+        };
+
+        GrammarAnalysis {
+            unreachable: select(&non_terminals, &reachable),
+            non_productive: select(&non_terminals, &productive),
+            unused_terminals: select(terminals, &used),
+            without_rules: select(&non_terminals, &defined),
+            symbols: table.clone(),
+        }
+    }
+}
+
+impl CNF {
+    pub fn analyze(&self) -> GrammarAnalysis {
+        GrammarAnalysis::new(self)
+    }
+
+    /// Returns the grammar without its non-productive and unreachable
+    /// symbols, and without the rules that mention them.
+    ///
+    /// The start symbol is always kept, even when its language is empty.
+    pub fn reduce(&self) -> CNF {
+        let productive = productive_symbols(self);
+        let keep = |branch: &&RuleRight| {
+            productive.contains(&branch.left()) && productive.contains(&branch.right())
+        };
+
+        let mut rules = Rules::new();
+        for rule in self.rules().iter() {
+            let branches: HashSet<RuleRight> = rule.branches().filter(keep).copied().collect();
+            if productive.contains(&rule.start()) && !branches.is_empty() {
+                rules.insert(rule.start(), branches);
+            }
+        }
+        let productive_only = CNF::new(
+            self.start_symbol(),
+            self.terminals().clone(),
+            self.non_terminals().clone(),
+            rules,
+            self.terminal_rules().clone(),
+        );
+
+        let reachable = reachable_symbols(&productive_only);
+        let mut rules = Rules::new();
+        for rule in productive_only.rules().iter() {
+            if reachable.contains(&rule.start()) {
+                rules.insert(rule.start(), rule.branches().copied().collect());
+            }
+        }
+        let mut terminal_rules = TerminalRules::new();
+        for rule in productive_only.terminal_rules().iter() {
+            if reachable.contains(&rule.start()) && rule.symbols().next().is_some() {
+                terminal_rules.insert(rule.start(), rule.symbols().copied().collect());
+            }
+        }
+
+        let non_terminals: HashSet<Symbol> = self
+            .non_terminals()
+            .iter()
+            .copied()
+            .filter(|symbol| *symbol == self.start_symbol() || reachable.contains(symbol))
+            .collect();
+        let terminals: HashSet<Symbol> = self
+            .terminals()
+            .iter()
+            .copied()
+            .filter(|symbol| reachable.contains(symbol))
+            .collect();
+
+        CNF::new(
+            self.start_symbol(),
+            terminals,
+            non_terminals,
+            rules,
+            terminal_rules,
+        )
+        .with_symbols(self.symbols().clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    fn untidy_grammar() -> CNF {
+        cnf_grammar! {
+            Start("S");
+            NonTerminals["S", "A", "B", "Loop", "Lost", "Empty"];
+            Terminals["a", "b", "c"];
+            Rules [
+                "S" => [["A", "B"], ["A", "Loop"]],
+                "Loop" => [["Loop", "A"]],
+                "Lost" => [["A", "A"]]
+            ];
+            TerminalRules [
+                "A" => ["a"],
+                "B" => ["b"]
+            ]
+        }
+    }
+
+    #[test]
+    fn analyze_grammar() {
+        let analysis = untidy_grammar().analyze();
+        let names = |symbols: &[Symbol]| -> Vec<&str> {
+            symbols.iter().map(|symbol| symbol.as_str()).collect()
+        };
+
+        assert_eq!(names(analysis.unreachable()), ["Empty", "Lost"]);
+        assert_eq!(names(analysis.non_productive()), ["Empty", "Loop"]);
+        assert_eq!(names(analysis.unused_terminals()), ["c"]);
+        assert_eq!(names(analysis.without_rules()), ["Empty"]);
+        assert!(!analysis.is_reduced());
+        assert_eq!(
+            analysis.to_string(),
+            "unreachable: Empty Lost\n\
+             non-productive: Empty Loop\n\
+             unused terminals: c\n\
+             without rules: Empty\n"
+        );
+    }
+
+    #[test]
+    fn analyze_owned_symbols() {
+        let grammar = GrammarBuilder::new()
+            .start("S")
+            .non_terminals(&["Lost", "Alone"])
+            .rule("S", &["A", "A"])
+            .terminal_rule("A", "a")
+            .build()
+            .unwrap();
+
+        let analysis = grammar.analyze();
+        assert_eq!(
+            analysis.to_string(),
+            "unreachable: Alone Lost\n\
+             non-productive: Alone Lost\n\
+             unused terminals:\n\
+             without rules: Alone Lost\n"
+        );
+
+        let cfg = Cfg::parse("S -> A A | 'b'; A -> 'a'; Lost -> A;").unwrap();
+        let analysis = cfg.analyze();
+        let lost = cfg.symbols().get("Lost").unwrap();
+        assert_eq!(analysis.unreachable(), [lost]);
+        assert_eq!(
+            analysis.to_string().lines().next(),
+            Some("unreachable: Lost")
+        );
+    }
+
+    #[test]
+    fn reduce_grammar() {
+        let grammar = untidy_grammar();
+        let reduced = grammar.reduce();
+
+        assert!(reduced.analyze().is_reduced());
+        assert_eq!(reduced.non_terminals().len(), 3);
+        assert_eq!(reduced.terminals().len(), 2);
+        assert_eq!(reduced.productions().len(), grammar.productions().len() - 3);
+
+        let mut parser = Parser::new(&reduced);
+        assert!(parser.parse("ab").is_ok());
+        assert!(parser.parse("aa").is_err());
+    }
+}
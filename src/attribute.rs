@@ -0,0 +1,654 @@
+use super::{AttributeError, Grammar, NodeId, Production, Symbol, Tree};
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AttrKind {
+    Synthesized,
+    Inherited,
+}
+
+type Compute<V> = Box<dyn Fn(&str, &[V]) -> V>;
+
+/// An attribute at a position of a production: 0 is the left side, 1 and 2
+/// are the children of `A -> B C`.
+type Occurrence<'g> = (usize, &'g str);
+
+/// A relation from inherited to synthesized attributes of one symbol.
+type Relation<'g> = BTreeSet<(&'g str, &'g str)>;
+
+/// An equation applied at a node: the node, the attribute it defines and the
+/// attributes it reads.
+type Instance<'g, V> = (
+    NodeId,
+    (NodeId, &'g str),
+    Vec<(NodeId, &'g str)>,
+    &'g Equation<V>,
+);
+
+struct Equation<V> {
+    target: (usize, String),
+    deps: Vec<(usize, String)>,
+    compute: Compute<V>,
+}
+
+impl<V> Equation<V> {
+    fn target(&self) -> Occurrence<'_> {
+        (self.target.0, &self.target.1)
+    }
+
+    fn deps(&self) -> impl Iterator<Item = Occurrence<'_>> {
+        self.deps
+            .iter()
+            .map(|(position, attribute)| (*position, attribute.as_str()))
+    }
+}
+
+fn symbol_at(production: Production, position: usize) -> Option<Symbol> {
+    match (production, position) {
+        (Production::Binary(left, _, _), 0) | (Production::Terminal(left, _), 0) => Some(left),
+        (Production::Binary(_, first, _), 1) => Some(first),
+        (Production::Binary(_, _, second), 2) => Some(second),
+        _ => None,
+    }
+}
+
+/// Attributes declared on non-terminals, with the equations that define them
+/// in each production.
+///
+/// An equation receives the text matched by the production's left side and
+/// the values of its dependencies, in the order they were given.
+pub struct AttributeGrammar<V> {
+    attributes: HashMap<Symbol, Vec<(String, AttrKind)>>,
+    equations: HashMap<Production, Vec<Equation<V>>>,
+    defaults: HashMap<Symbol, Vec<Equation<V>>>,
+}
+
+impl<V: Clone> Default for AttributeGrammar<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Clone> AttributeGrammar<V> {
+    pub fn new() -> Self {
+        AttributeGrammar {
+            attributes: HashMap::new(),
+            equations: HashMap::new(),
+            defaults: HashMap::new(),
+        }
+    }
+
+    pub fn synthesized(mut self, symbol: Symbol, attribute: &str) -> Self {
+        self.attributes
+            .entry(symbol)
+            .or_default()
+            .push((attribute.to_string(), AttrKind::Synthesized));
+        self
+    }
+
+    pub fn inherited(mut self, symbol: Symbol, attribute: &str) -> Self {
+        self.attributes
+            .entry(symbol)
+            .or_default()
+            .push((attribute.to_string(), AttrKind::Inherited));
+        self
+    }
+
+    /// Adds the equation defining `target` in `production` from `deps`.
+    pub fn equation<F: Fn(&str, &[V]) -> V + 'static>(
+        mut self,
+        production: Production,
+        target: (usize, &str),
+        deps: &[(usize, &str)],
+        compute: F,
+    ) -> Self {
+        let equation = Self::new_equation(target, deps, compute);
+        self.equations.entry(production).or_default().push(equation);
+        self
+    }
+
+    /// Adds an equation for every production of `symbol` that does not define
+    /// `target` itself; it may only refer to position 0, which `check`
+    /// enforces.
+    pub fn default_equation<F: Fn(&str, &[V]) -> V + 'static>(
+        mut self,
+        symbol: Symbol,
+        target: (usize, &str),
+        deps: &[(usize, &str)],
+        compute: F,
+    ) -> Self {
+        let equation = Self::new_equation(target, deps, compute);
+        self.defaults.entry(symbol).or_default().push(equation);
+        self
+    }
+
+    fn new_equation<F: Fn(&str, &[V]) -> V + 'static>(
+        target: (usize, &str),
+        deps: &[(usize, &str)],
+        compute: F,
+    ) -> Equation<V> {
+        Equation {
+            target: (target.0, target.1.to_string()),
+            deps: deps
+                .iter()
+                .map(|&(position, attribute)| (position, attribute.to_string()))
+                .collect(),
+            compute: Box::new(compute),
+        }
+    }
+
+    pub fn kind(&self, symbol: Symbol, attribute: &str) -> Option<AttrKind> {
+        self.attributes
+            .get(&symbol)?
+            .iter()
+            .find(|(name, _)| name == attribute)
+            .map(|&(_, kind)| kind)
+    }
+
+    fn attributes_of(&self, symbol: Symbol, kind: AttrKind) -> impl Iterator<Item = &str> {
+        self.attributes
+            .get(&symbol)
+            .into_iter()
+            .flatten()
+            .filter(move |(_, other)| *other == kind)
+            .map(|(name, _)| name.as_str())
+    }
+
+    fn equations_of(&self, production: Production) -> Vec<&Equation<V>> {
+        let mut equations: Vec<&Equation<V>> = self
+            .equations
+            .get(&production)
+            .into_iter()
+            .flatten()
+            .collect();
+
+        for default in self.defaults.get(&production.left()).into_iter().flatten() {
+            if equations
+                .iter()
+                .all(|equation| equation.target != default.target)
+            {
+                equations.push(default);
+            }
+        }
+
+        equations
+    }
+
+    /// Checks that every production of `grammar` defines exactly the
+    /// attributes it has to, and that no tree can have a circular dependency.
+    pub fn check<G: Grammar>(&self, grammar: &G) -> Result<(), AttributeError> {
+        let productions = grammar.productions();
+
+        for &production in &productions {
+            self.check_production(production)?;
+        }
+
+        self.check_circularity(&productions)
+    }
+
+    fn check_production(&self, production: Production) -> Result<(), AttributeError> {
+        // A default equation is shared by productions of different shapes,
+        // so only the left side is known to be there.
+        for default in self.defaults.get(&production.left()).into_iter().flatten() {
+            let mut occurrences = std::iter::once(default.target()).chain(default.deps());
+            if let Some((position, attribute)) = occurrences.find(|(position, _)| *position != 0) {
+                return Err(AttributeError::InvalidTarget(
+                    production,
+                    position,
+                    attribute.to_string(),
+                ));
+            }
+        }
+
+        let equations = self.equations_of(production);
+
+        let occurrence_kind = |(position, attribute): Occurrence| {
+            let symbol = symbol_at(production, position).ok_or_else(|| {
+                AttributeError::InvalidTarget(production, position, attribute.to_string())
+            })?;
+            self.kind(symbol, attribute)
+                .ok_or_else(|| AttributeError::Undeclared(symbol, attribute.to_string()))
+        };
+
+        for equation in &equations {
+            let (position, attribute) = equation.target();
+            let expected = match position {
+                0 => AttrKind::Synthesized,
+                _ => AttrKind::Inherited,
+            };
+            if occurrence_kind(equation.target())? != expected {
+                return Err(AttributeError::InvalidTarget(
+                    production,
+                    position,
+                    attribute.to_string(),
+                ));
+            }
+            for dep in equation.deps() {
+                occurrence_kind(dep)?;
+            }
+        }
+
+        let mut required: Vec<Occurrence> = self
+            .attributes_of(production.left(), AttrKind::Synthesized)
+            .map(|attribute| (0, attribute))
+            .collect();
+        if let Production::Binary(_, first, second) = production {
+            required.extend(
+                self.attributes_of(first, AttrKind::Inherited)
+                    .map(|attribute| (1, attribute)),
+            );
+            required.extend(
+                self.attributes_of(second, AttrKind::Inherited)
+                    .map(|attribute| (2, attribute)),
+            );
+        }
+
+        for (position, attribute) in required {
+            if equations
+                .iter()
+                .all(|equation| equation.target() != (position, attribute))
+            {
+                return Err(AttributeError::MissingEquation(
+                    production,
+                    position,
+                    attribute.to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Knuth's test: computes, for every symbol, the possible relations its
+    /// subtrees induce from inherited to synthesized attributes, and checks
+    /// every production against every combination of its children's ones.
+    fn check_circularity(&self, productions: &[Production]) -> Result<(), AttributeError> {
+        let mut relations: HashMap<Symbol, HashSet<Relation>> = HashMap::new();
+
+        loop {
+            let mut changed = false;
+
+            for &production in productions {
+                let equations = self.equations_of(production);
+                let children: Vec<Vec<Relation>> = match production {
+                    Production::Binary(_, first, second) => vec![first, second]
+                        .into_iter()
+                        .map(|child| match relations.get(&child) {
+                            Some(found) => found.iter().cloned().collect(),
+                            None => vec![],
+                        })
+                        .collect(),
+                    Production::Terminal(_, _) => vec![],
+                };
+
+                for combination in combinations(&children) {
+                    let mut edges: Vec<(Occurrence, Occurrence)> = Vec::new();
+                    for equation in &equations {
+                        edges.extend(equation.deps().map(|dep| (dep, equation.target())));
+                    }
+                    for (index, relation) in combination.iter().enumerate() {
+                        for &(inherited, synthesized) in relation.iter() {
+                            edges.push(((index + 1, inherited), (index + 1, synthesized)));
+                        }
+                    }
+
+                    let graph = Graph::new(edges);
+                    if graph.has_cycle() {
+                        return Err(AttributeError::Circular(production));
+                    }
+
+                    let left = production.left();
+                    let mut relation: Relation = BTreeSet::new();
+                    for inherited in self.attributes_of(left, AttrKind::Inherited) {
+                        for (position, reached) in graph.reachable((0, inherited)) {
+                            if position == 0
+                                && self.kind(left, reached) == Some(AttrKind::Synthesized)
+                            {
+                                relation.insert((inherited, reached));
+                            }
+                        }
+                    }
+
+                    changed |= relations.entry(left).or_default().insert(relation);
+                }
+            }
+
+            if !changed {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Evaluates every attribute of `tree`, which was parsed from `src`, given
+    /// the inherited attributes of its root.
+    pub fn evaluate(
+        &self,
+        tree: &Tree,
+        src: &str,
+        inherited: &[(&str, V)],
+    ) -> Result<Attributes<V>, AttributeError> {
+        let root = tree.root();
+        let mut values: HashMap<(NodeId, &str), V> = HashMap::new();
+
+        for attribute in self.attributes_of(tree[root].kind(), AttrKind::Inherited) {
+            match inherited.iter().find(|(name, _)| *name == attribute) {
+                Some((_, value)) => {
+                    values.insert((root, attribute), value.clone());
+                }
+                None => return Err(AttributeError::MissingInherited(attribute.to_string())),
+            }
+        }
+
+        // Every equation of every node, waiting on its missing dependencies.
+        let mut instances: Vec<Instance<V>> = Vec::new();
+        for (id, _) in tree.iter() {
+            let production = match tree.production(id) {
+                Some(production) => production,
+                None => continue,
+            };
+            let mut positions = vec![id];
+            positions.extend(tree.children(id));
+
+            for equation in self.equations_of(production) {
+                let target = place(&positions, production, equation.target())?;
+                let deps = equation
+                    .deps()
+                    .map(|dep| place(&positions, production, dep))
+                    .collect::<Result<_, _>>()?;
+                instances.push((id, target, deps, equation));
+            }
+        }
+
+        let mut waiting: HashMap<(NodeId, &str), Vec<usize>> = HashMap::new();
+        let mut missing: Vec<usize> = Vec::new();
+        let mut ready: Vec<usize> = Vec::new();
+        for (index, (_, _, deps, _)) in instances.iter().enumerate() {
+            let mut count = 0;
+            for dep in deps {
+                if !values.contains_key(dep) {
+                    waiting.entry(*dep).or_default().push(index);
+                    count += 1;
+                }
+            }
+            missing.push(count);
+            if count == 0 {
+                ready.push(index);
+            }
+        }
+
+        while let Some(index) = ready.pop() {
+            let (id, target, deps, equation) = &instances[index];
+            let args: Vec<V> = deps.iter().map(|dep| values[dep].clone()).collect();
+            values.insert(*target, (equation.compute)(tree[*id].text(src), &args));
+
+            for &next in waiting.remove(target).iter().flatten() {
+                missing[next] -= 1;
+                if missing[next] == 0 {
+                    ready.push(next);
+                }
+            }
+        }
+
+        if let Some(index) = missing.iter().position(|&count| count > 0) {
+            let (_, (node, attribute), _, _) = instances[index];
+            return Err(AttributeError::Unevaluated(node, attribute.to_string()));
+        }
+
+        let mut attributes = Attributes(HashMap::new());
+        for ((id, attribute), value) in values {
+            attributes
+                .0
+                .entry(id)
+                .or_default()
+                .insert(attribute.to_string(), value);
+        }
+        Ok(attributes)
+    }
+}
+
+/// Finds the node an occurrence refers to, given the nodes at each position.
+fn place<'e>(
+    positions: &[NodeId],
+    production: Production,
+    (position, attribute): Occurrence<'e>,
+) -> Result<(NodeId, &'e str), AttributeError> {
+    positions
+        .get(position)
+        .map(|&node| (node, attribute))
+        .ok_or_else(|| AttributeError::InvalidTarget(production, position, attribute.to_string()))
+}
+
+/// Every way to pick one relation for each child.
+fn combinations<'r, 'g>(children: &'r [Vec<Relation<'g>>]) -> Vec<Vec<&'r Relation<'g>>> {
+    let mut result: Vec<Vec<&Relation>> = vec![vec![]];
+
+    for relations in children {
+        let mut next: Vec<Vec<&Relation>> = Vec::new();
+        for prefix in &result {
+            for relation in relations {
+                let mut combination = prefix.clone();
+                combination.push(relation);
+                next.push(combination);
+            }
+        }
+        result = next;
+    }
+
+    result
+}
+
+struct Graph<'g> {
+    edges: HashMap<Occurrence<'g>, Vec<Occurrence<'g>>>,
+}
+
+impl<'g> Graph<'g> {
+    fn new(edges: Vec<(Occurrence<'g>, Occurrence<'g>)>) -> Self {
+        let mut graph = Graph {
+            edges: HashMap::new(),
+        };
+        for (from, to) in edges {
+            graph.edges.entry(from).or_default().push(to);
+        }
+        graph
+    }
+
+    fn reachable(&self, from: Occurrence<'g>) -> HashSet<Occurrence<'g>> {
+        let mut seen: HashSet<Occurrence> = HashSet::new();
+        let mut stack = vec![from];
+
+        while let Some(node) = stack.pop() {
+            for &next in self.edges.get(&node).into_iter().flatten() {
+                if seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+
+        seen
+    }
+
+    fn has_cycle(&self) -> bool {
+        self.edges
+            .keys()
+            .any(|&node| self.reachable(node).contains(&node))
+    }
+}
+
+/// The attribute values of every node of a tree.
+#[derive(Clone, Debug)]
+pub struct Attributes<V>(HashMap<NodeId, HashMap<String, V>>);
+
+impl<V> Attributes<V> {
+    pub fn get(&self, id: NodeId, attribute: &str) -> Option<&V> {
+        self.0.get(&id)?.get(attribute)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    // Knuth's binary numerals: N -> L . L, L -> L B | B, B -> 0 | 1.
+    fn binary_grammar() -> CNF {
+        cnf_grammar! {
+            Start("N");
+            NonTerminals["N", "F", "L", "B", "Dot"];
+            Terminals["0", "1", "."];
+            Rules [
+                "N" => [["L", "F"]],
+                "F" => [["Dot", "L"]],
+                "L" => [["L", "B"]]
+            ];
+            TerminalRules [
+                "L" => ["0", "1"],
+                "B" => ["0", "1"],
+                "Dot" => ["."]
+            ]
+        }
+    }
+
+    fn bit(text: &str, scale: f64) -> f64 {
+        text.parse::<f64>().unwrap() * 2f64.powf(scale)
+    }
+
+    fn binary_attributes() -> AttributeGrammar<f64> {
+        let s = Symbol::intern;
+        let (n, f, l, b, dot) = (s("N"), s("F"), s("L"), s("B"), s("Dot"));
+
+        AttributeGrammar::new()
+            .synthesized(n, "val")
+            .synthesized(f, "val")
+            .inherited(l, "scale")
+            .synthesized(l, "val")
+            .synthesized(l, "len")
+            .inherited(b, "scale")
+            .synthesized(b, "val")
+            .equation(Production::Binary(n, l, f), (1, "scale"), &[], |_, _| 0.0)
+            .equation(
+                Production::Binary(n, l, f),
+                (0, "val"),
+                &[(1, "val"), (2, "val")],
+                |_, v| v[0] + v[1],
+            )
+            .equation(
+                Production::Binary(f, dot, l),
+                (2, "scale"),
+                &[(2, "len")],
+                |_, v| -v[0],
+            )
+            .equation(
+                Production::Binary(f, dot, l),
+                (0, "val"),
+                &[(2, "val")],
+                |_, v| v[0],
+            )
+            .equation(
+                Production::Binary(l, l, b),
+                (1, "scale"),
+                &[(0, "scale")],
+                |_, v| v[0] + 1.0,
+            )
+            .equation(
+                Production::Binary(l, l, b),
+                (2, "scale"),
+                &[(0, "scale")],
+                |_, v| v[0],
+            )
+            .equation(
+                Production::Binary(l, l, b),
+                (0, "val"),
+                &[(1, "val"), (2, "val")],
+                |_, v| v[0] + v[1],
+            )
+            .equation(
+                Production::Binary(l, l, b),
+                (0, "len"),
+                &[(1, "len")],
+                |_, v| v[0] + 1.0,
+            )
+            .default_equation(l, (0, "val"), &[(0, "scale")], |text, v| bit(text, v[0]))
+            .default_equation(l, (0, "len"), &[], |_, _| 1.0)
+            .default_equation(b, (0, "val"), &[(0, "scale")], |text, v| bit(text, v[0]))
+    }
+
+    #[test]
+    fn evaluate_binary_numeral() {
+        let grammar = binary_grammar();
+        let attributes = binary_attributes();
+        assert_eq!(attributes.check(&grammar), Ok(()));
+
+        let src = "1101.01";
+        let mut parser = Parser::new(&grammar);
+        let tree = parser.parse(src).unwrap();
+        let values = attributes.evaluate(&tree, src, &[]).unwrap();
+
+        assert_eq!(values.get(tree.root(), "val"), Some(&13.25));
+        let fraction = tree.find_all(Symbol::intern("F"))[0];
+        assert_eq!(values.get(fraction, "val"), Some(&0.25));
+
+        let incomplete = AttributeGrammar::<f64>::new().synthesized(Symbol::intern("N"), "val");
+        assert!(matches!(
+            incomplete.check(&grammar),
+            Err(AttributeError::MissingEquation(_, 0, _))
+        ));
+
+        let (l, b) = (Symbol::intern("L"), Symbol::intern("B"));
+        let misplaced = binary_attributes().default_equation(l, (2, "scale"), &[], |_, _| 0.0);
+        assert_eq!(
+            misplaced.check(&grammar),
+            Err(AttributeError::InvalidTarget(
+                Production::Binary(l, l, b),
+                2,
+                "scale".to_string()
+            ))
+        );
+        let reads_child =
+            binary_attributes().default_equation(b, (0, "val"), &[(1, "val")], |_, v| v[0]);
+        assert!(matches!(
+            reads_child.check(&grammar),
+            Err(AttributeError::InvalidTarget(_, 1, _))
+        ));
+    }
+
+    #[test]
+    fn detect_circularity() {
+        let grammar = cnf_grammar! {
+            Start("S");
+            NonTerminals["S", "A", "B"];
+            Terminals["a", "b"];
+            Rules [
+                "S" => [["A", "B"]]
+            ];
+            TerminalRules [
+                "A" => ["a"],
+                "B" => ["b"]
+            ]
+        };
+        let s = Symbol::intern;
+        let (start, a, b) = (s("S"), s("A"), s("B"));
+        let production = Production::Binary(start, a, b);
+
+        let attributes = AttributeGrammar::<i32>::new()
+            .inherited(a, "i")
+            .synthesized(a, "s")
+            .inherited(b, "i")
+            .synthesized(b, "s")
+            .equation(production, (1, "i"), &[(2, "s")], |_, v| v[0])
+            .equation(production, (2, "i"), &[(1, "s")], |_, v| v[0])
+            .default_equation(a, (0, "s"), &[(0, "i")], |_, v| v[0])
+            .default_equation(b, (0, "s"), &[(0, "i")], |_, v| v[0]);
+
+        assert_eq!(
+            attributes.check(&grammar),
+            Err(AttributeError::Circular(production))
+        );
+
+        let src = "ab";
+        let tree = Parser::new(&grammar).parse(src).unwrap();
+        assert!(matches!(
+            attributes.evaluate(&tree, src, &[]),
+            Err(AttributeError::Unevaluated(_, _))
+        ));
+    }
+}
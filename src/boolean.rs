@@ -0,0 +1,265 @@
+use super::cfg::{read_grammar, resolve_rules, Binarizer, RawRule, Token};
+use super::{
+    DisplaySymbols, Grammar, HashSet, Production, ReadError, Rules, Symbol, SymbolTable,
+    TerminalRules, CNF,
+};
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// One conjunct of a Boolean rule: a pair of non-terminals the span must,
+/// or must not, split into.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum Conjunct {
+    /// `B C`
+    Positive(Symbol, Symbol),
+    /// `!B C`
+    Negative(Symbol, Symbol),
+}
+
+impl DisplaySymbols for Conjunct {
+    fn fmt_symbols(&self, symbols: &SymbolTable, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (negated, first, second) = match *self {
+            Conjunct::Positive(first, second) => ("", first, second),
+            Conjunct::Negative(first, second) => ("!", first, second),
+        };
+        write!(
+            f,
+            "{}{} {}",
+            negated,
+            first.display(symbols),
+            second.display(symbols)
+        )
+    }
+}
+
+/// A Boolean grammar in binary normal form: every rule is `A -> t`, or a
+/// conjunction `A -> B C & D E & !F G` of pairs, at least one of them not
+/// negated. A span derives `A` when it splits into every positive pair and
+/// into none of the negated ones, each over a split of its own, which lets
+/// the grammar express intersections and complements of context-free
+/// languages.
+///
+/// Its text form extends the one of `Cfg` with `&` between conjuncts and
+/// `!` before a negated one. Longer conjuncts and terminals inside them are
+/// split as `Cfg::to_cnf` does, through `A_1`, `A_2`, ... and `<t>`.
+///
+/// ```text
+/// # a^n b^n c^n
+/// S -> A B & D C;
+/// A -> 'a' A | 'a';
+/// B -> 'b' B 'c' | 'b' 'c';
+/// C -> 'c' C | 'c';
+/// D -> 'a' D 'b' | 'a' 'b';
+/// ```
+///
+/// `Parser` checks the conjuncts while filling the chart, and its trees
+/// follow the first positive conjunct of each rule. The other tools see
+/// only these first conjuncts, a context-free grammar whose language
+/// contains this one.
+#[derive(Debug, Clone)]
+pub struct BooleanGrammar {
+    cnf: CNF,
+    /// The other conjuncts of the rules, by their first positive one.
+    conjuncts: HashMap<Production, Vec<Vec<Conjunct>>>,
+}
+
+/// A conjunct as written: whether it is negated, its symbols and its
+/// offset.
+type RawConjunct = (bool, Vec<Symbol>, usize);
+
+impl BooleanGrammar {
+    /// Reads a grammar in the text form described on `BooleanGrammar`.
+    pub fn parse(src: &str) -> Result<BooleanGrammar, ReadError> {
+        let (start, rules) = read_grammar(src)?;
+
+        let defined: HashSet<&str> = rules.iter().map(|rule| rule.left.as_str()).collect();
+
+        let mut terminals: HashSet<Symbol> = HashSet::new();
+        let mut symbols = SymbolTable::new();
+        let mut alternatives: Vec<(Symbol, usize, Vec<RawConjunct>)> = Vec::new();
+        for rule in &rules {
+            for alternative in &rule.alternatives {
+                let mut conjuncts: Vec<RawConjunct> = Vec::new();
+                for part in alternative.split(|(token, _)| *token == Token::And) {
+                    let offset = part.first().map_or(rule.offset, |&(_, offset)| offset);
+                    let (negated, part) = match part.first() {
+                        Some((Token::Not, _)) => (true, &part[1..]),
+                        _ => (false, part),
+                    };
+                    if let Some((_, offset)) = part.iter().find(|(token, _)| *token == Token::Not) {
+                        return Err(ReadError::new("`!` must start a conjunct", *offset));
+                    }
+
+                    let conjunct = RawRule {
+                        left: rule.left.clone(),
+                        offset: rule.offset,
+                        features: rule.features.clone(),
+                        alternatives: vec![part.to_vec()],
+                    };
+                    let (_, right) =
+                        resolve_rules(&[conjunct], &defined, &mut terminals, &mut symbols)?
+                            .remove(0);
+                    conjuncts.push((negated, right, offset));
+                }
+                alternatives.push((symbols.intern(&rule.left), rule.offset, conjuncts));
+            }
+        }
+
+        let names = defined.iter().map(|name| symbols.intern(name)).collect();
+        let start = symbols.intern(&start);
+        let mut binarizer = Binarizer::new(symbols, names, terminals.clone());
+        let mut conjuncts: HashMap<Production, Vec<Vec<Conjunct>>> = HashMap::new();
+        for (left, offset, alternative) in alternatives {
+            if let [(false, right, _)] = &alternative[..] {
+                if let [terminal] = right[..] {
+                    if terminals.contains(&terminal) {
+                        binarizer.terminal(left, terminal);
+                        continue;
+                    }
+                }
+            }
+
+            let mut resolved: Vec<Conjunct> = Vec::new();
+            for (negated, right, offset) in alternative {
+                if right.len() < 2 {
+                    return Err(ReadError::new(
+                        "a conjunct needs at least two symbols",
+                        offset,
+                    ));
+                }
+
+                let (first, second) = binarizer.pair(left, &right);
+                resolved.push(if negated {
+                    Conjunct::Negative(first, second)
+                } else {
+                    Conjunct::Positive(first, second)
+                });
+            }
+
+            let position = resolved
+                .iter()
+                .position(|conjunct| matches!(conjunct, Conjunct::Positive(..)))
+                .ok_or_else(|| ReadError::new("a rule needs a conjunct without `!`", offset))?;
+            if let Conjunct::Positive(first, second) = resolved.remove(position) {
+                binarizer.binary(left, first, second);
+                conjuncts
+                    .entry(Production::Binary(left, first, second))
+                    .or_default()
+                    .push(resolved);
+            }
+        }
+
+        Ok(BooleanGrammar {
+            cnf: binarizer.build(start),
+            conjuncts,
+        })
+    }
+}
+
+impl Grammar for BooleanGrammar {
+    fn start_symbol(&self) -> Symbol {
+        self.cnf.start_symbol()
+    }
+
+    fn exist(&self, symbol: Symbol) -> bool {
+        self.cnf.exist(symbol)
+    }
+
+    fn first(&self, symbol: Symbol) -> Option<HashSet<Symbol>> {
+        self.cnf.first(symbol)
+    }
+
+    fn follow(&self, symbol: Symbol) -> Option<HashSet<Symbol>> {
+        self.cnf.follow(symbol)
+    }
+
+    fn derive(&self, base: Symbol, suffix: Symbol) -> Option<HashSet<Symbol>> {
+        self.cnf.derive(base, suffix)
+    }
+
+    fn derive_single(&self, base: Symbol) -> Option<HashSet<Symbol>> {
+        self.cnf.derive_single(base)
+    }
+
+    fn is_terminal(&self, input: Symbol) -> bool {
+        self.cnf.is_terminal(input)
+    }
+
+    fn is_non_terminal(&self, input: Symbol) -> bool {
+        self.cnf.is_non_terminal(input)
+    }
+
+    fn terminals(&self) -> &HashSet<Symbol> {
+        self.cnf.terminals()
+    }
+
+    fn non_terminals(&self) -> &HashSet<Symbol> {
+        self.cnf.non_terminals()
+    }
+
+    fn rules(&self) -> &Rules {
+        self.cnf.rules()
+    }
+
+    fn terminal_rules(&self) -> &TerminalRules {
+        self.cnf.terminal_rules()
+    }
+
+    fn symbols(&self) -> &SymbolTable {
+        self.cnf.symbols()
+    }
+
+    fn conjuncts(&self, production: Production) -> Option<&[Vec<Conjunct>]> {
+        self.conjuncts.get(&production).map(Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn parse_conjunctions() {
+        let grammar = BooleanGrammar::parse(
+            "S -> A B & D C;
+             A -> 'a' A | 'a';
+             B -> 'b' B 'c' | 'b' 'c';
+             C -> 'c' C | 'c';
+             D -> 'a' D 'b' | 'a' 'b';",
+        )
+        .unwrap();
+        let mut parser = Parser::new(&grammar);
+        for sentence in ["abc", "aabbcc", "aaabbbccc"] {
+            assert!(parser.parse(sentence).is_ok(), "{}", sentence);
+        }
+        for sentence in ["aabbc", "abbcc", "aabcc", "ab"] {
+            assert!(parser.parse(sentence).is_err(), "{}", sentence);
+        }
+        assert_eq!(
+            parser.parse("abc").unwrap().to_bracketed(grammar.symbols()),
+            "(S (A a) (B (<b> b) (<c> c)))"
+        );
+
+        assert!(Cfg::parse("S -> A B & D C;").is_err());
+    }
+
+    #[test]
+    fn parse_negations() {
+        let grammar =
+            BooleanGrammar::parse("S -> X X & !'a' X; X -> 'a' X | 'b' X | 'a' | 'b';").unwrap();
+        let mut parser = Parser::new(&grammar);
+        assert!(parser.parse("ba").is_ok());
+        assert!(parser.parse("bab").is_ok());
+        assert!(parser.parse("ab").is_err());
+        assert!(parser.parse("b").is_err());
+
+        let error = BooleanGrammar::parse("S -> !X X; X -> 'a';").unwrap_err();
+        assert_eq!(error.message(), "a rule needs a conjunct without `!`");
+        let error = BooleanGrammar::parse("S -> X & X X; X -> 'a';").unwrap_err();
+        assert_eq!(
+            (error.message(), error.offset()),
+            ("a conjunct needs at least two symbols", 5)
+        );
+    }
+}
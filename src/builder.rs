@@ -0,0 +1,358 @@
+use super::{
+    Cfg, GrammarError, HashSet, RuleRight, Rules, Symbol, SymbolTable, TerminalRules, CNF,
+};
+
+use std::collections::HashMap;
+
+/// Builds a `CNF` from names known only at runtime, reporting mistakes as
+/// `GrammarError` instead of panicking like `cnf_grammar!`.
+///
+/// Left sides of rules are non-terminals and the right sides of terminal
+/// rules are terminals, so declaring symbols is only needed for those that
+/// no rule mentions.
+#[derive(Clone, Debug, Default)]
+pub struct GrammarBuilder {
+    start: Option<String>,
+    non_terminals: Vec<String>,
+    terminals: Vec<String>,
+    rules: Vec<(String, Vec<String>)>,
+    symbols: Option<SymbolTable>,
+}
+
+fn rule_text(left: &str, right: &[String]) -> String {
+    if right.is_empty() {
+        format!("{} -> ε", left)
+    } else {
+        format!("{} -> {}", left, right.join(" "))
+    }
+}
+
+fn push_new<'b>(names: &mut Vec<&'b str>, name: &'b str) {
+    if !names.contains(&name) {
+        names.push(name)
+    }
+}
+
+impl GrammarBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interns the grammar's names in `symbols`, which defaults to a new
+    /// table of the grammar's own. Pass `SymbolTable::global()` to share the
+    /// process-wide table with `Symbol::intern`.
+    pub fn with_symbols(mut self, symbols: SymbolTable) -> Self {
+        self.symbols = Some(symbols);
+        self
+    }
+
+    pub fn start(mut self, start: &str) -> Self {
+        self.start = Some(start.to_string());
+        self
+    }
+
+    pub fn non_terminals(mut self, names: &[&str]) -> Self {
+        self.non_terminals
+            .extend(names.iter().map(|name| name.to_string()));
+        self
+    }
+
+    pub fn terminals(mut self, names: &[&str]) -> Self {
+        self.terminals
+            .extend(names.iter().map(|name| name.to_string()));
+        self
+    }
+
+    /// Adds `left -> right`, which must be `A -> B C` or `A -> t` once the
+    /// grammar is built.
+    pub fn rule(mut self, left: &str, right: &[&str]) -> Self {
+        self.rules.push((
+            left.to_string(),
+            right.iter().map(|name| name.to_string()).collect(),
+        ));
+        self
+    }
+
+    /// Adds `left -> terminal` and declares `terminal`.
+    pub fn terminal_rule(mut self, left: &str, terminal: &str) -> Self {
+        self.terminals.push(terminal.to_string());
+        self.rule(left, &[terminal])
+    }
+
+    pub fn build(self) -> Result<CNF, GrammarError> {
+        let start = self.start.ok_or(GrammarError::MissingStart)?;
+
+        let mut non_terminals: Vec<&str> = Vec::new();
+        let mut terminals: Vec<&str> = Vec::new();
+        for name in &self.non_terminals {
+            push_new(&mut non_terminals, name.as_str());
+        }
+        for (left, _) in &self.rules {
+            push_new(&mut non_terminals, left.as_str());
+        }
+        for name in &self.terminals {
+            push_new(&mut terminals, name.as_str());
+        }
+
+        if let Some(name) = terminals.iter().find(|name| non_terminals.contains(name)) {
+            return Err(GrammarError::Clash(name.to_string()));
+        }
+        if terminals.contains(&start.as_str()) {
+            return Err(GrammarError::Clash(start));
+        }
+        if !non_terminals.contains(&start.as_str()) {
+            return Err(GrammarError::Undefined(start));
+        }
+
+        let mut seen: HashSet<(&str, &[String])> = HashSet::new();
+        for (left, right) in &self.rules {
+            if let Some(name) = right.iter().find(|name| {
+                !non_terminals.contains(&name.as_str()) && !terminals.contains(&name.as_str())
+            }) {
+                return Err(GrammarError::Undefined(name.to_string()));
+            }
+
+            let binary = right.len() == 2
+                && right
+                    .iter()
+                    .all(|name| non_terminals.contains(&name.as_str()));
+            let terminal = right.len() == 1 && terminals.contains(&right[0].as_str());
+            if !binary && !terminal {
+                return Err(GrammarError::NotCnf(rule_text(left, right)));
+            }
+
+            if !seen.insert((left.as_str(), right.as_slice())) {
+                return Err(GrammarError::DuplicateRule(rule_text(left, right)));
+            }
+        }
+
+        let mut table = self.symbols.unwrap_or_default();
+        let mut intern = |name: &str| table.intern(name);
+
+        let start = intern(&start);
+        let non_terminal_set: HashSet<Symbol> =
+            non_terminals.iter().map(|name| intern(name)).collect();
+        let terminal_set: HashSet<Symbol> = terminals.iter().map(|name| intern(name)).collect();
+
+        let mut order: Vec<Symbol> = Vec::new();
+        let mut binary: HashMap<Symbol, HashSet<RuleRight>> = HashMap::new();
+        let mut single: HashMap<Symbol, HashSet<Symbol>> = HashMap::new();
+        for (left, right) in &self.rules {
+            let left = intern(left);
+            if !order.contains(&left) {
+                order.push(left);
+            }
+
+            match right.as_slice() {
+                [first, second] => {
+                    let branch = RuleRight::new(intern(first), intern(second));
+                    binary.entry(left).or_default().insert(branch);
+                }
+                [terminal] => {
+                    let terminal = intern(terminal);
+                    single.entry(left).or_default().insert(terminal);
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        let mut rules = Rules::new();
+        let mut terminal_rules = TerminalRules::new();
+        for left in order {
+            if let Some(branches) = binary.remove(&left) {
+                rules.insert(left, branches);
+            }
+            if let Some(symbols) = single.remove(&left) {
+                terminal_rules.insert(left, symbols);
+            }
+        }
+
+        Ok(
+            CNF::new(start, terminal_set, non_terminal_set, rules, terminal_rules)
+                .with_symbols(table),
+        )
+    }
+}
+
+/// The tables `grammar!` emits: every name once, the non-terminals first,
+/// and the productions as indexes into the names, which are also the ids
+/// of the symbols in the grammar's own table.
+#[doc(hidden)]
+pub struct GrammarTables {
+    pub names: &'static [&'static str],
+    pub non_terminals: usize,
+    pub start: u32,
+    pub productions: &'static [(u32, &'static [u32])],
+}
+
+impl GrammarTables {
+    /// Puts the grammar together in a table of its own. A grammar that is
+    /// not in Chomsky normal form is converted by `Cfg::to_cnf`, which adds
+    /// the names it makes up to that table.
+    pub fn build(&self) -> CNF {
+        let terminal = |index: &u32| *index as usize >= self.non_terminals;
+        let cnf = self.productions.iter().all(|(_, right)| match right {
+            [single] => terminal(single),
+            [first, second] => !terminal(first) && !terminal(second),
+            _ => false,
+        });
+        let mut table = SymbolTable::new();
+        for name in self.names {
+            table.intern(name);
+        }
+        let symbol = |index: &u32| Symbol::new(*index);
+        let indexes = |range: std::ops::Range<usize>| range.map(|index| Symbol::new(index as u32));
+
+        if !cnf {
+            let productions = self
+                .productions
+                .iter()
+                .map(|(left, right)| (symbol(left), right.iter().map(symbol).collect()))
+                .collect();
+            let terminals = indexes(self.non_terminals..self.names.len()).collect();
+            let cfg = Cfg::new(symbol(&self.start), terminals, productions).with_symbols(table);
+            return cfg
+                .to_cnf()
+                .expect("`grammar!` keeps terminals out of the left sides");
+        }
+
+        let mut order: Vec<Symbol> = Vec::new();
+        let mut binary: HashMap<Symbol, HashSet<RuleRight>> = HashMap::new();
+        let mut single: HashMap<Symbol, HashSet<Symbol>> = HashMap::new();
+        for (left, right) in self.productions {
+            let left = symbol(left);
+            if !order.contains(&left) {
+                order.push(left);
+            }
+            match right {
+                [first, second] => {
+                    let branch = RuleRight::new(symbol(first), symbol(second));
+                    binary.entry(left).or_default().insert(branch);
+                }
+                _ => {
+                    single.entry(left).or_default().insert(symbol(&right[0]));
+                }
+            }
+        }
+
+        let mut rules = Rules::new();
+        let mut terminal_rules = TerminalRules::new();
+        for left in order {
+            if let Some(branches) = binary.remove(&left) {
+                rules.insert(left, branches);
+            }
+            if let Some(symbols) = single.remove(&left) {
+                terminal_rules.insert(left, symbols);
+            }
+        }
+
+        CNF::new(
+            symbol(&self.start),
+            indexes(self.non_terminals..self.names.len()).collect(),
+            indexes(0..self.non_terminals).collect(),
+            rules,
+            terminal_rules,
+        )
+        .with_symbols(table)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    fn ab() -> GrammarBuilder {
+        GrammarBuilder::new()
+            .start("S")
+            .rule("S", &["A", "B"])
+            .terminal_rule("A", "a")
+            .terminal_rule("B", "b")
+    }
+
+    #[test]
+    fn build_grammar() {
+        let grammar = ab().terminals(&["c"]).build().unwrap();
+        assert_eq!(grammar.terminals().len(), 3);
+        assert_eq!(grammar.non_terminals().len(), 3);
+        assert!(Parser::new(&grammar).parse("ab").is_ok());
+
+        let owned = ab().with_symbols(SymbolTable::new()).build().unwrap();
+        assert_eq!(owned.symbols().len(), 5);
+        let tree = Parser::new(&owned).parse("ab").unwrap();
+        assert_eq!(owned.symbols().resolve(tree[tree.root()].kind()), Some("S"));
+    }
+
+    #[test]
+    fn reject_grammars() {
+        let error = |builder: GrammarBuilder| builder.build().unwrap_err();
+
+        assert_eq!(
+            error(GrammarBuilder::new().terminal_rule("A", "a")),
+            GrammarError::MissingStart
+        );
+        assert_eq!(
+            error(ab().start("T")),
+            GrammarError::Undefined("T".to_string())
+        );
+        assert_eq!(error(ab().start("a")), GrammarError::Clash("a".to_string()));
+        assert_eq!(
+            error(ab().rule("S", &["A", "C"])),
+            GrammarError::Undefined("C".to_string())
+        );
+        assert_eq!(
+            error(ab().terminal_rule("A", "B")),
+            GrammarError::Clash("B".to_string())
+        );
+        assert_eq!(
+            error(ab().rule("S", &["A", "b"])),
+            GrammarError::NotCnf("S -> A b".to_string())
+        );
+        assert_eq!(
+            error(ab().rule("S", &["A"])),
+            GrammarError::NotCnf("S -> A".to_string())
+        );
+        assert_eq!(
+            error(ab().rule("S", &[])),
+            GrammarError::NotCnf("S -> ε".to_string())
+        );
+        assert_eq!(
+            error(ab().terminal_rule("B", "b")),
+            GrammarError::DuplicateRule("B -> b".to_string())
+        );
+    }
+
+    #[test]
+    fn validate_grammars() {
+        let grammar = ab().build().unwrap();
+        assert_eq!(grammar.validate(), Ok(()));
+
+        let symbols = grammar.symbols().clone();
+        let get = |name| symbols.get(name).unwrap();
+        let (s, a, t) = (get("S"), get("A"), get("a"));
+        let mut rules = Rules::new();
+        for _ in 0..2 {
+            rules.insert(s, vec![RuleRight::new(a, a)].into_iter().collect());
+        }
+        let build = |start| {
+            CNF::new(
+                start,
+                vec![t].into_iter().collect(),
+                vec![s, a].into_iter().collect(),
+                rules.clone(),
+                TerminalRules::new(),
+            )
+            .with_symbols(symbols.clone())
+        };
+        assert_eq!(
+            build(s).validate(),
+            Err(GrammarError::DuplicateRule("S -> A A".to_string()))
+        );
+        assert_eq!(
+            build(t).validate(),
+            Err(GrammarError::Clash("a".to_string()))
+        );
+
+        let error: Box<dyn std::error::Error> = Box::new(build(s).validate().unwrap_err());
+        assert_eq!(error.to_string(), "rule S -> A A is given twice");
+    }
+}
@@ -0,0 +1,927 @@
+use super::{
+    Grammar, GrammarBuilder, GrammarError, GrammarSets, HashSet, Production, ReadError, RuleRight,
+    Rules, Symbol, SymbolTable, TerminalRules, CNF,
+};
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// A context-free grammar whose right sides may have any length.
+///
+/// Its text form is the one of the `grammar!` macro: names are
+/// non-terminals, quoted strings are terminals (one per char), an empty
+/// alternative or `ε` is the empty string, `#` starts a comment, and an
+/// optional `start S;` picks the start symbol, which otherwise is the left
+/// side of the first rule. A name may also be any single char between `<`
+/// and `>`, such as `<;>`, as `to_cnf` names the symbols it adds for
+/// terminals.
+///
+/// Like a `CNF`, a grammar read from text keeps its names in a table of
+/// its own, which the grammars it converts to share.
+///
+/// ```text
+/// start Sum;
+/// Sum -> Sum '+' Digit | Digit;
+/// Digit -> '0' | '1';
+/// ```
+#[derive(Clone, Debug)]
+pub struct Cfg {
+    start: Symbol,
+    terminals: HashSet<Symbol>,
+    productions: Vec<(Symbol, Vec<Symbol>)>,
+    symbols: SymbolTable,
+}
+
+/// Grammars are equal when they have the same productions, in the same
+/// order, over the same names, whatever the ids of their symbols.
+impl PartialEq for Cfg {
+    fn eq(&self, other: &Cfg) -> bool {
+        let names = |cfg: &Cfg, symbols: &[Symbol]| -> Vec<String> {
+            symbols
+                .iter()
+                .map(|&symbol| cfg.name(symbol).to_string())
+                .collect()
+        };
+        let terminals = |cfg: &Cfg| -> HashSet<String> {
+            cfg.terminals
+                .iter()
+                .map(|&symbol| cfg.name(symbol).to_string())
+                .collect()
+        };
+
+        self.name(self.start) == other.name(other.start)
+            && terminals(self) == terminals(other)
+            && self.productions.len() == other.productions.len()
+            && self.productions.iter().zip(&other.productions).all(
+                |((left, right), (other_left, other_right))| {
+                    self.name(*left) == other.name(*other_left)
+                        && names(self, right) == names(other, other_right)
+                },
+            )
+    }
+}
+
+impl Eq for Cfg {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Token {
+    Name(String),
+    Terminal(char),
+    Epsilon,
+    Arrow,
+    Bar,
+    Semi,
+    /// `&`, between the conjuncts of a `BooleanGrammar` rule.
+    And,
+    /// `!`, before a negated conjunct.
+    Not,
+    /// `[...]`, the features of a symbol in a `FeatureGrammar` rule.
+    Features(String),
+}
+
+pub(crate) type Alternative = Vec<(Token, usize)>;
+
+fn tokenize(src: &str) -> Result<Vec<(Token, usize)>, ReadError> {
+    let mut tokens: Vec<(Token, usize)> = Vec::new();
+    let mut chars = src.char_indices().peekable();
+
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            _ if c.is_whitespace() => {}
+            '#' => {
+                while chars.peek().is_some_and(|&(_, c)| c != '\n') {
+                    chars.next();
+                }
+            }
+            '|' => tokens.push((Token::Bar, offset)),
+            ';' => tokens.push((Token::Semi, offset)),
+            '&' => tokens.push((Token::And, offset)),
+            '!' => tokens.push((Token::Not, offset)),
+            'ε' => tokens.push((Token::Epsilon, offset)),
+            '[' => {
+                let mut features = String::new();
+                let mut closed = false;
+                for (_, next) in chars.by_ref() {
+                    if next == ']' {
+                        closed = true;
+                        break;
+                    }
+                    features.push(next);
+                }
+                if !closed {
+                    return Err(ReadError::new("unterminated features", offset));
+                }
+                tokens.push((Token::Features(features), offset));
+            }
+            '-' if chars.peek().map(|&(_, c)| c) == Some('>') => {
+                chars.next();
+                tokens.push((Token::Arrow, offset));
+            }
+            '\'' | '"' => {
+                let mut closed = false;
+                while let Some((offset, next)) = chars.next() {
+                    let value = match next {
+                        _ if next == c => {
+                            closed = true;
+                            break;
+                        }
+                        '\\' => match chars.next() {
+                            Some((_, 'n')) => '\n',
+                            Some((_, 't')) => '\t',
+                            Some((_, escaped)) => escaped,
+                            None => break,
+                        },
+                        _ => next,
+                    };
+                    tokens.push((Token::Terminal(value), offset));
+                }
+                if !closed {
+                    return Err(ReadError::new("unterminated terminal", offset));
+                }
+            }
+            _ => {
+                let mut name = c.to_string();
+                let mut bracketed = src[offset + c.len_utf8()..].chars();
+                if let (Some(inner), Some('>')) = (bracketed.next(), bracketed.next()) {
+                    if c == '<' {
+                        name.push(inner);
+                        name.push('>');
+                        chars.nth(1);
+                    }
+                }
+                while let Some(&(_, next)) = chars.peek() {
+                    if next.is_whitespace() || "|;&![#'\"".contains(next) || next == 'ε' {
+                        break;
+                    }
+                    if next == '-' && src[offset + name.len()..].starts_with("->") {
+                        break;
+                    }
+                    name.push(next);
+                    chars.next();
+                }
+                tokens.push((Token::Name(name), offset));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// A rule as written, before its names are resolved.
+pub(crate) struct RawRule {
+    pub(crate) left: String,
+    pub(crate) offset: usize,
+    /// The features of the left side and their offset.
+    pub(crate) features: Option<(String, usize)>,
+    pub(crate) alternatives: Vec<Alternative>,
+}
+
+pub(crate) type StartName = Option<(String, usize)>;
+
+/// Splits the text form into rules, checking only its syntax.
+pub(crate) fn read_rules(src: &str) -> Result<(StartName, Vec<RawRule>), ReadError> {
+    let tokens = tokenize(src)?;
+    let mut at = 0;
+    let end = src.len();
+    let offset = |at: usize| tokens.get(at).map_or(end, |&(_, offset)| offset);
+
+    let mut start = None;
+    if let (Some((Token::Name(keyword), _)), Some((Token::Name(name), _))) =
+        (tokens.first(), tokens.get(1))
+    {
+        if keyword == "start" {
+            start = Some((name.clone(), offset(1)));
+            if tokens.get(2).map(|(token, _)| token) != Some(&Token::Semi) {
+                return Err(ReadError::new("expected `;`", offset(2)));
+            }
+            at = 3;
+        }
+    }
+
+    let mut rules: Vec<RawRule> = Vec::new();
+    while at < tokens.len() {
+        let (left, left_offset) = match &tokens[at] {
+            (Token::Name(name), offset) => (name.clone(), *offset),
+            (_, offset) => return Err(ReadError::new("expected a rule", *offset)),
+        };
+        at += 1;
+        let mut features = None;
+        if let Some((Token::Features(text), offset)) = tokens.get(at) {
+            features = Some((text.clone(), *offset));
+            at += 1;
+        }
+        if tokens.get(at).map(|(token, _)| token) != Some(&Token::Arrow) {
+            return Err(ReadError::new("expected `->`", offset(at)));
+        }
+        at += 1;
+
+        let mut alternatives = vec![Vec::new()];
+        loop {
+            match tokens.get(at) {
+                Some((Token::Semi, _)) => break,
+                Some((Token::Bar, _)) => alternatives.push(Vec::new()),
+                Some((Token::Arrow, offset)) => {
+                    return Err(ReadError::new("expected `;`", *offset))
+                }
+                Some(token) => alternatives.last_mut().unwrap().push(token.clone()),
+                None => return Err(ReadError::new("expected `;`", end)),
+            }
+            at += 1;
+        }
+        at += 1;
+
+        rules.push(RawRule {
+            left,
+            offset: left_offset,
+            features,
+            alternatives,
+        });
+    }
+
+    Ok((start, rules))
+}
+
+/// Splits the text form of a whole grammar into rules, and picks its start
+/// symbol.
+pub(crate) fn read_grammar(src: &str) -> Result<(String, Vec<RawRule>), ReadError> {
+    let (start, rules) = read_rules(src)?;
+
+    let (start, start_offset) = match start {
+        Some(start) => start,
+        None => match rules.first() {
+            Some(rule) => (rule.left.clone(), 0),
+            None => return Err(ReadError::new("the grammar has no rules", 0)),
+        },
+    };
+    if !rules.iter().any(|rule| rule.left == start) {
+        return Err(ReadError::new(
+            "the start symbol has no rules",
+            start_offset,
+        ));
+    }
+
+    Ok((start, rules))
+}
+
+/// Turns rules into productions, given the names of every non-terminal,
+/// interning their names in `symbols`. The terminals met are added to
+/// `terminals`.
+pub(crate) fn resolve_rules(
+    rules: &[RawRule],
+    defined: &HashSet<&str>,
+    terminals: &mut HashSet<Symbol>,
+    symbols: &mut SymbolTable,
+) -> Result<Vec<(Symbol, Vec<Symbol>)>, ReadError> {
+    let mut productions: Vec<(Symbol, Vec<Symbol>)> = Vec::new();
+    for rule in rules {
+        if let Some((_, offset)) = rule.features {
+            return Err(ReadError::new("features need a feature grammar", offset));
+        }
+        for alternative in &rule.alternatives {
+            let mut right: Vec<Symbol> = Vec::new();
+            for (token, offset) in alternative {
+                match token {
+                    Token::Name(name) if !defined.contains(name.as_str()) => {
+                        return Err(ReadError::new("the non-terminal has no rules", *offset))
+                    }
+                    Token::Name(name) => right.push(symbols.intern(name)),
+                    Token::Terminal(c) => {
+                        if defined.contains(c.encode_utf8(&mut [0; 4]) as &str) {
+                            return Err(ReadError::new(
+                                "the terminal is also a non-terminal",
+                                *offset,
+                            ));
+                        }
+                        let terminal = symbols.intern(c.encode_utf8(&mut [0; 4]));
+                        terminals.insert(terminal);
+                        right.push(terminal);
+                    }
+                    Token::Features(_) => {
+                        return Err(ReadError::new("features need a feature grammar", *offset))
+                    }
+                    Token::And | Token::Not => {
+                        return Err(ReadError::new(
+                            "conjunctions need a Boolean grammar",
+                            *offset,
+                        ))
+                    }
+                    _ => {}
+                }
+            }
+            productions.push((symbols.intern(&rule.left), right));
+        }
+    }
+
+    Ok(productions)
+}
+
+fn write_symbol(f: &mut fmt::Formatter<'_>, name: &str, terminal: bool) -> fmt::Result {
+    if !terminal {
+        return write!(f, "{}", name);
+    }
+
+    write!(f, "'")?;
+    for c in name.chars() {
+        match c {
+            '\'' | '\\' => write!(f, "\\{}", c)?,
+            '\n' => write!(f, "\\n")?,
+            '\t' => write!(f, "\\t")?,
+            _ => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "'")
+}
+
+/// Interns `name`, with as many `_` appended as it takes for it not to be
+/// one of `names`, and adds it to them.
+fn fresh_name(symbols: &mut SymbolTable, names: &mut HashSet<Symbol>, mut name: String) -> Symbol {
+    while symbols
+        .get(&name)
+        .is_some_and(|symbol| names.contains(&symbol))
+    {
+        name.push('_');
+    }
+    let symbol = symbols.intern(&name);
+    names.insert(symbol);
+    symbol
+}
+
+impl Cfg {
+    /// Makes a grammar of symbols from the process-wide table; use
+    /// `with_symbols` for symbols from another one.
+    pub fn new(
+        start: Symbol,
+        terminals: HashSet<Symbol>,
+        productions: Vec<(Symbol, Vec<Symbol>)>,
+    ) -> Self {
+        Cfg {
+            start,
+            terminals,
+            productions,
+            symbols: SymbolTable::global(),
+        }
+    }
+
+    /// Replaces the table the grammar's symbols are resolved through.
+    pub fn with_symbols(mut self, symbols: SymbolTable) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
+    /// Reads a grammar in the text form described on `Cfg`.
+    pub fn parse(src: &str) -> Result<Cfg, ReadError> {
+        let (start, rules) = read_grammar(src)?;
+
+        let defined: HashSet<&str> = rules.iter().map(|rule| rule.left.as_str()).collect();
+        let mut terminals: HashSet<Symbol> = HashSet::new();
+        let mut symbols = SymbolTable::new();
+        let productions = resolve_rules(&rules, &defined, &mut terminals, &mut symbols)?;
+        let start = symbols.intern(&start);
+        Ok(Cfg::new(start, terminals, productions).with_symbols(symbols))
+    }
+
+    /// Reads rules in the text form and adds the productions that are not in
+    /// the grammar yet, returning how many were added. Names may refer to
+    /// the non-terminals of the grammar, and a `start S;` moves the start
+    /// symbol.
+    pub fn add_rules(&mut self, src: &str) -> Result<usize, ReadError> {
+        let (start, rules) = read_rules(src)?;
+
+        let known: Vec<String> = self
+            .non_terminals()
+            .into_iter()
+            .map(|symbol| self.name(symbol).to_string())
+            .collect();
+        let mut defined: HashSet<&str> = known.iter().map(String::as_str).collect();
+        for rule in &rules {
+            let symbol = self.symbols.get(&rule.left);
+            if symbol.is_some_and(|symbol| self.terminals.contains(&symbol)) {
+                return Err(ReadError::new(
+                    "the non-terminal is also a terminal",
+                    rule.offset,
+                ));
+            }
+            defined.insert(&rule.left);
+        }
+        if let Some((start, offset)) = &start {
+            if !defined.contains(start.as_str()) {
+                return Err(ReadError::new("the start symbol has no rules", *offset));
+            }
+        }
+
+        let mut symbols = self.symbols.clone();
+        let mut terminals = self.terminals.clone();
+        let productions = resolve_rules(&rules, &defined, &mut terminals, &mut symbols)?;
+        if let Some((start, _)) = start {
+            self.start = symbols.intern(&start);
+        }
+        self.symbols = symbols;
+        self.terminals = terminals;
+
+        let mut added = 0;
+        for production in productions {
+            if !self.productions.contains(&production) {
+                self.productions.push(production);
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Reads rules in the text form and removes their productions from the
+    /// grammar, returning how many were found.
+    pub fn remove_rules(&mut self, src: &str) -> Result<usize, ReadError> {
+        let (_, rules) = read_rules(src)?;
+
+        let known: Vec<String> = self
+            .non_terminals()
+            .into_iter()
+            .map(|symbol| self.name(symbol).to_string())
+            .collect();
+        let mut defined: HashSet<&str> = known.iter().map(String::as_str).collect();
+        defined.extend(rules.iter().map(|rule| rule.left.as_str()));
+        // Names the grammar does not have match none of its productions, so
+        // they are interned in a copy of the table.
+        let mut symbols = self.symbols.clone();
+        let productions = resolve_rules(&rules, &defined, &mut HashSet::new(), &mut symbols)?;
+
+        let before = self.productions.len();
+        self.productions
+            .retain(|production| !productions.contains(production));
+        Ok(before - self.productions.len())
+    }
+
+    /// Copies the productions of any grammar, those of the start symbol
+    /// first and the rest sorted by name. The copy shares the table of a
+    /// grammar that has one of its own, so its symbols keep their ids, and
+    /// copies the names of any other into a new table.
+    pub fn from_grammar<G: Grammar>(grammar: &G) -> Cfg {
+        let table = grammar.symbols();
+        let name = |symbol: &Symbol| table.resolve(*symbol).unwrap_or("");
+
+        let start = grammar.start_symbol();
+        let mut productions: Vec<(Symbol, Vec<Symbol>)> = grammar
+            .productions()
+            .into_iter()
+            .map(|production| match production {
+                Production::Binary(left, first, second) => (left, vec![first, second]),
+                Production::Terminal(left, terminal) => (left, vec![terminal]),
+            })
+            .collect();
+        productions.sort_by_cached_key(|(left, right)| {
+            let right: Vec<&str> = right.iter().map(name).collect();
+            (*left != start, name(left), right)
+        });
+
+        if !table.is_global() {
+            let terminals = grammar.terminals().clone();
+            return Cfg::new(start, terminals, productions).with_symbols(table.clone());
+        }
+
+        let mut symbols = SymbolTable::new();
+        let mut own = |symbol: Symbol| symbols.intern(name(&symbol));
+        let start = own(start);
+        let productions = productions
+            .into_iter()
+            .map(|(left, right)| (own(left), right.into_iter().map(&mut own).collect()))
+            .collect();
+        let terminals = grammar.terminals().iter().map(|&symbol| own(symbol));
+        let terminals = terminals.collect();
+        Cfg::new(start, terminals, productions).with_symbols(symbols)
+    }
+
+    pub fn start(&self) -> Symbol {
+        self.start
+    }
+
+    pub fn terminals(&self) -> &HashSet<Symbol> {
+        &self.terminals
+    }
+
+    pub fn non_terminals(&self) -> HashSet<Symbol> {
+        let mut result: HashSet<Symbol> = self.productions.iter().map(|(left, _)| *left).collect();
+        result.insert(self.start);
+        result
+    }
+
+    pub fn productions(&self) -> &[(Symbol, Vec<Symbol>)] {
+        &self.productions
+    }
+
+    /// The table the grammar's symbols are resolved through.
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    fn name(&self, symbol: Symbol) -> &str {
+        self.symbols.resolve(symbol).unwrap_or("")
+    }
+
+    pub fn sets(&self) -> GrammarSets {
+        GrammarSets::from_productions(self.start, &self.terminals, self.productions.clone())
+    }
+
+    /// Whether every production is `A -> B C` or `A -> t`.
+    pub fn is_cnf(&self) -> bool {
+        self.productions
+            .iter()
+            .all(|(_, right)| self.is_cnf_right(right))
+    }
+
+    fn is_cnf_right(&self, right: &[Symbol]) -> bool {
+        match right {
+            [terminal] => self.terminals.contains(terminal),
+            [first, second] => !self.terminals.contains(first) && !self.terminals.contains(second),
+            _ => false,
+        }
+    }
+
+    /// Returns the grammar as a `CNF` sharing its table, failing if some
+    /// production is not in Chomsky normal form already. Every symbol that
+    /// is not a terminal is a non-terminal, whether it has rules or not.
+    pub fn as_cnf(&self) -> Result<CNF, GrammarError> {
+        let mut non_terminals: Vec<&str> = vec![self.name(self.start)];
+        for (left, right) in &self.productions {
+            for &symbol in Some(left).into_iter().chain(right) {
+                let name = self.name(symbol);
+                if !self.terminals.contains(&symbol) && !non_terminals.contains(&name) {
+                    non_terminals.push(name);
+                }
+            }
+        }
+
+        let mut builder = GrammarBuilder::new()
+            .with_symbols(self.symbols.clone())
+            .start(self.name(self.start))
+            .non_terminals(&non_terminals);
+        for (left, right) in &self.productions {
+            let left = self.name(*left);
+            if right.len() == 1 && self.terminals.contains(&right[0]) {
+                builder = builder.terminal_rule(left, self.name(right[0]));
+            } else {
+                let right: Vec<&str> = right.iter().map(|&symbol| self.name(symbol)).collect();
+                builder = builder.rule(left, &right);
+            }
+        }
+
+        builder.build()
+    }
+
+    /// Converts the grammar to Chomsky normal form, then drops the useless
+    /// symbols. The result shares a copy of the grammar's table, with the
+    /// names it adds.
+    ///
+    /// Terminals inside longer right sides get a non-terminal `<t>` of their
+    /// own and right sides longer than two are split through `A_1`, `A_2`,
+    /// ...; names that are taken get a `_` appended. A CNF grammar cannot
+    /// derive the empty string, so the result accepts the same language
+    /// without it. Fails only if a terminal also has productions.
+    pub fn to_cnf(&self) -> Result<CNF, GrammarError> {
+        let mut symbols = self.symbols.clone();
+        let mut names: HashSet<Symbol> = self.non_terminals();
+        names.extend(&self.terminals);
+
+        let mut productions: Vec<(Symbol, Vec<Symbol>)> = Vec::new();
+        let mut term: HashMap<Symbol, Symbol> = HashMap::new();
+        let mut parts: HashMap<Symbol, usize> = HashMap::new();
+        for (left, right) in &self.productions {
+            let mut right = right.clone();
+            if right.len() >= 2 {
+                for symbol in right.iter_mut() {
+                    if self.terminals.contains(symbol) {
+                        let terminal = *symbol;
+                        *symbol = *term.entry(terminal).or_insert_with(|| {
+                            let name = format!("<{}>", self.name(terminal));
+                            let name = fresh_name(&mut symbols, &mut names, name);
+                            productions.push((name, vec![terminal]));
+                            name
+                        });
+                    }
+                }
+            }
+
+            let mut left = *left;
+            while right.len() > 2 {
+                let part = parts.entry(left).or_insert(0);
+                *part += 1;
+                let name = format!("{}_{}", symbols.resolve(left).unwrap_or(""), part);
+                let next = fresh_name(&mut symbols, &mut names, name);
+                productions.push((left, vec![right.remove(0), next]));
+                left = next;
+            }
+            productions.push((left, right));
+        }
+
+        let sets = GrammarSets::from_productions(self.start, &self.terminals, productions.clone());
+        let mut shortened: Vec<(Symbol, Vec<Symbol>)> = Vec::new();
+        for (left, right) in productions {
+            if let [first, second] = right[..] {
+                if sets.is_nullable(first) {
+                    shortened.push((left, vec![second]));
+                }
+                if sets.is_nullable(second) {
+                    shortened.push((left, vec![first]));
+                }
+            }
+            if !right.is_empty() {
+                shortened.push((left, right));
+            }
+        }
+
+        let is_unit = |right: &[Symbol]| right.len() == 1 && !self.terminals.contains(&right[0]);
+        let mut units: HashMap<Symbol, Vec<Symbol>> = HashMap::new();
+        for (left, right) in &shortened {
+            if is_unit(right) {
+                units.entry(*left).or_default().push(right[0]);
+            }
+        }
+
+        let mut lefts: Vec<Symbol> = Vec::new();
+        for (left, _) in &shortened {
+            if !lefts.contains(left) {
+                lefts.push(*left);
+            }
+        }
+
+        let mut result: Vec<(Symbol, Vec<Symbol>)> = Vec::new();
+        for &left in &lefts {
+            let mut reached = vec![left];
+            let mut at = 0;
+            while at < reached.len() {
+                let nexts = units.get(&reached[at]).cloned().unwrap_or_default();
+                for next in nexts {
+                    if !reached.contains(&next) {
+                        reached.push(next);
+                    }
+                }
+                at += 1;
+            }
+
+            for &symbol in &reached {
+                for (other, right) in &shortened {
+                    let production = (left, right.clone());
+                    if *other == symbol && !is_unit(right) && !result.contains(&production) {
+                        result.push(production);
+                    }
+                }
+            }
+        }
+
+        let converted = Cfg::new(self.start, self.terminals.clone(), result).with_symbols(symbols);
+        Ok(converted.as_cnf()?.reduce())
+    }
+}
+
+impl fmt::Display for Cfg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "start {};", self.name(self.start))?;
+
+        let mut lefts: Vec<Symbol> = Vec::new();
+        for (left, _) in &self.productions {
+            if !lefts.contains(left) {
+                lefts.push(*left);
+            }
+        }
+
+        for left in lefts {
+            write!(f, "{} ->", self.name(left))?;
+            let alternatives = self.productions.iter().filter(|(other, _)| *other == left);
+            for (index, (_, right)) in alternatives.enumerate() {
+                if index > 0 {
+                    write!(f, " |")?;
+                }
+                if right.is_empty() {
+                    write!(f, " ε")?;
+                }
+                for &symbol in right {
+                    write!(f, " ")?;
+                    write_symbol(f, self.name(symbol), self.terminals.contains(&symbol))?;
+                }
+            }
+            writeln!(f, ";")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits right sides into pairs the way `Cfg::to_cnf` does, for the
+/// grammars whose rules carry more than a production.
+pub(crate) struct Binarizer {
+    symbols: SymbolTable,
+    names: HashSet<Symbol>,
+    terminals: HashSet<Symbol>,
+    term: HashMap<Symbol, Symbol>,
+    parts: HashMap<Symbol, usize>,
+    binary: Vec<(Symbol, Symbol, Symbol)>,
+    singles: Vec<(Symbol, Symbol)>,
+}
+
+impl Binarizer {
+    /// Starts with `names` taken by the grammar's non-terminals, whose
+    /// names are in `symbols`.
+    pub(crate) fn new(
+        symbols: SymbolTable,
+        mut names: HashSet<Symbol>,
+        terminals: HashSet<Symbol>,
+    ) -> Self {
+        names.extend(&terminals);
+        Binarizer {
+            symbols,
+            names,
+            terminals,
+            term: HashMap::new(),
+            parts: HashMap::new(),
+            binary: Vec::new(),
+            singles: Vec::new(),
+        }
+    }
+
+    fn fresh(&mut self, name: String) -> Symbol {
+        fresh_name(&mut self.symbols, &mut self.names, name)
+    }
+
+    fn part(&mut self, left: Symbol) -> Symbol {
+        let part = self.parts.entry(left).or_insert(0);
+        *part += 1;
+        let name = format!("{}_{}", self.symbols.resolve(left).unwrap_or(""), part);
+        self.fresh(name)
+    }
+
+    /// Returns the pair of non-terminals the right side `right` of `left`,
+    /// of two symbols or more, stands for, adding the rules of the `<t>`
+    /// and `A_1`, `A_2`, ... it needs.
+    pub(crate) fn pair(&mut self, left: Symbol, right: &[Symbol]) -> (Symbol, Symbol) {
+        let mut right: Vec<Symbol> = right
+            .iter()
+            .map(|&symbol| {
+                if !self.terminals.contains(&symbol) {
+                    return symbol;
+                }
+                if let Some(&name) = self.term.get(&symbol) {
+                    return name;
+                }
+                let name = format!("<{}>", self.symbols.resolve(symbol).unwrap_or(""));
+                let name = self.fresh(name);
+                self.term.insert(symbol, name);
+                self.singles.push((name, symbol));
+                name
+            })
+            .collect();
+
+        let first = right.remove(0);
+        if let [second] = right[..] {
+            return (first, second);
+        }
+
+        let head = self.part(left);
+        let mut rest = head;
+        while right.len() > 2 {
+            let next = self.part(left);
+            self.binary.push((rest, right.remove(0), next));
+            rest = next;
+        }
+        self.binary.push((rest, right[0], right[1]));
+        (first, head)
+    }
+
+    pub(crate) fn binary(&mut self, left: Symbol, first: Symbol, second: Symbol) {
+        self.binary.push((left, first, second));
+    }
+
+    pub(crate) fn terminal(&mut self, left: Symbol, terminal: Symbol) {
+        self.singles.push((left, terminal));
+    }
+
+    /// Builds the CNF of the rules collected.
+    pub(crate) fn build(self, start: Symbol) -> CNF {
+        let mut non_terminals: HashSet<Symbol> = HashSet::new();
+        non_terminals.insert(start);
+
+        let mut grouped: HashMap<Symbol, HashSet<RuleRight>> = HashMap::new();
+        for (left, first, second) in self.binary {
+            non_terminals.insert(left);
+            grouped
+                .entry(left)
+                .or_default()
+                .insert(RuleRight::new(first, second));
+        }
+        let mut rules = Rules::new();
+        for (left, right) in grouped {
+            rules.insert(left, right);
+        }
+
+        let mut grouped: HashMap<Symbol, HashSet<Symbol>> = HashMap::new();
+        for (left, terminal) in self.singles {
+            non_terminals.insert(left);
+            grouped.entry(left).or_default().insert(terminal);
+        }
+        let mut terminal_rules = TerminalRules::new();
+        for (left, right) in grouped {
+            terminal_rules.insert(left, right);
+        }
+
+        CNF::new(start, self.terminals, non_terminals, rules, terminal_rules)
+            .with_symbols(self.symbols)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    const SUM: &str = "
+        # sums of binary digits, possibly empty
+        start Sum;
+        Sum -> Sum '+' Digit | Digit | ;
+        Digit -> \"0\" | '1';
+    ";
+
+    #[test]
+    fn read_and_print() {
+        let grammar = Cfg::parse(SUM).unwrap();
+        assert_eq!(grammar.productions().len(), 5);
+        assert!(!grammar.is_cnf());
+        assert_eq!(
+            grammar.to_string(),
+            "start Sum;\nSum -> Sum '+' Digit | Digit | ε;\nDigit -> '0' | '1';\n"
+        );
+        assert_eq!(Cfg::parse(&grammar.to_string()), Ok(grammar));
+
+        let error = |src| Cfg::parse(src).unwrap_err().offset();
+        assert_eq!(error("S -> A;"), 5);
+        assert_eq!(error("S -> 'a'"), 8);
+        assert_eq!(error("S -> 'a"), 5);
+        assert_eq!(error("start T; S -> 'a';"), 6);
+
+        let accented = Cfg::parse("É -> 'a' Ü; Ü -> 'ü';").unwrap();
+        assert_eq!(accented.to_string(), "start É;\nÉ -> 'a' Ü;\nÜ -> 'ü';\n");
+        assert_eq!(error("É -> Ö;"), 6);
+    }
+
+    #[test]
+    fn edit_rules() {
+        let mut grammar = Cfg::parse(SUM).unwrap();
+        assert_eq!(grammar.add_rules("Digit -> '1' | '2';"), Ok(1));
+        assert_eq!(
+            grammar.add_rules("start List; List -> Sum ',' List | Sum;"),
+            Ok(2)
+        );
+        assert_eq!(grammar.symbols().resolve(grammar.start()), Some("List"));
+        assert_eq!(
+            grammar.add_rules("Sum -> Missing;").unwrap_err().offset(),
+            7
+        );
+        assert_eq!(grammar.add_rules("+ -> Sum;").unwrap_err().offset(), 0);
+
+        assert_eq!(grammar.remove_rules("Sum -> ; Digit -> '0' | '9';"), Ok(2));
+        assert_eq!(grammar.productions().len(), 6);
+    }
+
+    #[test]
+    fn convert_to_cnf() {
+        let grammar = Cfg::parse(SUM).unwrap().to_cnf().unwrap();
+        assert!(grammar.analyze().is_reduced());
+
+        let mut parser = Parser::new(&grammar);
+        for (src, accepted) in &[("1", true), ("0+1+1", true), ("+1", true), ("1+", false)] {
+            assert_eq!(parser.parse(src).is_ok(), *accepted, "{}", src);
+        }
+
+        let cnf = Cfg::from_grammar(&grammar);
+        assert!(cnf.is_cnf());
+        assert_eq!(
+            cnf.as_cnf().unwrap().productions().len(),
+            grammar.productions().len()
+        );
+    }
+
+    #[test]
+    fn convert_in_own_table() {
+        let grammar = Cfg::parse("Unshared -> 'a' 'b' 'c' | ;").unwrap();
+        let converted = grammar.to_cnf().unwrap();
+        assert!(converted.symbols().get("Unshared_1").is_some());
+        assert!(converted.symbols().get("<a>").is_some());
+        for name in &["Unshared", "Unshared_1", "<a>"] {
+            assert_eq!(SymbolTable::global().get(name), None);
+        }
+
+        let mut symbols = SymbolTable::new();
+        let (s, a) = (symbols.intern("S"), symbols.intern("a"));
+        let productions = vec![(s, vec![a, a]), (a, vec![a])];
+        let clash = Cfg::new(s, vec![a].into_iter().collect(), productions).with_symbols(symbols);
+        assert_eq!(
+            clash.to_cnf().unwrap_err(),
+            GrammarError::Clash("a".to_string())
+        );
+    }
+
+    #[test]
+    fn reread_converted() {
+        let grammar = Cfg::parse("S -> 'a' ';' S '-' | '>' ' ' '<' | '#' \"'\" S;").unwrap();
+        let converted = Cfg::from_grammar(&grammar.to_cnf().unwrap());
+        let text = converted.to_string();
+        assert!(text.contains("<;> -> ';';") && text.contains("<'> -> '\\'';"));
+        assert_eq!(Cfg::parse(&text), Ok(converted));
+    }
+}
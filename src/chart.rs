@@ -0,0 +1,621 @@
+use super::{
+    DisplaySymbols, Features, Grammar, HashSet, ParseObserver, Parser, Span, Symbol, SymbolTable,
+    Tree,
+};
+
+use std::cmp::{Ord, Ordering};
+use std::collections::HashMap;
+use std::fmt::{Debug, Write};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// How an entry of the chart was derived.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BackPointer {
+    /// `A -> t`, where `t` is the terminal matched by the cell's char.
+    Terminal(Symbol),
+    /// `A -> B C`, with `B` found in the first span and `C` in the second.
+    Binary(Span, Symbol, Span, Symbol),
+}
+
+/// A symbol found for a span, together with the way it was derived and,
+/// for a `FeatureGrammar`, its features. Since it may carry features, an
+/// entry is `Clone` but no longer `Copy`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Entry {
+    kind: Symbol,
+    back_pointer: BackPointer,
+    features: Option<Arc<Features>>,
+}
+
+impl Entry {
+    pub fn new(kind: Symbol, back_pointer: BackPointer) -> Self {
+        Entry {
+            kind,
+            back_pointer,
+            features: None,
+        }
+    }
+
+    pub fn with_features(mut self, features: Features) -> Self {
+        self.features = Some(Arc::new(features));
+        self
+    }
+
+    pub fn features(&self) -> Option<&Features> {
+        self.features.as_deref()
+    }
+
+    pub fn kind(&self) -> Symbol {
+        self.kind
+    }
+
+    pub fn back_pointer(&self) -> BackPointer {
+        self.back_pointer
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cell(pub Vec<Entry>, Span);
+
+impl Ord for Cell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.span().unwrap().cmp(&other.span().unwrap())
+    }
+}
+
+impl PartialOrd for Cell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Cell {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Cell {}
+
+#[macro_export]
+macro_rules! cell {
+    (;$span:expr) => (
+        Cell::new(Vec::new(), $span)
+    );
+    ($elem:expr; $n:expr; $span:expr) => (
+        Cell::new(vec::from_elem($elem, $n))
+    );
+    ($($x:expr),+ $(,)?; $span:expr) => (
+        <Cell>::new([$($x),+].to_vec(), $span)
+    );
+}
+
+impl Cell {
+    pub fn new(entries: Vec<Entry>, span: Span) -> Self {
+        Cell(entries, span)
+    }
+
+    pub fn nth(&self, n: usize) -> Option<&Entry> {
+        self.0.get(n)
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.1)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn append(&mut self, mut another: Cell) {
+        self.0.append(another.0.as_mut())
+    }
+
+    pub fn append_entries(&mut self, mut entries: Vec<Entry>) {
+        self.0.append(&mut entries)
+    }
+
+    pub fn push_entry(&mut self, entry: Entry) {
+        self.0.push(entry)
+    }
+
+    /// Returns the preferred derivation of `symbol` in this cell.
+    pub fn has(&self, symbol: Symbol) -> Option<&Entry> {
+        self.0.iter().find(|entry| entry.kind.eq(&symbol))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.0.iter()
+    }
+
+    /// Returns the distinct features `symbol` was found with in this cell,
+    /// in order.
+    pub fn features_of(&self, symbol: Symbol) -> Vec<Option<&Features>> {
+        let mut seen: HashSet<Option<&Features>> = HashSet::new();
+        self.0
+            .iter()
+            .filter(|entry| entry.kind == symbol && seen.insert(entry.features()))
+            .map(Entry::features)
+            .collect()
+    }
+
+    /// Returns the distinct symbols found in this cell, in order.
+    pub fn symbols(&self) -> Vec<Symbol> {
+        let mut seen: HashSet<Symbol> = HashSet::new();
+        self.0
+            .iter()
+            .map(Entry::kind)
+            .filter(|symbol| seen.insert(*symbol))
+            .collect()
+    }
+}
+
+/// A parse tree sharing its subtrees with others, built while listing the
+/// trees of a chart.
+enum Shape {
+    Terminal(Symbol, Span, Symbol),
+    Binary(Symbol, Span, Rc<Shape>, Rc<Shape>),
+}
+
+impl Shape {
+    fn root(&self) -> (Symbol, Span) {
+        match self {
+            Shape::Terminal(kind, span, _) | Shape::Binary(kind, span, _, _) => (*kind, *span),
+        }
+    }
+
+    fn to_tree(&self) -> Tree {
+        let (kind, span) = self.root();
+        let mut tree = Tree::new(kind, span);
+        let mut stack = vec![(tree.root(), self)];
+
+        while let Some((id, shape)) = stack.pop() {
+            match shape {
+                Shape::Terminal(_, span, terminal) => {
+                    tree.push(id, *terminal, *span);
+                }
+                Shape::Binary(_, _, left, right) => {
+                    for child in [left, right] {
+                        let (kind, span) = child.root();
+                        let child_id = tree.push(id, kind, span);
+                        stack.push((child_id, child.as_ref()));
+                    }
+                }
+            }
+        }
+
+        tree
+    }
+}
+
+/// A read-only view of the chart filled by the last `Parser` run.
+#[derive(Clone, Debug)]
+pub struct Chart<'p> {
+    chars: &'p [char],
+    offsets: &'p [usize],
+    cells: &'p HashMap<Span, Cell>,
+    start: Symbol,
+    symbols: &'p SymbolTable,
+}
+
+impl<'a, G: Grammar + Debug + Clone, O: ParseObserver> Parser<'a, G, O> {
+    pub fn chart(&self) -> Chart<'_> {
+        Chart {
+            chars: &self.chars,
+            offsets: &self.offsets,
+            cells: &self.slices,
+            start: self.grammar.start_symbol(),
+            symbols: self.grammar.symbols(),
+        }
+    }
+}
+
+impl<'p> Chart<'p> {
+    /// The number of chars of the input.
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    /// Returns the span of `len` chars starting at char `start`.
+    pub fn span(&self, start: usize, len: usize) -> Span {
+        let byte_start = self.offsets[start];
+        Span::with_bytes(
+            start,
+            len,
+            byte_start,
+            self.offsets[start + len] - byte_start,
+        )
+    }
+
+    pub fn cell(&self, start: usize, len: usize) -> Option<&'p Cell> {
+        if len == 0 || start + len > self.len() {
+            return None;
+        }
+        self.cells.get(&self.span(start, len))
+    }
+
+    /// Iterates over the non-empty cells, shortest spans first.
+    pub fn iter(&self) -> impl Iterator<Item = &'p Cell> + '_ {
+        (1..=self.len()).flat_map(move |len| {
+            (0..=self.len() - len).filter_map(move |start| self.cell(start, len))
+        })
+    }
+
+    /// Returns the entries on the derivation of the start symbol over the
+    /// whole input, or nothing if the input was rejected.
+    pub fn used(&self) -> HashSet<(Span, Symbol)> {
+        let mut used: HashSet<(Span, Symbol)> = HashSet::new();
+        if self.is_empty() {
+            return used;
+        }
+
+        let mut stack = vec![(self.span(0, self.len()), self.start)];
+        while let Some((span, symbol)) = stack.pop() {
+            let entry = match self.cells.get(&span).and_then(|cell| cell.has(symbol)) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            used.insert((span, symbol));
+            if let BackPointer::Binary(left_span, left, right_span, right) = entry.back_pointer() {
+                stack.push((left_span, left));
+                stack.push((right_span, right));
+            }
+        }
+
+        used
+    }
+
+    /// Counts the parse trees of the start symbol over the whole input,
+    /// saturating at `u64::MAX`. More than one means the input is ambiguous.
+    pub fn count_trees(&self) -> u64 {
+        if self.is_empty() {
+            return 0;
+        }
+
+        let mut counts: HashMap<(Span, Symbol), u64> = HashMap::new();
+        for cell in self.iter() {
+            for entry in cell.iter() {
+                let count = match entry.back_pointer() {
+                    BackPointer::Terminal(_) => 1,
+                    BackPointer::Binary(left_span, left, right_span, right) => {
+                        let left = counts.get(&(left_span, left)).copied().unwrap_or(0);
+                        let right = counts.get(&(right_span, right)).copied().unwrap_or(0);
+                        left.saturating_mul(right)
+                    }
+                };
+                let total = counts.entry((cell.1, entry.kind())).or_insert(0);
+                *total = total.saturating_add(count);
+            }
+        }
+
+        let span = self.span(0, self.len());
+        counts.get(&(span, self.start)).copied().unwrap_or(0)
+    }
+
+    /// Returns up to `limit` distinct parse trees of the start symbol over
+    /// the whole input, the one `Parser::parse` returns first.
+    pub fn trees(&self, limit: usize) -> Vec<Tree> {
+        if self.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        let mut shapes: HashMap<(Span, Symbol), Vec<Rc<Shape>>> = HashMap::new();
+        for cell in self.iter() {
+            for entry in cell.iter() {
+                let key = (cell.1, entry.kind());
+                let mut found = shapes.remove(&key).unwrap_or_default();
+                match entry.back_pointer() {
+                    BackPointer::Terminal(terminal) => {
+                        found.push(Rc::new(Shape::Terminal(entry.kind(), cell.1, terminal)))
+                    }
+                    BackPointer::Binary(left_span, left, right_span, right) => {
+                        let none = Vec::new();
+                        let lefts = shapes.get(&(left_span, left)).unwrap_or(&none);
+                        let rights = shapes.get(&(right_span, right)).unwrap_or(&none);
+                        for first in lefts {
+                            for second in rights {
+                                if found.len() >= limit {
+                                    break;
+                                }
+                                found.push(Rc::new(Shape::Binary(
+                                    entry.kind(),
+                                    cell.1,
+                                    first.clone(),
+                                    second.clone(),
+                                )));
+                            }
+                        }
+                    }
+                }
+                found.truncate(limit);
+                shapes.insert(key, found);
+            }
+        }
+
+        let span = self.span(0, self.len());
+        shapes
+            .remove(&(span, self.start))
+            .unwrap_or_default()
+            .iter()
+            .map(|shape| shape.to_tree())
+            .collect()
+    }
+
+    fn name(&self, symbol: Symbol) -> String {
+        symbol.display(self.symbols).to_string()
+    }
+
+    /// The symbols of a cell, with `*` after the ones in `used`.
+    fn labels(
+        &self,
+        start: usize,
+        len: usize,
+        used: &HashSet<(Span, Symbol)>,
+    ) -> Vec<(String, bool)> {
+        let span = self.span(start, len);
+        self.cell(start, len)
+            .map(|cell| cell.symbols())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|symbol| (self.name(symbol), used.contains(&(span, symbol))))
+            .collect()
+    }
+
+    /// Draws the triangular table, longest span on top and the input at the
+    /// bottom. Symbols on the final derivation are marked with `*`.
+    pub fn to_text(&self) -> String {
+        let used = self.used();
+        let mut rows: Vec<(String, Vec<String>)> = Vec::new();
+
+        for len in (1..=self.len()).rev() {
+            let cells = (0..=self.len() - len)
+                .map(|start| {
+                    let labels = self.labels(start, len, &used);
+                    if labels.is_empty() {
+                        return "-".to_string();
+                    }
+                    labels
+                        .into_iter()
+                        .map(|(name, used)| if used { name + "*" } else { name })
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect();
+            rows.push((len.to_string(), cells));
+        }
+        rows.push((
+            String::new(),
+            self.chars
+                .iter()
+                .map(|c| c.escape_debug().to_string())
+                .collect(),
+        ));
+
+        let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+        let width = rows
+            .iter()
+            .flat_map(|(_, cells)| cells.iter().map(|cell| cell.chars().count()))
+            .max()
+            .unwrap_or(0);
+
+        let mut out = String::new();
+        for (label, cells) in rows {
+            let _ = write!(out, "{:>w$} |", label, w = label_width);
+            for cell in cells {
+                let _ = write!(out, " {:<w$} |", cell, w = width);
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Draws the triangular table as an HTML `<table>`; cells and symbols on
+    /// the final derivation get the `used` class.
+    pub fn to_html(&self) -> String {
+        let used = self.used();
+        let mut out = String::from("<table class=\"cyk-chart\">\n");
+
+        for len in (1..=self.len()).rev() {
+            let _ = write!(out, "  <tr><th>{}</th>", len);
+            for start in 0..=self.len() - len {
+                let labels = self.labels(start, len, &used);
+                let class = if labels.iter().any(|&(_, used)| used) {
+                    " class=\"used\""
+                } else {
+                    ""
+                };
+                let _ = write!(out, "<td{}>", class);
+                for (index, (name, used)) in labels.into_iter().enumerate() {
+                    if index > 0 {
+                        out.push(' ');
+                    }
+                    if used {
+                        let _ = write!(out, "<span class=\"used\">{}</span>", escape_html(&name));
+                    } else {
+                        out.push_str(&escape_html(&name));
+                    }
+                }
+                out.push_str("</td>");
+            }
+            out.push_str("</tr>\n");
+        }
+
+        out.push_str("  <tr><th></th>");
+        for c in self.chars {
+            let _ = write!(
+                out,
+                "<td class=\"input\">{}</td>",
+                escape_html(&c.to_string())
+            );
+        }
+        out.push_str("</tr>\n</table>\n");
+        out
+    }
+
+    /// Draws every entry as a node with edges along its back-pointers; entries
+    /// on the final derivation are filled.
+    pub fn to_dot(&self) -> String {
+        let used = self.used();
+        let id = |span: Span, symbol: Symbol| format!("\"{}:{}\"", span, symbol.as_u32());
+        let mut out = String::from("digraph chart {\n");
+
+        for len in (1..=self.len()).rev() {
+            out.push_str("  { rank=same;");
+            for start in 0..=self.len() - len {
+                let span = self.span(start, len);
+                for symbol in self
+                    .cell(start, len)
+                    .map(|cell| cell.symbols())
+                    .unwrap_or_default()
+                {
+                    let _ = write!(out, " {};", id(span, symbol));
+                }
+            }
+            out.push_str(" }\n");
+        }
+
+        for cell in self.iter() {
+            let span = cell.span().unwrap();
+            for symbol in cell.symbols() {
+                let style = if used.contains(&(span, symbol)) {
+                    ", style=filled, fillcolor=lightblue"
+                } else {
+                    ""
+                };
+                let _ = writeln!(
+                    out,
+                    "  {} [label=\"{} [{}]\"{}];",
+                    id(span, symbol),
+                    escape_dot(&self.name(symbol)),
+                    span,
+                    style
+                );
+            }
+            for entry in cell.iter() {
+                let from = id(span, entry.kind());
+                match entry.back_pointer() {
+                    BackPointer::Terminal(terminal) => {
+                        let _ = writeln!(
+                            out,
+                            "  {} -> \"{}\";",
+                            from,
+                            escape_dot(&format!("{}@{}", self.name(terminal), span.start()))
+                        );
+                    }
+                    BackPointer::Binary(left_span, left, right_span, right) => {
+                        let _ = writeln!(out, "  {} -> {};", from, id(left_span, left));
+                        let _ = writeln!(out, "  {} -> {};", from, id(right_span, right));
+                    }
+                }
+            }
+        }
+
+        out.push_str("}\n");
+        out
+    }
+}
+
+fn escape_html(string: &str) -> String {
+    string
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn escape_dot(string: &str) -> String {
+    string.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn inspect_chart() {
+        let grammar = cnf_grammar! {
+            Start("S");
+            NonTerminals["S", "A", "B", "X"];
+            Terminals["a", "b"];
+            Rules [
+                "S" => [["A", "B"]],
+                "X" => [["A", "A"]]
+            ];
+            TerminalRules [
+                "A" => ["a"],
+                "B" => ["b"],
+                "X" => ["b"]
+            ]
+        };
+        let mut parser = Parser::new(&grammar);
+        parser.parse("ab").unwrap();
+        let chart = parser.chart();
+
+        assert_eq!(chart.len(), 2);
+        assert_eq!(
+            chart.cell(0, 2).unwrap().symbols(),
+            vec![Symbol::intern("S")]
+        );
+        assert_eq!(chart.iter().count(), 3);
+        assert_eq!(chart.used().len(), 3);
+        assert!(!chart
+            .used()
+            .contains(&(chart.span(1, 1), Symbol::intern("X"))));
+
+        let text = chart.to_text();
+        assert_eq!(text.lines().next(), Some("2 | S*   |"));
+        let row = text.lines().nth(1).unwrap();
+        assert!(row.starts_with("1 | A*   |") && row.contains("B*") && row.contains("X"));
+        assert!(chart
+            .to_html()
+            .contains("<td class=\"used\"><span class=\"used\">S</span></td>"));
+        assert!(chart.to_dot().contains("style=filled"));
+
+        assert!(parser.parse("ba").is_err());
+        assert!(parser.chart().used().is_empty());
+    }
+
+    #[test]
+    fn count_trees() {
+        let grammar = cnf_grammar! {
+            Start("S");
+            NonTerminals["S"];
+            Terminals["a"];
+            Rules ["S" => [["S", "S"]]];
+            TerminalRules ["S" => ["a"]]
+        };
+        let mut parser = Parser::new(&grammar);
+
+        parser.parse("a").unwrap();
+        assert_eq!(parser.chart().count_trees(), 1);
+        parser.parse("aaaa").unwrap();
+        assert_eq!(parser.chart().count_trees(), 5);
+        let trees = parser.chart().trees(3);
+        assert_eq!(trees.len(), 3);
+        assert_eq!(
+            trees[0].to_sexpr(grammar.symbols()),
+            parser.parse("aaaa").unwrap().to_sexpr(grammar.symbols())
+        );
+        assert_ne!(
+            trees[1].to_sexpr(grammar.symbols()),
+            trees[2].to_sexpr(grammar.symbols())
+        );
+
+        assert!(parser.parse("ab").is_err());
+        assert_eq!(parser.chart().count_trees(), 0);
+        assert!(parser.chart().trees(2).is_empty());
+    }
+}
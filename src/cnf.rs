@@ -1,5 +1,7 @@
 use super::symbol::*;
-use super::Grammar;
+use super::{Grammar, GrammarError};
+
+use std::fmt;
 
 pub use std::collections::HashSet;
 
@@ -21,7 +23,7 @@ macro_rules! cnf_grammar {
             let start_terminal = $crate::Symbol::intern($start);
             assert!(
                 non_terminals.contains(&start_terminal),
-                format!("Start:{} is not exist in non-terminals set", start_terminal)
+                "Start:{} is not exist in non-terminals set", start_terminal
             );
 
             let mut terminals: $crate::HashSet<$crate::Symbol> = $crate::HashSet::new();
@@ -29,7 +31,7 @@ macro_rules! cnf_grammar {
                 let symbol = $crate::Symbol::intern($terminal);
                 assert!(
                     !non_terminals.contains(&symbol),
-                    format!("Non-terminal:{} has already exist in terminal set.", symbol)
+                    "Non-terminal:{} has already exist in terminal set.", symbol
                 );
 
                 terminals.insert(symbol);
@@ -40,7 +42,7 @@ macro_rules! cnf_grammar {
                 let left = $crate::Symbol::intern($left);
                 assert!(
                     non_terminals.contains(&left),
-                    format!("The rule's left part: {} is not exist in non-terminals", left)
+                    "The rule's left part: {} is not exist in non-terminals", left
                 );
 
                 let mut right: $crate::HashSet<$crate::RuleRight> = $crate::HashSet::new();
@@ -48,13 +50,13 @@ macro_rules! cnf_grammar {
                     let first = $crate::Symbol::intern($first);
                     assert!(
                         non_terminals.contains(&first),
-                        format!("The rule's first part: {} is not exist in non-terminal set", first)
+                        "The rule's first part: {} is not exist in non-terminal set", first
                     );
 
                     let second = $crate::Symbol::intern($second);
                     assert!(
                         non_terminals.contains(&second),
-                        format!("The rule's second part: {} is not exist in non-terminal set", second)
+                        "The rule's second part: {} is not exist in non-terminal set", second
                     );
 
                     right.insert($crate::RuleRight::new(first, second));
@@ -68,7 +70,7 @@ macro_rules! cnf_grammar {
                 let left = $crate::Symbol::intern($t_left);
                 assert!(
                     non_terminals.contains(&left),
-                    format!("The rule's left part: {} is not exist in non-terminal set", left)
+                    "The rule's left part: {} is not exist in non-terminal set", left
                 );
 
                 let mut right: $crate::HashSet<$crate::Symbol> = $crate::HashSet::new();
@@ -76,7 +78,7 @@ macro_rules! cnf_grammar {
                     let symbol = $crate::Symbol::intern($t_right);
                     assert!(
                         terminals.contains(&symbol),
-                        format!("The rule's left part: {} is not exist in terminal set", symbol)
+                        "The rule's left part: {} is not exist in terminal set", symbol
                     );
                     right.insert(symbol);
                 )*
@@ -86,8 +88,8 @@ macro_rules! cnf_grammar {
 
             $crate::CNF::new(
                 start_terminal,
-                non_terminals,
                 terminals,
+                non_terminals,
                 rules,
                 terminal_rules
             )
@@ -95,16 +97,65 @@ macro_rules! cnf_grammar {
     };
 }
 
+/// A single production of a CNF grammar.
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Production {
+    /// `A -> B C`
+    Binary(Symbol, Symbol, Symbol),
+    /// `A -> t`
+    Terminal(Symbol, Symbol),
+}
+
+impl Production {
+    pub fn left(&self) -> Symbol {
+        match *self {
+            Production::Binary(left, _, _) => left,
+            Production::Terminal(left, _) => left,
+        }
+    }
+}
+
+impl DisplaySymbols for Production {
+    fn fmt_symbols(&self, symbols: &SymbolTable, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Production::Binary(left, first, second) => write!(
+                f,
+                "{} -> {} {}",
+                left.display(symbols),
+                first.display(symbols),
+                second.display(symbols)
+            ),
+            Production::Terminal(left, terminal) => write!(
+                f,
+                "{} -> {}",
+                left.display(symbols),
+                terminal.display(symbols)
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RuleRight(Symbol, Symbol);
 
 impl RuleRight {
     pub fn new(left: Symbol, right: Symbol) -> Self {
         RuleRight(left, right)
     }
+
+    pub fn left(&self) -> Symbol {
+        self.0
+    }
+
+    pub fn right(&self) -> Symbol {
+        self.1
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rule(Symbol, HashSet<RuleRight>);
 
 impl Rule {
@@ -146,6 +197,10 @@ impl Rule {
         self.0 == symbol
     }
 
+    pub fn branches(&self) -> impl Iterator<Item = &RuleRight> {
+        self.1.iter()
+    }
+
     pub fn derive(&self, base: Symbol, suffix: Symbol) -> Option<Symbol> {
         match self.follow(base) {
             Some(symbols) => {
@@ -161,6 +216,7 @@ impl Rule {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rules(Vec<Rule>);
 
 impl Default for Rules {
@@ -179,6 +235,10 @@ impl Rules {
         self.0.push(Rule(left, right))
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &Rule> {
+        self.0.iter()
+    }
+
     pub fn first(&self, symbol: Symbol) -> Option<HashSet<Symbol>> {
         let mut result: HashSet<Symbol> = HashSet::new();
 
@@ -231,6 +291,7 @@ impl Rules {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TerminalRule(Symbol, HashSet<Symbol>);
 
 impl TerminalRule {
@@ -238,6 +299,10 @@ impl TerminalRule {
         self.0
     }
 
+    pub fn symbols(&self) -> impl Iterator<Item = &Symbol> {
+        self.1.iter()
+    }
+
     fn derive(&self, base: Symbol) -> Option<Symbol> {
         for symbol in &self.1 {
             if symbol.eq(&base) {
@@ -250,6 +315,7 @@ impl TerminalRule {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TerminalRules(Vec<TerminalRule>);
 
 impl Default for TerminalRules {
@@ -268,6 +334,10 @@ impl TerminalRules {
         self.0.push(TerminalRule(left, right))
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &TerminalRule> {
+        self.0.iter()
+    }
+
     fn derive(&self, base: Symbol) -> Option<HashSet<Symbol>> {
         let mut result: HashSet<Symbol> = HashSet::new();
 
@@ -285,6 +355,10 @@ impl TerminalRules {
     }
 }
 
+/// With the `serde` feature, a grammar is written with the names of its
+/// symbols. One with a table of its own also writes the table, and reads
+/// back into a table of its own with the same ids; one without is read
+/// back into the process-wide table.
 #[derive(Debug, Clone)]
 pub struct CNF {
     start: Symbol,
@@ -292,9 +366,12 @@ pub struct CNF {
     non_terminals: HashSet<Symbol>,
     rules: Rules,
     terminal_rules: TerminalRules,
+    symbols: SymbolTable,
 }
 
 impl CNF {
+    /// Puts a grammar together from its parts without checking them; see
+    /// `validate`.
     pub fn new(
         start: Symbol,
         terminals: HashSet<Symbol>,
@@ -308,7 +385,76 @@ impl CNF {
             non_terminals,
             rules,
             terminal_rules,
+            symbols: SymbolTable::global(),
+        }
+    }
+
+    /// Checks what `GrammarBuilder::build` checks, for a grammar put
+    /// together with `new`, which takes its parts as they are: that the
+    /// start symbol is a non-terminal, that no symbol is both a terminal and
+    /// a non-terminal, that every rule has the shape its kind calls for with
+    /// declared symbols, and that no rule is given twice.
+    pub fn validate(&self) -> Result<(), GrammarError> {
+        let name = |symbol: Symbol| symbol.display(&self.symbols).to_string();
+        let undefined = |symbol: Symbol| GrammarError::Undefined(name(symbol));
+
+        if self.terminals.contains(&self.start) {
+            return Err(GrammarError::Clash(name(self.start)));
+        }
+        if !self.non_terminals.contains(&self.start) {
+            return Err(undefined(self.start));
+        }
+        if let Some(&symbol) = self.terminals.intersection(&self.non_terminals).next() {
+            return Err(GrammarError::Clash(name(symbol)));
+        }
+
+        let mut seen: HashSet<Production> = HashSet::new();
+        for rule in self.rules.iter() {
+            for branch in rule.branches() {
+                let production = Production::Binary(rule.start(), branch.left(), branch.right());
+                for symbol in [rule.start(), branch.left(), branch.right()] {
+                    if self.terminals.contains(&symbol) {
+                        let text = production.display(&self.symbols).to_string();
+                        return Err(GrammarError::NotCnf(text));
+                    }
+                    if !self.non_terminals.contains(&symbol) {
+                        return Err(undefined(symbol));
+                    }
+                }
+                if !seen.insert(production) {
+                    let text = production.display(&self.symbols).to_string();
+                    return Err(GrammarError::DuplicateRule(text));
+                }
+            }
+        }
+        for rule in self.terminal_rules.iter() {
+            if !self.non_terminals.contains(&rule.start()) {
+                return Err(undefined(rule.start()));
+            }
+            for &symbol in rule.symbols() {
+                let production = Production::Terminal(rule.start(), symbol);
+                if self.non_terminals.contains(&symbol) {
+                    let text = production.display(&self.symbols).to_string();
+                    return Err(GrammarError::NotCnf(text));
+                }
+                if !self.terminals.contains(&symbol) {
+                    return Err(undefined(symbol));
+                }
+                if !seen.insert(production) {
+                    let text = production.display(&self.symbols).to_string();
+                    return Err(GrammarError::DuplicateRule(text));
+                }
+            }
         }
+
+        Ok(())
+    }
+
+    /// Replaces the symbol table the grammar's symbols were interned in,
+    /// which defaults to a view of the process-wide table.
+    pub fn with_symbols(mut self, symbols: SymbolTable) -> Self {
+        self.symbols = symbols;
+        self
     }
 }
 
@@ -344,4 +490,131 @@ impl Grammar for CNF {
     fn is_non_terminal(&self, input: Symbol) -> bool {
         self.non_terminals.contains(&input)
     }
+
+    fn terminals(&self) -> &HashSet<Symbol> {
+        &self.terminals
+    }
+
+    fn non_terminals(&self) -> &HashSet<Symbol> {
+        &self.non_terminals
+    }
+
+    fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    fn terminal_rules(&self) -> &TerminalRules {
+        &self.terminal_rules
+    }
+
+    fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+}
+
+/// The form a `CNF` is written in, with names in place of symbols.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct NamedCnf {
+    start: String,
+    terminals: Vec<String>,
+    non_terminals: Vec<String>,
+    rules: Vec<(String, Vec<(String, String)>)>,
+    terminal_rules: Vec<(String, Vec<String>)>,
+    /// The names of an owned table in id order, absent for the
+    /// process-wide one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    symbols: Option<Vec<String>>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for CNF {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let table = &self.symbols;
+        let name = |symbol: &Symbol| match table.resolve(*symbol) {
+            Some(name) => Ok(name.to_string()),
+            None => Err(serde::ser::Error::custom(format!(
+                "symbol {} is not in the grammar's table",
+                symbol.display(table)
+            ))),
+        };
+        let names = |symbols: &HashSet<Symbol>| -> Result<Vec<String>, S::Error> {
+            let mut names = symbols.iter().map(name).collect::<Result<Vec<_>, _>>()?;
+            names.sort_unstable();
+            Ok(names)
+        };
+
+        let mut rules = Vec::new();
+        for rule in self.rules.iter() {
+            let mut branches = Vec::new();
+            for branch in rule.branches() {
+                branches.push((name(&branch.left())?, name(&branch.right())?));
+            }
+            branches.sort_unstable();
+            rules.push((name(&rule.start())?, branches));
+        }
+        let mut terminal_rules = Vec::new();
+        for rule in self.terminal_rules.iter() {
+            let mut symbols = rule.symbols().map(name).collect::<Result<Vec<_>, _>>()?;
+            symbols.sort_unstable();
+            terminal_rules.push((name(&rule.start())?, symbols));
+        }
+
+        let symbols = match table.is_global() {
+            true => None,
+            false => Some(table.iter().map(|(_, name)| name.to_string()).collect()),
+        };
+        serde::Serialize::serialize(
+            &NamedCnf {
+                start: name(&self.start)?,
+                terminals: names(&self.terminals)?,
+                non_terminals: names(&self.non_terminals)?,
+                rules,
+                terminal_rules,
+                symbols,
+            },
+            serializer,
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CNF {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let named: NamedCnf = serde::Deserialize::deserialize(deserializer)?;
+
+        let mut table = SymbolTable::global();
+        if let Some(names) = &named.symbols {
+            table = SymbolTable::new();
+            for name in names {
+                if table.get(name).is_some() {
+                    return Err(serde::de::Error::custom(format!(
+                        "symbol {} is listed twice",
+                        name
+                    )));
+                }
+                table.intern(name);
+            }
+        }
+
+        let mut intern = |name: &String| table.intern(name);
+        let start = intern(&named.start);
+        let terminals = named.terminals.iter().map(&mut intern).collect();
+        let non_terminals = named.non_terminals.iter().map(&mut intern).collect();
+        let mut rules = Rules::new();
+        for (left, branches) in &named.rules {
+            let branches = branches
+                .iter()
+                .map(|(first, second)| RuleRight::new(intern(first), intern(second)))
+                .collect();
+            rules.insert(intern(left), branches);
+        }
+        let mut terminal_rules = TerminalRules::new();
+        for (left, symbols) in &named.terminal_rules {
+            let symbols = symbols.iter().map(&mut intern).collect();
+            terminal_rules.insert(intern(left), symbols);
+        }
+
+        Ok(CNF::new(start, terminals, non_terminals, rules, terminal_rules).with_symbols(table))
+    }
 }
@@ -0,0 +1,349 @@
+use super::{Enumeration, Generator, Grammar, HashSet, Parser, Production, Sampling, Symbol};
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+
+/// A sentence that one of two grammars accepts and the other rejects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Counterexample {
+    sentence: String,
+    in_first: bool,
+}
+
+impl Counterexample {
+    pub fn sentence(&self) -> &str {
+        &self.sentence
+    }
+
+    /// Whether the first grammar is the one accepting the sentence.
+    pub fn in_first(&self) -> bool {
+        self.in_first
+    }
+
+    /// Orders counterexamples shortest first, then by text.
+    fn key(&self) -> (usize, &str) {
+        (self.sentence.chars().count(), &self.sentence)
+    }
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let which = if self.in_first { "first" } else { "second" };
+        write!(
+            f,
+            "{:?} is accepted by the {} grammar only",
+            self.sentence, which
+        )
+    }
+}
+
+/// Compares the languages of two grammars on their short sentences.
+///
+/// Whether two context-free grammars accept the same language cannot be
+/// decided in general, so every sentence up to `max_length` terminals is
+/// checked, and then, if asked for, random sentences up to `sample_length`.
+/// The exhaustive part always finds the shortest counterexample within its
+/// bound; the sampled part finds the shortest among those it drew.
+///
+/// The sentences of a length are only listed while their derivations, which
+/// bound how many there are, number at most `max_sentences`. From the first
+/// length past that limit on, a hundred sentences of each length up to
+/// `max_length` are drawn from the grammars instead.
+///
+/// A `Cfg` is compared through `Cfg::to_cnf`, which leaves out the empty
+/// sentence; the empty sentence is never checked.
+#[derive(Clone, Debug)]
+pub struct Comparison<'g, A, B> {
+    first: &'g A,
+    second: &'g B,
+    max_length: usize,
+    max_sentences: u64,
+    samples: usize,
+    sample_length: usize,
+    seed: u64,
+}
+
+/// The number of sentences of each length drawn from a grammar once its
+/// sentences of that length are too many to list.
+const LENGTH_SAMPLES: usize = 100;
+
+/// Returns, for every length up to `max_length`, the number of derivations
+/// of every non-terminal added up: a bound on the sentences `Enumeration`
+/// keeps for that length, counted without listing them.
+fn derivations<G: Grammar>(grammar: &G, max_length: usize) -> Vec<u64> {
+    let productions: HashSet<Production> = grammar.productions().into_iter().collect();
+    let mut counts: Vec<HashMap<Symbol, u64>> = Vec::new();
+    for length in 1..=max_length {
+        let mut table: HashMap<Symbol, u64> = HashMap::new();
+        for production in &productions {
+            let (left, count) = match *production {
+                Production::Terminal(left, _) if length == 1 => (left, 1),
+                Production::Terminal(..) => continue,
+                Production::Binary(left, first, second) => {
+                    let count = |split: usize, symbol| {
+                        let table: &HashMap<Symbol, u64> = &counts[split - 1];
+                        table.get(&symbol).copied().unwrap_or(0)
+                    };
+                    let total = (1..length).fold(0u64, |total, split| {
+                        let pairs =
+                            count(split, first).saturating_mul(count(length - split, second));
+                        total.saturating_add(pairs)
+                    });
+                    (left, total)
+                }
+            };
+            let total = table.entry(left).or_insert(0);
+            *total = total.saturating_add(count);
+        }
+        counts.push(table);
+    }
+
+    counts
+        .iter()
+        .map(|table| {
+            table
+                .values()
+                .fold(0u64, |total, &count| total.saturating_add(count))
+        })
+        .collect()
+}
+
+impl<'g, A, B> Comparison<'g, A, B>
+where
+    A: Grammar + Debug + Clone,
+    B: Grammar + Debug + Clone,
+{
+    pub fn new(first: &'g A, second: &'g B) -> Self {
+        Comparison {
+            first,
+            second,
+            max_length: 8,
+            max_sentences: 10_000,
+            samples: 0,
+            sample_length: 0,
+            seed: 0,
+        }
+    }
+
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Lists the sentences of a length only while they have at most
+    /// `max_sentences` derivations, and samples the longer ones.
+    pub fn max_sentences(mut self, max_sentences: u64) -> Self {
+        self.max_sentences = max_sentences;
+        self
+    }
+
+    /// Returns the length up to which every sentence is checked: the
+    /// lengths past it up to `max_length` have too many sentences and are
+    /// sampled.
+    pub fn enumerated_length(&self) -> usize {
+        let first = derivations(self.first, self.max_length);
+        let second = derivations(self.second, self.max_length);
+        first
+            .iter()
+            .zip(&second)
+            .take_while(|&(&first, &second)| first.max(second) <= self.max_sentences)
+            .count()
+    }
+
+    /// Also draws `samples` random sentences of at most `max_length`
+    /// terminals from each grammar.
+    pub fn samples(mut self, samples: usize, max_length: usize) -> Self {
+        self.samples = samples;
+        self.sample_length = max_length;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Returns the shortest sentence found in the first language and not in
+    /// the second, or `None` if the first looks included in the second.
+    pub fn included(&self) -> Option<Counterexample> {
+        self.exhaustive(false)
+            .or_else(|| self.by_length(false))
+            .or_else(|| self.sampled(false))
+    }
+
+    /// Returns the shortest sentence found in only one of the languages, or
+    /// `None` if they look equal.
+    pub fn equivalent(&self) -> Option<Counterexample> {
+        self.exhaustive(true)
+            .or_else(|| self.by_length(true))
+            .or_else(|| self.sampled(true))
+    }
+
+    fn exhaustive(&self, both: bool) -> Option<Counterexample> {
+        let enumerated = self.enumerated_length();
+        let mut first = Enumeration::new(self.first, enumerated);
+        let mut second = Enumeration::new(self.second, enumerated);
+
+        for length in 1..=enumerated {
+            let mut found: Vec<Counterexample> = Vec::new();
+            let sentences = first.sentences_of(length);
+
+            if both {
+                let others = second.sentences_of(length);
+                let (ours, theirs): (HashSet<&String>, HashSet<&String>) =
+                    (sentences.iter().collect(), others.iter().collect());
+                found.extend(ours.difference(&theirs).map(|sentence| Counterexample {
+                    sentence: sentence.to_string(),
+                    in_first: true,
+                }));
+                found.extend(theirs.difference(&ours).map(|sentence| Counterexample {
+                    sentence: sentence.to_string(),
+                    in_first: false,
+                }));
+            } else {
+                let mut parser = Parser::new(self.second);
+                found.extend(
+                    sentences
+                        .iter()
+                        .filter(|sentence| parser.parse(sentence).is_err())
+                        .map(|sentence| Counterexample {
+                            sentence: sentence.clone(),
+                            in_first: true,
+                        }),
+                );
+            }
+
+            if let Some(shortest) = found.into_iter().min_by(|a, b| a.key().cmp(&b.key())) {
+                return Some(shortest);
+            }
+        }
+
+        None
+    }
+
+    /// Samples the lengths the exhaustive part left out, shortest first.
+    fn by_length(&self, both: bool) -> Option<Counterexample> {
+        for length in self.enumerated_length() + 1..=self.max_length {
+            let sampling = Sampling::Length(length);
+            let generator = self.generator(self.first, sampling);
+            let mut found = self.missing(generator, self.second, true, LENGTH_SAMPLES);
+            if both {
+                let generator = self.generator(self.second, sampling);
+                found.extend(self.missing(generator, self.first, false, LENGTH_SAMPLES));
+            }
+            if let Some(shortest) = found.into_iter().min_by(|a, b| a.key().cmp(&b.key())) {
+                return Some(shortest);
+            }
+        }
+
+        None
+    }
+
+    fn sampled(&self, both: bool) -> Option<Counterexample> {
+        if self.samples == 0 {
+            return None;
+        }
+
+        let generator = self.generator(self.first, Sampling::Derivations);
+        let mut found = self.missing(generator, self.second, true, self.samples);
+        if both {
+            let generator = self.generator(self.second, Sampling::Derivations);
+            found.extend(self.missing(generator, self.first, false, self.samples));
+        }
+        found.into_iter().min_by(|a, b| a.key().cmp(&b.key()))
+    }
+
+    fn generator<G: Grammar>(&self, grammar: &G, sampling: Sampling) -> Generator {
+        let generator = Generator::new(grammar, self.seed).sampling(sampling);
+        match sampling {
+            // A CNF tree is no taller than its sentence is long.
+            Sampling::Length(length) => generator.max_depth(length),
+            _ => generator.max_length(self.sample_length),
+        }
+    }
+
+    /// Draws `count` sentences from `generator` and returns those `into`
+    /// rejects.
+    fn missing<I>(
+        &self,
+        generator: Generator,
+        into: &I,
+        in_first: bool,
+        count: usize,
+    ) -> Vec<Counterexample>
+    where
+        I: Grammar + Debug + Clone,
+    {
+        let sentences: Vec<String> = generator.take(count).collect();
+        let mut parser = Parser::new(into);
+
+        sentences
+            .iter()
+            .filter(|sentence| !sentence.is_empty() && parser.parse(sentence).is_err())
+            .map(|sentence| Counterexample {
+                sentence: sentence.clone(),
+                in_first,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn find_counterexamples() {
+        let sums = Cfg::parse("Sum -> Sum '+' Digit | Digit; Digit -> '0' | '1';").unwrap();
+        let before = sums.to_cnf().unwrap();
+        let after = Cfg::from_grammar(&before).as_cnf().unwrap();
+        assert_eq!(Comparison::new(&before, &after).equivalent(), None);
+
+        let digits = Cfg::parse("Sum -> '0' | '1';").unwrap().as_cnf().unwrap();
+        assert_eq!(Comparison::new(&digits, &before).included(), None);
+        let counterexample = Comparison::new(&before, &digits).included().unwrap();
+        assert_eq!(counterexample.sentence(), "0+0");
+        assert!(counterexample.in_first());
+
+        let longer = Cfg::parse(
+            "Sum -> Sum '+' Digit | Digit | Sum '+' Sum '+' Sum '+' '2'; Digit -> '0' | '1';",
+        )
+        .unwrap()
+        .to_cnf()
+        .unwrap();
+        let counterexample = Comparison::new(&before, &longer).equivalent().unwrap();
+        assert_eq!(counterexample.sentence(), "0+0+0+2");
+        assert!(!counterexample.in_first());
+
+        let bounded = Comparison::new(&before, &longer).max_length(5);
+        assert_eq!(bounded.equivalent(), None);
+        let sampled = bounded.samples(500, 11).seed(1).equivalent().unwrap();
+        assert!(sampled.sentence().ends_with('2') && !sampled.in_first());
+    }
+
+    #[test]
+    fn sample_long_lengths() {
+        let all = Cfg::parse("S -> X S | X; X -> 'a' | 'b';").unwrap();
+        let not_four =
+            Cfg::parse("S -> X | X X | X X X | X X X X X T; T -> X T | ; X -> 'a' | 'b';").unwrap();
+        let (all, not_four) = (all.to_cnf().unwrap(), not_four.to_cnf().unwrap());
+
+        let listed = Comparison::new(&all, &not_four);
+        assert_eq!(listed.enumerated_length(), 8);
+        let counterexample = listed.equivalent().unwrap();
+        assert_eq!(counterexample.sentence(), "aaaa");
+
+        let sampled = Comparison::new(&all, &not_four).max_sentences(10);
+        assert!(sampled.enumerated_length() < 4);
+        let counterexample = sampled.equivalent().unwrap();
+        assert_eq!(counterexample.sentence().len(), 4);
+        assert!(counterexample.in_first());
+        assert_eq!(sampled.included(), Some(counterexample));
+        assert_eq!(
+            Comparison::new(&not_four, &all)
+                .max_sentences(10)
+                .included(),
+            None
+        );
+    }
+}
@@ -0,0 +1,605 @@
+use super::{Grammar, HashSet, LoadError, RuleRight, Rules, Symbol, SymbolTable, TerminalRules};
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+const MAGIC: &[u8; 4] = b"CYKG";
+const HEADER_LEN: usize = 16;
+
+/// The version of the format written by `CompiledGrammar::compile`.
+pub const COMPILED_VERSION: u32 = 2;
+
+/// FNV-1a, which is plenty to catch truncated or damaged files.
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash: u32, &byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+/// Returns the first index in `0..len` for which `less` is false.
+fn lower_bound<F: Fn(usize) -> bool>(len: usize, less: F) -> usize {
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if less(middle) {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
+
+/// A table of `len` records of `width` words, starting at word `at` of the
+/// body.
+#[derive(Copy, Clone, Debug)]
+struct Table {
+    at: usize,
+    len: usize,
+    width: usize,
+}
+
+struct Reader<'b> {
+    body: &'b [u8],
+    at: usize,
+}
+
+impl<'b> Reader<'b> {
+    fn next(&mut self) -> Result<u32, LoadError> {
+        if (self.at + 1) * 4 > self.body.len() {
+            return Err(LoadError::Truncated);
+        }
+        self.at += 1;
+        Ok(read_u32(self.body, (self.at - 1) * 4))
+    }
+
+    fn table(&mut self, len: usize, width: usize) -> Result<Table, LoadError> {
+        let end = len
+            .checked_mul(width)
+            .and_then(|words| words.checked_add(self.at))
+            .filter(|&end| end.saturating_mul(4) <= self.body.len())
+            .ok_or(LoadError::Truncated)?;
+        let table = Table {
+            at: self.at,
+            len,
+            width,
+        };
+        self.at = end;
+        Ok(table)
+    }
+
+    fn counted(&mut self, width: usize) -> Result<Table, LoadError> {
+        let len = self.next()? as usize;
+        self.table(len, width)
+    }
+}
+
+/// Everything `Grammar` hands out by reference, built on first use.
+#[derive(Clone, Debug)]
+struct Owned {
+    terminals: HashSet<Symbol>,
+    non_terminals: HashSet<Symbol>,
+    rules: Rules,
+    terminal_rules: TerminalRules,
+    symbols: SymbolTable,
+}
+
+/// A grammar read in place from the bytes written by `compile`.
+///
+/// The bytes can come straight from a memory-mapped file. Parsing looks
+/// rules up by binary search in the sorted tables of the buffer; the
+/// `Rules`, sets and symbol table of the `Grammar` trait are only built the
+/// first time something asks for them.
+///
+/// Only the symbols the grammar uses are written, renumbered from zero in
+/// the order of their ids, so resolve the symbols of a compiled grammar
+/// through `symbols()`.
+#[derive(Clone)]
+pub struct CompiledGrammar<'b> {
+    body: &'b [u8],
+    start: Symbol,
+    names: Table,
+    terminals: Table,
+    non_terminals: Table,
+    binary: Table,
+    parents: Table,
+    single: Table,
+    chars: Table,
+    strings: usize,
+    owned: OnceLock<Owned>,
+}
+
+impl<'b> CompiledGrammar<'b> {
+    /// Writes `grammar` in the compiled format.
+    pub fn compile<G: Grammar>(grammar: &G) -> Vec<u8> {
+        let table = grammar.symbols();
+
+        // A grammar on the process-wide table would otherwise carry every
+        // name interned so far.
+        let mut used: Vec<Symbol> = vec![grammar.start_symbol()];
+        used.extend(grammar.terminals());
+        used.extend(grammar.non_terminals());
+        for rule in grammar.rules().iter() {
+            used.push(rule.start());
+            for branch in rule.branches() {
+                used.extend([branch.left(), branch.right()]);
+            }
+        }
+        for rule in grammar.terminal_rules().iter() {
+            used.push(rule.start());
+            used.extend(rule.symbols());
+        }
+        used.sort_unstable();
+        used.dedup();
+        let renumbered: HashMap<Symbol, u32> = used
+            .iter()
+            .enumerate()
+            .map(|(id, symbol)| (*symbol, id as u32))
+            .collect();
+        let id = |symbol: Symbol| renumbered[&symbol];
+        let ids = |symbols: &HashSet<Symbol>| -> Vec<u32> {
+            let mut ids: Vec<u32> = symbols.iter().map(|&symbol| id(symbol)).collect();
+            ids.sort_unstable();
+            ids
+        };
+
+        let mut words: Vec<u32> = vec![id(grammar.start_symbol()), used.len() as u32];
+        let mut strings: Vec<u8> = Vec::new();
+        words.push(0);
+        for symbol in &used {
+            match table.resolve(*symbol) {
+                Some(name) => strings.extend(name.as_bytes()),
+                None => strings.extend(symbol.to_string().as_bytes()),
+            }
+            words.push(strings.len() as u32);
+        }
+
+        let terminals = ids(grammar.terminals());
+        let non_terminals = ids(grammar.non_terminals());
+
+        let mut binary: Vec<[u32; 3]> = Vec::new();
+        let mut single: Vec<[u32; 2]> = Vec::new();
+        for rule in grammar.rules().iter() {
+            for branch in rule.branches() {
+                binary.push([id(branch.left()), id(branch.right()), id(rule.start())]);
+            }
+        }
+        for rule in grammar.terminal_rules().iter() {
+            for terminal in rule.symbols() {
+                single.push([id(*terminal), id(rule.start())]);
+            }
+        }
+        binary.sort_unstable();
+        binary.dedup();
+        single.sort_unstable();
+        single.dedup();
+        let mut parents: Vec<[u32; 2]> = binary
+            .iter()
+            .map(|&[left, _, parent]| [parent, left])
+            .collect();
+        parents.sort_unstable();
+        parents.dedup();
+
+        let mut chars: Vec<[u32; 2]> = Vec::new();
+        for &terminal in grammar.terminals() {
+            let name = table.resolve(terminal).unwrap_or("");
+            let mut name = name.chars();
+            if let (Some(c), None) = (name.next(), name.next()) {
+                chars.push([c as u32, id(terminal)]);
+            }
+        }
+        chars.sort_unstable();
+
+        words.push(terminals.len() as u32);
+        words.extend(terminals);
+        words.push(non_terminals.len() as u32);
+        words.extend(non_terminals);
+        words.push(binary.len() as u32);
+        words.extend(binary.iter().flatten());
+        words.push(parents.len() as u32);
+        words.extend(parents.iter().flatten());
+        words.push(single.len() as u32);
+        words.extend(single.iter().flatten());
+        words.push(chars.len() as u32);
+        words.extend(chars.iter().flatten());
+        words.push(strings.len() as u32);
+
+        let mut body: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        body.extend(strings);
+        body.resize(body.len().next_multiple_of(4), 0);
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(HEADER_LEN + body.len());
+        bytes.extend(MAGIC);
+        bytes.extend(COMPILED_VERSION.to_le_bytes());
+        bytes.extend(checksum(&body).to_le_bytes());
+        bytes.extend((body.len() as u32).to_le_bytes());
+        bytes.extend(body);
+        bytes
+    }
+
+    /// Whether `bytes` starts like a compiled grammar, so that callers can
+    /// tell it from a grammar in text form.
+    pub fn is_compiled(bytes: &[u8]) -> bool {
+        bytes.starts_with(MAGIC)
+    }
+
+    /// Checks the header and checksum of `bytes` and locates its tables.
+    /// Nothing is copied.
+    pub fn from_bytes(bytes: &'b [u8]) -> Result<Self, LoadError> {
+        if bytes.len() < 4 || &bytes[..4] != MAGIC {
+            return Err(LoadError::BadMagic);
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(LoadError::Truncated);
+        }
+
+        let version = read_u32(bytes, 4);
+        if version != COMPILED_VERSION {
+            return Err(LoadError::Version {
+                found: version,
+                expected: COMPILED_VERSION,
+            });
+        }
+
+        let body_len = read_u32(bytes, 12) as usize;
+        let body = bytes
+            .get(HEADER_LEN..HEADER_LEN + body_len)
+            .ok_or(LoadError::Truncated)?;
+        let (found, expected) = (checksum(body), read_u32(bytes, 8));
+        if found != expected {
+            return Err(LoadError::Checksum { found, expected });
+        }
+
+        let mut reader = Reader { body, at: 0 };
+        let start = Symbol::new(reader.next()?);
+        let symbol_count = reader.next()? as usize;
+        let names = reader.table(symbol_count + 1, 1)?;
+        let terminals = reader.counted(1)?;
+        let non_terminals = reader.counted(1)?;
+        let binary = reader.counted(3)?;
+        let parents = reader.counted(2)?;
+        let single = reader.counted(2)?;
+        let chars = reader.counted(2)?;
+        let strings_len = reader.next()? as usize;
+        let strings = reader.at * 4;
+        if strings + strings_len > body.len() {
+            return Err(LoadError::Truncated);
+        }
+
+        let grammar = CompiledGrammar {
+            body,
+            start,
+            names,
+            terminals,
+            non_terminals,
+            binary,
+            parents,
+            single,
+            chars,
+            strings,
+            owned: OnceLock::new(),
+        };
+
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut previous = grammar.word(names, 0, 0) as usize;
+        for index in 0..symbol_count {
+            let end = grammar.word(names, index + 1, 0) as usize;
+            if end < previous || end > strings_len {
+                return Err(LoadError::Corrupt("bad symbol name offsets".to_string()));
+            }
+            let name = std::str::from_utf8(&body[strings + previous..strings + end])
+                .map_err(|_| LoadError::Corrupt("symbol name is not UTF-8".to_string()))?;
+            if !seen.insert(name) {
+                return Err(LoadError::Corrupt(format!("symbol {} is repeated", name)));
+            }
+            previous = end;
+        }
+        if start.as_u32() as usize >= symbol_count {
+            return Err(LoadError::Corrupt(
+                "start symbol is out of range".to_string(),
+            ));
+        }
+        let symbol_fields = [
+            (terminals, 0..1),
+            (non_terminals, 0..1),
+            (binary, 0..3),
+            (parents, 0..2),
+            (single, 0..2),
+            (chars, 1..2),
+        ];
+        for (table, fields) in symbol_fields {
+            for record in 0..table.len {
+                for field in fields.clone() {
+                    if grammar.word(table, record, field) as usize >= symbol_count {
+                        return Err(LoadError::Corrupt("symbol is out of range".to_string()));
+                    }
+                }
+            }
+        }
+
+        Ok(grammar)
+    }
+
+    fn word(&self, table: Table, record: usize, field: usize) -> u32 {
+        read_u32(self.body, (table.at + record * table.width + field) * 4)
+    }
+
+    fn symbol(&self, table: Table, record: usize, field: usize) -> Symbol {
+        Symbol::new(self.word(table, record, field))
+    }
+
+    fn name(&self, index: usize) -> &'b str {
+        let start = self.strings + self.word(self.names, index, 0) as usize;
+        let end = self.strings + self.word(self.names, index + 1, 0) as usize;
+        std::str::from_utf8(&self.body[start..end]).unwrap_or("")
+    }
+
+    fn contains(&self, table: Table, symbol: Symbol) -> bool {
+        let index = lower_bound(table.len, |i| self.word(table, i, 0) < symbol.as_u32());
+        index < table.len && self.word(table, index, 0) == symbol.as_u32()
+    }
+
+    /// Compares the first words of a record with `key`.
+    fn compare(&self, table: Table, record: usize, key: &[u32]) -> Ordering {
+        key.iter()
+            .enumerate()
+            .map(|(field, word)| self.word(table, record, field).cmp(word))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// Collects `field` of the records whose first words equal `key`.
+    fn lookup(&self, table: Table, key: &[u32], field: usize) -> Option<HashSet<Symbol>> {
+        let mut index = lower_bound(table.len, |i| self.compare(table, i, key) == Ordering::Less);
+        let mut result: HashSet<Symbol> = HashSet::new();
+        while index < table.len && self.compare(table, index, key) == Ordering::Equal {
+            result.insert(self.symbol(table, index, field));
+            index += 1;
+        }
+
+        if !result.is_empty() {
+            Some(result)
+        } else {
+            None
+        }
+    }
+
+    fn owned(&self) -> &Owned {
+        self.owned.get_or_init(|| {
+            let set = |table: Table| -> HashSet<Symbol> {
+                (0..table.len).map(|i| self.symbol(table, i, 0)).collect()
+            };
+
+            let mut symbols = SymbolTable::new();
+            for index in 0..self.names.len - 1 {
+                symbols.intern(self.name(index));
+            }
+
+            let mut binary: HashMap<Symbol, HashSet<RuleRight>> = HashMap::new();
+            for i in 0..self.binary.len {
+                let branch = RuleRight::new(
+                    self.symbol(self.binary, i, 0),
+                    self.symbol(self.binary, i, 1),
+                );
+                binary
+                    .entry(self.symbol(self.binary, i, 2))
+                    .or_default()
+                    .insert(branch);
+            }
+            let mut single: HashMap<Symbol, HashSet<Symbol>> = HashMap::new();
+            for i in 0..self.single.len {
+                single
+                    .entry(self.symbol(self.single, i, 1))
+                    .or_default()
+                    .insert(self.symbol(self.single, i, 0));
+            }
+
+            let mut rules = Rules::new();
+            let mut lefts: Vec<Symbol> = binary.keys().copied().collect();
+            lefts.sort_unstable();
+            for left in lefts {
+                rules.insert(left, binary.remove(&left).unwrap());
+            }
+            let mut terminal_rules = TerminalRules::new();
+            let mut lefts: Vec<Symbol> = single.keys().copied().collect();
+            lefts.sort_unstable();
+            for left in lefts {
+                terminal_rules.insert(left, single.remove(&left).unwrap());
+            }
+
+            Owned {
+                terminals: set(self.terminals),
+                non_terminals: set(self.non_terminals),
+                rules,
+                terminal_rules,
+                symbols,
+            }
+        })
+    }
+}
+
+impl<'b> fmt::Debug for CompiledGrammar<'b> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompiledGrammar")
+            .field("start", &self.name(self.start.as_u32() as usize))
+            .field("symbols", &(self.names.len - 1))
+            .field("rules", &self.binary.len)
+            .field("terminal_rules", &self.single.len)
+            .finish()
+    }
+}
+
+impl<'b> Grammar for CompiledGrammar<'b> {
+    fn start_symbol(&self) -> Symbol {
+        self.start
+    }
+
+    fn exist(&self, symbol: Symbol) -> bool {
+        self.is_terminal(symbol) || self.is_non_terminal(symbol)
+    }
+
+    fn first(&self, symbol: Symbol) -> Option<HashSet<Symbol>> {
+        self.lookup(self.parents, &[symbol.as_u32()], 1)
+    }
+
+    fn follow(&self, symbol: Symbol) -> Option<HashSet<Symbol>> {
+        self.lookup(self.binary, &[symbol.as_u32()], 1)
+    }
+
+    fn derive(&self, base: Symbol, suffix: Symbol) -> Option<HashSet<Symbol>> {
+        self.lookup(self.binary, &[base.as_u32(), suffix.as_u32()], 2)
+    }
+
+    fn derive_single(&self, base: Symbol) -> Option<HashSet<Symbol>> {
+        self.lookup(self.single, &[base.as_u32()], 1)
+    }
+
+    fn is_terminal(&self, input: Symbol) -> bool {
+        self.contains(self.terminals, input)
+    }
+
+    fn is_non_terminal(&self, input: Symbol) -> bool {
+        self.contains(self.non_terminals, input)
+    }
+
+    fn terminals(&self) -> &HashSet<Symbol> {
+        &self.owned().terminals
+    }
+
+    fn non_terminals(&self) -> &HashSet<Symbol> {
+        &self.owned().non_terminals
+    }
+
+    fn rules(&self) -> &Rules {
+        &self.owned().rules
+    }
+
+    fn terminal_rules(&self) -> &TerminalRules {
+        &self.owned().terminal_rules
+    }
+
+    fn symbols(&self) -> &SymbolTable {
+        &self.owned().symbols
+    }
+
+    fn terminal(&self, c: char) -> Option<Symbol> {
+        let chars = self.chars;
+        let index = lower_bound(chars.len, |i| self.word(chars, i, 0) < c as u32);
+        if index < chars.len && self.word(chars, index, 0) == c as u32 {
+            Some(self.symbol(chars, index, 1))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    fn expression_grammar() -> CNF {
+        GrammarBuilder::new()
+            .start("Sum")
+            .rule("Sum", &["Sum", "Tail"])
+            .rule("Tail", &["Plus", "Digit"])
+            .terminal_rule("Sum", "1")
+            .terminal_rule("Sum", "2")
+            .terminal_rule("Digit", "1")
+            .terminal_rule("Digit", "2")
+            .terminal_rule("Plus", "+")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn compile_and_load() {
+        let grammar = expression_grammar();
+        let bytes = CompiledGrammar::compile(&grammar);
+        let compiled = CompiledGrammar::from_bytes(&bytes).unwrap();
+
+        for src in &["1+2+1", "2", "1+", "12", "1+3"] {
+            let expected = Parser::new(&grammar).parse(src);
+            let found = Parser::new(&compiled).parse(src);
+            assert_eq!(format!("{:?}", found), format!("{:?}", expected));
+        }
+
+        assert_eq!(compiled.productions().len(), grammar.productions().len());
+        assert_eq!(compiled.terminals(), grammar.terminals());
+        assert_eq!(
+            compiled.symbols().resolve(compiled.start_symbol()),
+            Some("Sum")
+        );
+        assert_eq!(CompiledGrammar::compile(&compiled), bytes);
+    }
+
+    #[test]
+    fn reject_damaged_buffers() {
+        let bytes = CompiledGrammar::compile(&expression_grammar());
+        let load = |bytes: &[u8]| CompiledGrammar::from_bytes(bytes).map(|_| ());
+
+        assert_eq!(load(b"GRAM"), Err(LoadError::BadMagic));
+        assert_eq!(load(&bytes[..bytes.len() - 4]), Err(LoadError::Truncated));
+
+        let mut version = bytes.clone();
+        version[4] = 9;
+        assert_eq!(
+            load(&version),
+            Err(LoadError::Version {
+                found: 9,
+                expected: COMPILED_VERSION
+            })
+        );
+
+        let mut damaged = bytes.clone();
+        let last = damaged.len() - 1;
+        damaged[last] ^= 0xff;
+        assert!(matches!(load(&damaged), Err(LoadError::Checksum { .. })));
+
+        // The first terminal, right after the start, the symbol count and
+        // the name offsets, pointing past the last symbol.
+        let mut out_of_range = bytes.clone();
+        let count = super::read_u32(&bytes, 20) as usize;
+        let at = 16 + (2 + count + 1 + 1) * 4;
+        out_of_range[at..at + 4].copy_from_slice(&(count as u32).to_le_bytes());
+        let sum = super::checksum(&out_of_range[16..]);
+        out_of_range[8..12].copy_from_slice(&sum.to_le_bytes());
+        assert_eq!(
+            load(&out_of_range),
+            Err(LoadError::Corrupt("symbol is out of range".to_string()))
+        );
+    }
+
+    #[test]
+    fn compile_used_symbols() {
+        let mut symbols = SymbolTable::new();
+        for index in 0..1000 {
+            symbols.intern(&format!("unused{}", index));
+        }
+        let grammar = GrammarBuilder::new()
+            .with_symbols(symbols)
+            .start("S")
+            .rule("S", &["A", "B"])
+            .rule("S", &["A", "S"])
+            .terminal_rule("A", "a")
+            .terminal_rule("B", "b")
+            .build()
+            .unwrap();
+        assert_eq!(grammar.symbols().len(), 1005);
+
+        let bytes = CompiledGrammar::compile(&grammar);
+        let compiled = CompiledGrammar::from_bytes(&bytes).unwrap();
+        assert_eq!(compiled.symbols().len(), 5);
+        assert!(Parser::new(&compiled).parse("aab").is_ok());
+
+        let symbol = |name: &str| compiled.symbols().get(name).unwrap();
+        let first: HashSet<Symbol> = compiled.first(symbol("S")).unwrap();
+        assert_eq!(first, vec![symbol("A")].into_iter().collect());
+        assert_eq!(compiled.first(symbol("A")), None);
+    }
+}
@@ -1,8 +1,12 @@
-use super::tree::Span;
+use super::cnf::Production;
+use super::symbol::{DisplaySymbols, Symbol, SymbolTable};
+use super::tree::{NodeId, Span};
 
+use std::error::Error;
 use std::fmt;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Diagnostic {
     c: char,
     span: Span,
@@ -18,4 +22,254 @@ impl Diagnostic {
     pub fn new(c: char, span: Span) -> Self {
         Diagnostic { c, span }
     }
+
+    pub fn char(&self) -> char {
+        self.c
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// Raised when a tree uses a production no semantic action was given for.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MissingAction(pub Production);
+
+impl DisplaySymbols for MissingAction {
+    fn fmt_symbols(&self, symbols: &SymbolTable, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no action for production: {}", self.0.display(symbols))
+    }
+}
+
+impl fmt::Display for MissingAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_symbols(&SymbolTable::global(), f)
+    }
+}
+
+impl Error for MissingAction {}
+
+/// The failure of `Parser::parse_with`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EvalError {
+    Parse(Vec<Diagnostic>),
+    Action(MissingAction),
+}
+
+impl DisplaySymbols for EvalError {
+    fn fmt_symbols(&self, symbols: &SymbolTable, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Parse(diagnostics) => {
+                write!(f, "cannot parse the input")?;
+                for (index, diagnostic) in diagnostics.iter().enumerate() {
+                    let separator = if index == 0 { ": unknown" } else { "," };
+                    write!(
+                        f,
+                        "{} {:?} at {}",
+                        separator,
+                        diagnostic.char(),
+                        diagnostic.span()
+                    )?;
+                }
+                Ok(())
+            }
+            EvalError::Action(missing) => missing.fmt_symbols(symbols, f),
+        }
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_symbols(&SymbolTable::global(), f)
+    }
+}
+
+impl Error for EvalError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EvalError::Parse(_) => None,
+            EvalError::Action(missing) => Some(missing),
+        }
+    }
+}
+
+/// The failure of `AttributeGrammar::check` or `AttributeGrammar::evaluate`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AttributeError {
+    /// The attribute is not declared on the symbol.
+    Undeclared(Symbol, String),
+    /// The equation defines something other than a synthesized attribute of
+    /// the left side or an inherited attribute of a child.
+    InvalidTarget(Production, usize, String),
+    /// No equation defines the attribute at this position of the production.
+    MissingEquation(Production, usize, String),
+    /// Some tree built with the production has a circular dependency.
+    Circular(Production),
+    /// An inherited attribute of the root was not supplied.
+    MissingInherited(String),
+    /// The attribute of this node could not be evaluated.
+    Unevaluated(NodeId, String),
+}
+
+impl DisplaySymbols for AttributeError {
+    fn fmt_symbols(&self, symbols: &SymbolTable, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeError::Undeclared(symbol, attribute) => write!(
+                f,
+                "attribute {} is not declared on {}",
+                attribute,
+                symbol.display(symbols)
+            ),
+            AttributeError::InvalidTarget(production, position, attribute) => write!(
+                f,
+                "{}: cannot define attribute {} at position {}",
+                production.display(symbols),
+                attribute,
+                position
+            ),
+            AttributeError::MissingEquation(production, position, attribute) => write!(
+                f,
+                "{}: no equation for attribute {} at position {}",
+                production.display(symbols),
+                attribute,
+                position
+            ),
+            AttributeError::Circular(production) => write!(
+                f,
+                "{}: circular attribute dependency",
+                production.display(symbols)
+            ),
+            AttributeError::MissingInherited(attribute) => {
+                write!(
+                    f,
+                    "inherited attribute {} of the root is missing",
+                    attribute
+                )
+            }
+            AttributeError::Unevaluated(id, attribute) => write!(
+                f,
+                "attribute {} of node {} cannot be evaluated",
+                attribute,
+                id.index()
+            ),
+        }
+    }
+}
+
+impl fmt::Display for AttributeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_symbols(&SymbolTable::global(), f)
+    }
+}
+
+impl Error for AttributeError {}
+
+/// Raised when a tree cannot be read back from one of the export formats.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReadError {
+    message: String,
+    offset: usize,
+}
+
+impl ReadError {
+    pub fn new(message: &str, offset: usize) -> Self {
+        ReadError {
+            message: message.to_string(),
+            offset,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The byte offset in the input the error was found at.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl Error for ReadError {}
+
+/// Why `GrammarBuilder::build` or `CNF::validate` rejected a grammar.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GrammarError {
+    /// No start symbol was given.
+    MissingStart,
+    /// The symbol is used but never declared or defined by a rule.
+    Undefined(String),
+    /// The symbol is used both as a terminal and as a non-terminal, or is a
+    /// terminal given as the start symbol.
+    Clash(String),
+    /// The rule was given more than once.
+    DuplicateRule(String),
+    /// The rule is neither `A -> B C` nor `A -> t`.
+    NotCnf(String),
+}
+
+impl fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GrammarError::MissingStart => write!(f, "the grammar has no start symbol"),
+            GrammarError::Undefined(symbol) => write!(f, "symbol {} is not defined", symbol),
+            GrammarError::Clash(symbol) => {
+                write!(f, "symbol {} is both a terminal and a non-terminal", symbol)
+            }
+            GrammarError::DuplicateRule(rule) => write!(f, "rule {} is given twice", rule),
+            GrammarError::NotCnf(rule) => {
+                write!(f, "rule {} is not in Chomsky normal form", rule)
+            }
+        }
+    }
 }
+
+impl Error for GrammarError {}
+
+/// Why `CompiledGrammar::from_bytes` rejected a buffer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    /// The buffer does not start with the compiled grammar magic.
+    BadMagic,
+    /// The buffer was written by another version of the format.
+    Version { found: u32, expected: u32 },
+    /// The contents do not match the stored checksum.
+    Checksum { found: u32, expected: u32 },
+    /// The buffer ends before the data it announces.
+    Truncated,
+    /// The contents are well-formed but inconsistent.
+    Corrupt(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::BadMagic => write!(f, "not a compiled grammar"),
+            LoadError::Version { found, expected } => write!(
+                f,
+                "compiled grammar has format version {}, expected {}",
+                found, expected
+            ),
+            LoadError::Checksum { found, expected } => write!(
+                f,
+                "compiled grammar checksum is {:08x}, expected {:08x}",
+                found, expected
+            ),
+            LoadError::Truncated => write!(f, "compiled grammar is truncated"),
+            LoadError::Corrupt(message) => write!(f, "compiled grammar is corrupt: {}", message),
+        }
+    }
+}
+
+impl Error for LoadError {}
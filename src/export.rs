@@ -0,0 +1,738 @@
+use super::{Control, DisplaySymbols, NodeId, ReadError, Span, SymbolTable, Tree, Visitor};
+
+use std::fmt::Write;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Format {
+    Dot,
+    SExpr,
+    Bracketed,
+    Json,
+}
+
+/// Writes a tree in one of the export formats while walking it.
+struct Writer<'t> {
+    symbols: &'t SymbolTable,
+    format: Format,
+    out: String,
+}
+
+impl<'t> Writer<'t> {
+    fn name(&self, tree: &Tree, id: NodeId) -> String {
+        tree[id].kind().display(self.symbols).to_string()
+    }
+}
+
+impl<'t> Visitor for Writer<'t> {
+    fn enter(&mut self, tree: &Tree, id: NodeId) -> Control {
+        let node = &tree[id];
+        let name = self.name(tree, id);
+        let first = tree.prev_sibling(id).is_none();
+
+        match self.format {
+            Format::Dot => {
+                let shape = if node.is_leaf() {
+                    "plaintext"
+                } else {
+                    "ellipse"
+                };
+                let _ = writeln!(
+                    self.out,
+                    "  n{} [label={}, shape={}];",
+                    id.index(),
+                    quote(&name),
+                    shape
+                );
+                if let Some(parent) = tree.parent(id) {
+                    let _ = writeln!(self.out, "  n{} -> n{};", parent.index(), id.index());
+                }
+            }
+            Format::SExpr | Format::Bracketed => {
+                if tree.parent(id).is_some() {
+                    self.out.push(' ');
+                }
+                let atom = match self.format {
+                    Format::SExpr if node.is_leaf() => quote(&name),
+                    Format::SExpr => sexpr_atom(&name),
+                    _ => penn_atom(&name),
+                };
+                if node.is_leaf() {
+                    self.out.push_str(&atom);
+                } else {
+                    self.out.push('(');
+                    self.out.push_str(&atom);
+                }
+            }
+            Format::Json => {
+                if !first {
+                    self.out.push(',');
+                }
+                let span = node.span();
+                let _ = write!(
+                    self.out,
+                    "{{\"kind\":{},\"span\":{{\"start\":{},\"len\":{},\"byte_start\":{},\"byte_len\":{}}},\"children\":[",
+                    quote(&name),
+                    span.start(),
+                    span.len(),
+                    span.byte_start(),
+                    span.byte_len()
+                );
+            }
+        }
+
+        Control::Continue
+    }
+
+    fn exit(&mut self, tree: &Tree, id: NodeId) {
+        match self.format {
+            Format::Dot => {}
+            Format::SExpr | Format::Bracketed => {
+                if !tree[id].is_leaf() {
+                    self.out.push(')')
+                }
+            }
+            Format::Json => self.out.push_str("]}"),
+        }
+    }
+}
+
+fn quote(name: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in name.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn sexpr_atom(name: &str) -> String {
+    if name.is_empty()
+        || name
+            .chars()
+            .any(|c| c.is_whitespace() || c == '(' || c == ')' || c == '"')
+    {
+        quote(name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Writes parentheses as `-LRB-` and `-RRB-`, and escapes with a backslash
+/// the whitespace, the backslashes and the dashes that would otherwise be
+/// read back as the start of one of them.
+fn penn_atom(name: &str) -> String {
+    let mut atom = String::new();
+    for (index, c) in name.char_indices() {
+        match c {
+            '(' => atom.push_str("-LRB-"),
+            ')' => atom.push_str("-RRB-"),
+            '-' if is_penn_bracket(&name[index..]) => atom.push_str("\\-"),
+            c if c == '\\' || c.is_whitespace() => {
+                atom.push('\\');
+                atom.push(c);
+            }
+            c => atom.push(c),
+        }
+    }
+    atom
+}
+
+fn is_penn_bracket(rest: &str) -> bool {
+    rest.starts_with("-LRB-") || rest.starts_with("-RRB-")
+}
+
+impl Tree {
+    fn export(&self, symbols: &SymbolTable, format: Format) -> String {
+        let mut writer = Writer {
+            symbols,
+            format,
+            out: String::new(),
+        };
+        self.walk(self.root(), &mut writer);
+        writer.out
+    }
+
+    /// Renders the tree as a Graphviz digraph.
+    pub fn to_dot(&self, symbols: &SymbolTable) -> String {
+        format!("digraph tree {{\n{}}}\n", self.export(symbols, Format::Dot))
+    }
+
+    /// Renders the tree as `(Number (Integer "3") ...)`, quoting terminals.
+    pub fn to_sexpr(&self, symbols: &SymbolTable) -> String {
+        self.export(symbols, Format::SExpr)
+    }
+
+    /// Renders the tree in Penn Treebank notation, `(Number (Integer 3) ...)`,
+    /// writing parentheses in names as `-LRB-` and `-RRB-` and escaping
+    /// whitespace, backslashes and literal `-LRB-` or `-RRB-` with a
+    /// backslash, so that `from_bracketed` reads back the same names.
+    pub fn to_bracketed(&self, symbols: &SymbolTable) -> String {
+        self.export(symbols, Format::Bracketed)
+    }
+
+    /// Renders every node as `{"kind", "span", "children"}`.
+    pub fn to_json(&self, symbols: &SymbolTable) -> String {
+        self.export(symbols, Format::Json)
+    }
+
+    /// Reads a tree written by `to_bracketed`, interning its names into
+    /// `symbols`. Spans are recomputed from the leaves, one per char.
+    ///
+    /// A node with a single child stands for `A -> t`, so that child must be
+    /// a leaf.
+    pub fn from_bracketed(src: &str, symbols: &mut SymbolTable) -> Result<Tree, ReadError> {
+        let mut raws: Vec<Raw> = Vec::new();
+        let mut stack: Vec<usize> = Vec::new();
+        let mut root: Option<usize> = None;
+        let mut chars = src.char_indices().peekable();
+
+        while let Some(&(offset, c)) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+                continue;
+            }
+
+            if c == ')' {
+                chars.next();
+                if stack.pop().is_none() {
+                    return Err(ReadError::new("unbalanced `)`", offset));
+                }
+                continue;
+            }
+
+            let open = c == '(';
+            if open {
+                chars.next();
+            }
+
+            let mut name = String::new();
+            let mut read = false;
+            while let Some(&(at, c)) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' {
+                    break;
+                }
+                read = true;
+                chars.next();
+                match c {
+                    '\\' => match chars.next() {
+                        Some((_, c)) => name.push(c),
+                        None => return Err(ReadError::new("unfinished escape", at)),
+                    },
+                    '-' if is_penn_bracket(&src[at..]) => {
+                        name.push(if src[at..].starts_with("-LRB-") {
+                            '('
+                        } else {
+                            ')'
+                        });
+                        for _ in 0..4 {
+                            chars.next();
+                        }
+                    }
+                    c => name.push(c),
+                }
+            }
+            if !read {
+                return Err(ReadError::new("expected a name", offset));
+            }
+
+            let index = raws.len();
+            raws.push(Raw {
+                name,
+                offset,
+                span: None,
+                children: vec![],
+            });
+            match stack.last() {
+                Some(&parent) => raws[parent].children.push(index),
+                None if root.is_none() => root = Some(index),
+                None => return Err(ReadError::new("more than one tree", offset)),
+            }
+            if open {
+                stack.push(index);
+            }
+        }
+
+        if !stack.is_empty() {
+            return Err(ReadError::new("unbalanced `(`", src.len()));
+        }
+        match root {
+            Some(root) => build(raws, root, symbols),
+            None => Err(ReadError::new("empty input", 0)),
+        }
+    }
+
+    /// Reads a tree written by `to_json`, interning its names into `symbols`.
+    pub fn from_json(src: &str, symbols: &mut SymbolTable) -> Result<Tree, ReadError> {
+        let mut reader = JsonReader { src, offset: 0 };
+        let value = reader.value()?;
+        reader.skip_whitespace();
+        if reader.offset != src.len() {
+            return Err(ReadError::new("trailing characters", reader.offset));
+        }
+
+        let mut raws: Vec<Raw> = Vec::new();
+        let mut stack: Vec<(&Json, Option<usize>)> = vec![(&value, None)];
+        while let Some((value, parent)) = stack.pop() {
+            let name = match value.get("kind") {
+                Some((_, Json::String(name))) => name.clone(),
+                Some((offset, _)) => return Err(ReadError::new("`kind` is not a string", offset)),
+                None => return Err(ReadError::new("node without a `kind`", value.offset())),
+            };
+            let span = match value.get("span") {
+                Some((_, span)) => Some(Span::with_bytes(
+                    span.number("start")?,
+                    span.number("len")?,
+                    span.number("byte_start")?,
+                    span.number("byte_len")?,
+                )),
+                None => None,
+            };
+
+            let index = raws.len();
+            raws.push(Raw {
+                name,
+                offset: value.offset(),
+                span,
+                children: vec![],
+            });
+            if let Some(parent) = parent {
+                raws[parent].children.push(index);
+            }
+
+            match value.get("children") {
+                Some((_, Json::Array(children, _))) => {
+                    stack.extend(children.iter().rev().map(|child| (child, Some(index))))
+                }
+                None => {}
+                Some((offset, _)) => {
+                    return Err(ReadError::new("`children` is not an array", offset))
+                }
+            }
+        }
+
+        build(raws, 0, symbols)
+    }
+}
+
+/// A node read from a file, before it is moved into a `Tree`.
+struct Raw {
+    name: String,
+    /// Where the node starts in the file, for errors.
+    offset: usize,
+    span: Option<Span>,
+    children: Vec<usize>,
+}
+
+fn build(mut raws: Vec<Raw>, root: usize, symbols: &mut SymbolTable) -> Result<Tree, ReadError> {
+    // Missing spans are derived from the leaves: lengths bottom-up, then
+    // starts top-down.
+    let mut order: Vec<usize> = Vec::new();
+    let mut stack = vec![root];
+    while let Some(index) = stack.pop() {
+        let raw = &raws[index];
+        if raw.children.len() > 2 {
+            return Err(ReadError::new(
+                &format!("node `{}` has more than two children", raw.name),
+                raw.offset,
+            ));
+        }
+        if let [child] = raw.children[..] {
+            if !raws[child].children.is_empty() {
+                return Err(ReadError::new(
+                    &format!("node `{}` has a single child that is not a leaf", raw.name),
+                    raws[child].offset,
+                ));
+            }
+        }
+        order.push(index);
+        stack.extend(raws[index].children.iter().rev());
+    }
+
+    let mut lens: Vec<(usize, usize)> = vec![(0, 0); raws.len()];
+    for &index in order.iter().rev() {
+        let raw = &raws[index];
+        lens[index] = if raw.children.is_empty() {
+            (raw.name.chars().count(), raw.name.len())
+        } else {
+            raw.children.iter().fold((0, 0), |(len, byte_len), &child| {
+                (len + lens[child].0, byte_len + lens[child].1)
+            })
+        };
+    }
+
+    let mut starts: Vec<(usize, usize)> = vec![(0, 0); raws.len()];
+    for &index in &order {
+        let mut start = match raws[index].span {
+            Some(span) => (span.start(), span.byte_start()),
+            None => starts[index],
+        };
+        if raws[index].span.is_none() {
+            let (len, byte_len) = lens[index];
+            raws[index].span = Some(Span::with_bytes(start.0, len, start.1, byte_len));
+        }
+        for &child in &raws[index].children {
+            starts[child] = start;
+            start = (start.0 + lens[child].0, start.1 + lens[child].1);
+        }
+    }
+
+    let span = |raw: &Raw| raw.span.unwrap();
+    let mut tree = Tree::new(symbols.intern(&raws[root].name), span(&raws[root]));
+    let mut stack: Vec<(usize, NodeId)> = vec![(root, tree.root())];
+    while let Some((index, id)) = stack.pop() {
+        for &child in &raws[index].children {
+            let child_id = tree.push(id, symbols.intern(&raws[child].name), span(&raws[child]));
+            stack.push((child, child_id));
+        }
+    }
+
+    Ok(tree)
+}
+
+/// A JSON value; arrays and objects keep the offset they start at, and
+/// every field the offset of its value.
+enum Json {
+    /// `true`, `false` or `null`, none of which trees use.
+    Literal,
+    Number(f64),
+    String(String),
+    Array(Vec<Json>, usize),
+    Object(Vec<(String, usize, Json)>, usize),
+}
+
+impl Json {
+    fn offset(&self) -> usize {
+        match self {
+            Json::Array(_, offset) | Json::Object(_, offset) => *offset,
+            _ => 0,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<(usize, &Json)> {
+        match self {
+            Json::Object(fields, _) => fields
+                .iter()
+                .find(|(name, _, _)| name == key)
+                .map(|(_, offset, value)| (*offset, value)),
+            _ => None,
+        }
+    }
+
+    fn number(&self, key: &str) -> Result<usize, ReadError> {
+        match self.get(key) {
+            Some((_, Json::Number(n))) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+            found => Err(ReadError::new(
+                &format!("`{}` is not a non-negative integer", key),
+                found.map_or(self.offset(), |(offset, _)| offset),
+            )),
+        }
+    }
+}
+
+struct JsonReader<'s> {
+    src: &'s str,
+    offset: usize,
+}
+
+impl<'s> JsonReader<'s> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.offset..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+            self.bump();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ReadError> {
+        self.skip_whitespace();
+        match self.bump() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(ReadError::new(
+                &format!("expected `{}`", expected),
+                self.offset,
+            )),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<Json, ReadError> {
+        if self.src[self.offset..].starts_with(keyword) {
+            self.offset += keyword.len();
+            Ok(Json::Literal)
+        } else {
+            Err(ReadError::new("unexpected value", self.offset))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, ReadError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => {
+                let start = self.offset;
+                self.bump();
+                let mut fields = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.bump();
+                    return Ok(Json::Object(fields, start));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    self.skip_whitespace();
+                    let offset = self.offset;
+                    fields.push((key, offset, self.value()?));
+                    self.skip_whitespace();
+                    match self.bump() {
+                        Some(',') => continue,
+                        Some('}') => return Ok(Json::Object(fields, start)),
+                        _ => return Err(ReadError::new("expected `,` or `}`", self.offset)),
+                    }
+                }
+            }
+            Some('[') => {
+                let start = self.offset;
+                self.bump();
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(']') {
+                    self.bump();
+                    return Ok(Json::Array(items, start));
+                }
+                loop {
+                    items.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.bump() {
+                        Some(',') => continue,
+                        Some(']') => return Ok(Json::Array(items, start)),
+                        _ => return Err(ReadError::new("expected `,` or `]`", self.offset)),
+                    }
+                }
+            }
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('t') => self.keyword("true"),
+            Some('f') => self.keyword("false"),
+            Some('n') => self.keyword("null"),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.offset;
+                while matches!(self.peek(), Some(c) if c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' || c.is_ascii_digit())
+                {
+                    self.bump();
+                }
+                self.src[start..self.offset]
+                    .parse()
+                    .map(Json::Number)
+                    .map_err(|_| ReadError::new("invalid number", start))
+            }
+            _ => Err(ReadError::new("expected a value", self.offset)),
+        }
+    }
+
+    /// Reads the four hex digits of a `\u` escape.
+    fn hex4(&mut self) -> Option<u32> {
+        let hex = self.src.get(self.offset..self.offset + 4)?;
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        self.offset += 4;
+        u32::from_str_radix(hex, 16).ok()
+    }
+
+    fn string(&mut self) -> Result<String, ReadError> {
+        if self.bump() != Some('"') {
+            return Err(ReadError::new("expected a string", self.offset));
+        }
+
+        let mut string = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(string),
+                Some('\\') => match self.bump() {
+                    Some('n') => string.push('\n'),
+                    Some('r') => string.push('\r'),
+                    Some('t') => string.push('\t'),
+                    Some('b') => string.push('\u{8}'),
+                    Some('f') => string.push('\u{c}'),
+                    Some('u') => {
+                        let start = self.offset;
+                        let invalid = || ReadError::new("invalid escape", start);
+                        let mut code = self.hex4().ok_or_else(invalid)?;
+                        // Chars outside the basic plane come as a pair of
+                        // surrogates, `\ud83d\ude00`.
+                        if (0xd800..0xdc00).contains(&code) {
+                            if !self.src[self.offset..].starts_with("\\u") {
+                                return Err(invalid());
+                            }
+                            self.offset += 2;
+                            let low = self.hex4().ok_or_else(invalid)?;
+                            if !(0xdc00..0xe000).contains(&low) {
+                                return Err(invalid());
+                            }
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                        }
+                        string.push(std::char::from_u32(code).ok_or_else(invalid)?);
+                    }
+                    Some(c) => string.push(c),
+                    None => return Err(ReadError::new("unterminated string", self.offset)),
+                },
+                Some(c) => string.push(c),
+                None => return Err(ReadError::new("unterminated string", self.offset)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    fn expr_grammar() -> CNF {
+        cnf_grammar! {
+            Start("Group");
+            NonTerminals["Group", "Open", "Rest", "Digit", "Close"];
+            Terminals["(", ")", "1", "2"];
+            Rules [
+                "Group" => [["Open", "Rest"]],
+                "Rest" => [["Digit", "Close"]]
+            ];
+            TerminalRules [
+                "Open" => ["("],
+                "Close" => [")"],
+                "Digit" => ["1", "2"]
+            ]
+        }
+    }
+
+    #[test]
+    fn export_formats() {
+        let grammar = expr_grammar();
+        let symbols = grammar.symbols();
+        let tree = Parser::new(&grammar).parse("(1)").unwrap();
+
+        assert_eq!(
+            tree.to_sexpr(symbols),
+            r#"(Group (Open "(") (Rest (Digit "1") (Close ")")))"#
+        );
+        assert_eq!(
+            tree.to_bracketed(symbols),
+            "(Group (Open -LRB-) (Rest (Digit 1) (Close -RRB-)))"
+        );
+        assert!(tree
+            .to_json(symbols)
+            .starts_with(r#"{"kind":"Group","span":{"start":0,"len":3,"byte_start":0,"byte_len":3},"children":[{"kind":"Open""#));
+
+        let dot = tree.to_dot(symbols);
+        assert!(dot.starts_with("digraph tree {\n"));
+        assert!(dot.contains("n0 -> n1;"));
+        assert!(dot.contains(r#"[label="(", shape=plaintext]"#));
+    }
+
+    #[test]
+    fn round_trips() {
+        let grammar = expr_grammar();
+        let mut symbols = grammar.symbols().clone();
+        let tree = Parser::new(&grammar).parse("(2)").unwrap();
+
+        let bracketed = tree.to_bracketed(&symbols);
+        let read = Tree::from_bracketed(&bracketed, &mut symbols).unwrap();
+        assert_eq!(read.to_bracketed(&symbols), bracketed);
+        assert_eq!(read[read.root()].span(), tree[tree.root()].span());
+
+        let json = tree.to_json(&symbols);
+        let read = Tree::from_json(&json, &mut symbols).unwrap();
+        assert_eq!(read.to_json(&symbols), json);
+
+        let mut fresh = SymbolTable::new();
+        let read = Tree::from_bracketed("(S (A é) (B b))", &mut fresh).unwrap();
+        let (left, right) = match read[read.root()].children() {
+            NodeChildren::Double(left, right) => (left, right),
+            _ => panic!("Error"),
+        };
+        assert_eq!(read[left].span(), Span::with_bytes(0, 1, 0, 2));
+        assert_eq!(read[right].span(), Span::with_bytes(1, 1, 2, 1));
+
+        assert!(Tree::from_bracketed("(S (A a)", &mut fresh).is_err());
+        assert!(Tree::from_bracketed("(S a b c)", &mut fresh).is_err());
+        assert!(Tree::from_json(r#"{"kind": 1}"#, &mut fresh).is_err());
+    }
+
+    #[test]
+    fn round_trip_names() {
+        let mut symbols = SymbolTable::new();
+        let names = ["(-LRB-)", "a b", "\\", "-RRB", "-"];
+        let mut tree = Tree::new(symbols.intern("S"), Span::with_bytes(0, 0, 0, 0));
+        let left = tree.push(
+            tree.root(),
+            symbols.intern(names[0]),
+            Span::with_bytes(0, 0, 0, 0),
+        );
+        tree.push(left, symbols.intern(names[1]), Span::with_bytes(0, 0, 0, 0));
+        let right = tree.push(
+            tree.root(),
+            symbols.intern(names[2]),
+            Span::with_bytes(0, 0, 0, 0),
+        );
+        tree.push(
+            right,
+            symbols.intern(names[3]),
+            Span::with_bytes(0, 0, 0, 0),
+        );
+        tree.push(
+            right,
+            symbols.intern(names[4]),
+            Span::with_bytes(0, 0, 0, 0),
+        );
+
+        let bracketed = tree.to_bracketed(&symbols);
+        assert_eq!(bracketed, r"(S (-LRB-\-LRB--RRB- a\ b) (\\ -RRB -))");
+        let read = Tree::from_bracketed(&bracketed, &mut symbols).unwrap();
+        let read_names: Vec<&str> = read
+            .pre_order(read.root())
+            .skip(1)
+            .map(|id| symbols.resolve(read[id].kind()).unwrap())
+            .collect();
+        assert_eq!(read_names, ["(-LRB-)", "a b", "\\", "-RRB", "-"]);
+
+        let read = Tree::from_json(r#"{"kind":"\ud83d\ude00"}"#, &mut symbols).unwrap();
+        assert_eq!(symbols.resolve(read[read.root()].kind()), Some("\u{1f600}"));
+        assert!(Tree::from_json(r#"{"kind":"\ud83d"}"#, &mut symbols).is_err());
+        assert!(Tree::from_json(r#"{"kind":"\ude00"}"#, &mut symbols).is_err());
+    }
+
+    #[test]
+    fn read_error_offsets() {
+        let mut symbols = SymbolTable::new();
+        let offset = |result: Result<Tree, ReadError>| result.unwrap_err().offset();
+
+        let chain = "(S (A (B b)) (C c))";
+        assert_eq!(offset(Tree::from_bracketed(chain, &mut symbols)), 6);
+        assert_eq!(offset(Tree::from_bracketed("(S a b c)", &mut symbols)), 0);
+
+        let json = r#"{"kind":"S","children":[{"kind":"A"},{"kind":1}]}"#;
+        assert_eq!(offset(Tree::from_json(json, &mut symbols)), 45);
+        let json = r#"{"kind":"S","children":[{"span":{}}]}"#;
+        assert_eq!(offset(Tree::from_json(json, &mut symbols)), 24);
+        let json = r#"{"kind":"S","span":{"start":-1}}"#;
+        assert_eq!(offset(Tree::from_json(json, &mut symbols)), 28);
+        let json = r#"{"kind":"S","children":{}}"#;
+        assert_eq!(offset(Tree::from_json(json, &mut symbols)), 23);
+    }
+}
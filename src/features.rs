@@ -0,0 +1,524 @@
+use super::cfg::{read_grammar, resolve_rules, Binarizer, RawRule, Token};
+use super::{
+    Grammar, HashSet, Production, ReadError, Rules, Symbol, SymbolTable, TerminalRules, CNF,
+};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// The value of a feature: an atom such as `sg`, or a nested structure.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FeatureValue {
+    Atom(String),
+    Structure(Features),
+}
+
+impl FeatureValue {
+    /// Returns the most general value both values describe, or `None` if
+    /// they disagree.
+    pub fn unify(&self, other: &FeatureValue) -> Option<FeatureValue> {
+        match (self, other) {
+            (FeatureValue::Atom(first), FeatureValue::Atom(second)) if first == second => {
+                Some(self.clone())
+            }
+            (FeatureValue::Structure(first), FeatureValue::Structure(second)) => {
+                first.unify(second).map(FeatureValue::Structure)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FeatureValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeatureValue::Atom(atom) => write!(f, "{}", atom),
+            FeatureValue::Structure(features) => write!(f, "{}", features),
+        }
+    }
+}
+
+/// A feature structure: named features whose values are atoms or nested
+/// structures, written `[agr=[num=sg, per=3], case=nom]`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Features(BTreeMap<String, FeatureValue>);
+
+impl Features {
+    pub fn new() -> Self {
+        Features(BTreeMap::new())
+    }
+
+    pub fn insert(&mut self, name: &str, value: FeatureValue) {
+        self.0.insert(name.to_string(), value);
+    }
+
+    /// Returns the value at a dotted `path` such as `agr.num`.
+    pub fn get(&self, path: &str) -> Option<&FeatureValue> {
+        self.at(&path.split('.').collect::<Vec<&str>>())
+    }
+
+    /// Returns the atom at a dotted `path`, if the value there is one.
+    pub fn atom(&self, path: &str) -> Option<&str> {
+        match self.get(path)? {
+            FeatureValue::Atom(atom) => Some(atom),
+            FeatureValue::Structure(_) => None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FeatureValue)> {
+        self.0.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// Merges two structures, or returns `None` if they give some feature
+    /// values that do not unify.
+    pub fn unify(&self, other: &Features) -> Option<Features> {
+        let mut result = self.0.clone();
+        for (name, value) in &other.0 {
+            let merged = match result.get(name) {
+                Some(known) => known.unify(value)?,
+                None => value.clone(),
+            };
+            result.insert(name.clone(), merged);
+        }
+        Some(Features(result))
+    }
+
+    fn at<S: AsRef<str>>(&self, path: &[S]) -> Option<&FeatureValue> {
+        let (last, path) = path.split_last()?;
+        let mut features = self;
+        for name in path {
+            match features.0.get(name.as_ref())? {
+                FeatureValue::Structure(nested) => features = nested,
+                FeatureValue::Atom(_) => return None,
+            }
+        }
+        features.0.get(last.as_ref())
+    }
+
+    /// Unifies the value at `path` with `value`.
+    fn unify_at<S: AsRef<str>>(&self, path: &[S], value: &FeatureValue) -> Option<Features> {
+        let mut nested = value.clone();
+        for name in path.iter().rev() {
+            let mut features = Features::new();
+            features.insert(name.as_ref(), nested);
+            nested = FeatureValue::Structure(features);
+        }
+        match nested {
+            FeatureValue::Structure(features) => self.unify(&features),
+            FeatureValue::Atom(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Features {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (index, (name, value)) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}={}", name, value)?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// A unification equation of a production. Positions are 0 for the left
+/// side, and 1 and 2 for the children.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FeatureEquation {
+    /// `position.path = atom`
+    Value(usize, Vec<String>, String),
+    /// `position.path = position.path`
+    Share(usize, Vec<String>, usize, Vec<String>),
+}
+
+impl fmt::Display for FeatureEquation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeatureEquation::Value(position, path, atom) => {
+                write!(f, "{}.{} = {}", position, path.join("."), atom)
+            }
+            FeatureEquation::Share(position, path, other, other_path) => write!(
+                f,
+                "{}.{} = {}.{}",
+                position,
+                path.join("."),
+                other,
+                other_path.join(".")
+            ),
+        }
+    }
+}
+
+/// Applies `equations` to the features of the left side and children of a
+/// production until none of them adds anything, or returns `None` if they
+/// cannot all hold.
+pub(crate) fn unify_production(
+    equations: &[FeatureEquation],
+    mut nodes: [Features; 3],
+) -> Option<[Features; 3]> {
+    loop {
+        let mut changed = false;
+        let mut set = |nodes: &mut [Features; 3], position: usize, path, value: &FeatureValue| {
+            let next = nodes[position].unify_at(path, value)?;
+            if next != nodes[position] {
+                nodes[position] = next;
+                changed = true;
+            }
+            Some(())
+        };
+
+        for equation in equations {
+            match equation {
+                FeatureEquation::Value(position, path, atom) => {
+                    set(
+                        &mut nodes,
+                        *position,
+                        path,
+                        &FeatureValue::Atom(atom.clone()),
+                    )?;
+                }
+                FeatureEquation::Share(position, path, other, other_path) => {
+                    let value = match (nodes[*position].at(path), nodes[*other].at(other_path)) {
+                        (Some(first), Some(second)) => first.unify(second)?,
+                        (Some(value), None) | (None, Some(value)) => value.clone(),
+                        (None, None) => continue,
+                    };
+                    set(&mut nodes, *position, path, &value)?;
+                    set(&mut nodes, *other, other_path, &value)?;
+                }
+            }
+        }
+
+        if !changed {
+            return Some(nodes);
+        }
+    }
+}
+
+/// Reads the features written after the symbol at `position`, such as
+/// `num=sg, agr.per=?p`, into equations. The first symbol a variable is
+/// met on gives it its value, and the later ones share it.
+fn read_features(
+    text: &str,
+    position: usize,
+    offset: usize,
+    variables: &mut HashMap<String, (usize, Vec<String>)>,
+    equations: &mut Vec<FeatureEquation>,
+) -> Result<(), ReadError> {
+    for feature in text.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        let (path, value) = match feature.split_once('=') {
+            Some((path, value)) => (path.trim(), value.trim()),
+            None => return Err(ReadError::new("expected `feature=value`", offset)),
+        };
+        let path: Vec<String> = path.split('.').map(str::to_string).collect();
+        if value.is_empty() || path.iter().any(|name| name.is_empty()) {
+            return Err(ReadError::new("expected `feature=value`", offset));
+        }
+
+        match value.strip_prefix('?') {
+            Some(variable) => match variables.get(variable) {
+                Some((first, first_path)) => equations.push(FeatureEquation::Share(
+                    *first,
+                    first_path.clone(),
+                    position,
+                    path,
+                )),
+                None => {
+                    variables.insert(variable.to_string(), (position, path));
+                }
+            },
+            None => equations.push(FeatureEquation::Value(position, path, value.to_string())),
+        }
+    }
+
+    Ok(())
+}
+
+/// A grammar whose symbols carry feature structures, and whose rules carry
+/// unification equations between the features of their left side and of
+/// their children.
+///
+/// Its text form extends the one of `Cfg` with features in brackets after
+/// a non-terminal; a value starting with `?` is a variable shared by the
+/// symbols of the rule. A rule is either two non-terminals or a word of
+/// terminals; words are split as `Cfg::to_cnf` does, and the features of
+/// their left side go on the first of the productions.
+///
+/// ```text
+/// S -> NP[num=?n] VP[num=?n];
+/// NP[num=?n] -> Det[num=?n] N[num=?n];
+/// Det[num=sg] -> 'this ';
+/// Det -> 'the ';
+/// N[num=sg] -> 'dog';
+/// N -> 'sheep';
+/// VP[num=sg] -> ' runs';
+/// ```
+///
+/// `Parser` unifies the features while filling the chart, so that an entry
+/// is kept only with features its rule's equations agree with, and a
+/// symbol may be found several times over a span with different features.
+/// The nodes of the tree it returns carry their features once unified with
+/// those of their ancestors.
+#[derive(Debug, Clone)]
+pub struct FeatureGrammar {
+    cnf: CNF,
+    /// The equations of every production, one list for each rule it was
+    /// written in.
+    equations: HashMap<Production, Vec<Vec<FeatureEquation>>>,
+}
+
+impl FeatureGrammar {
+    /// Reads a grammar in the text form described on `FeatureGrammar`.
+    pub fn parse(src: &str) -> Result<FeatureGrammar, ReadError> {
+        let (start, rules) = read_grammar(src)?;
+
+        let defined: HashSet<&str> = rules.iter().map(|rule| rule.left.as_str()).collect();
+        let mut terminals: HashSet<Symbol> = HashSet::new();
+        let mut table = SymbolTable::new();
+        let mut alternatives: Vec<(Symbol, Vec<Symbol>, Vec<FeatureEquation>)> = Vec::new();
+        for rule in &rules {
+            for alternative in &rule.alternatives {
+                let mut variables: HashMap<String, (usize, Vec<String>)> = HashMap::new();
+                let mut equations: Vec<FeatureEquation> = Vec::new();
+                if let Some((text, offset)) = &rule.features {
+                    read_features(text, 0, *offset, &mut variables, &mut equations)?;
+                }
+
+                let mut symbols = Vec::new();
+                for (token, offset) in alternative {
+                    match token {
+                        Token::Features(text) => match symbols.last() {
+                            Some((Token::Name(_), _)) => read_features(
+                                text,
+                                symbols.len(),
+                                *offset,
+                                &mut variables,
+                                &mut equations,
+                            )?,
+                            _ => {
+                                return Err(ReadError::new(
+                                    "features must follow a non-terminal",
+                                    *offset,
+                                ))
+                            }
+                        },
+                        _ => symbols.push((token.clone(), *offset)),
+                    }
+                }
+
+                let offset = symbols.first().map_or(rule.offset, |&(_, offset)| offset);
+                let plain = RawRule {
+                    left: rule.left.clone(),
+                    offset: rule.offset,
+                    features: None,
+                    alternatives: vec![symbols],
+                };
+                let (left, right) =
+                    resolve_rules(&[plain], &defined, &mut terminals, &mut table)?.remove(0);
+
+                let word = !right.is_empty() && right.iter().all(|s| terminals.contains(s));
+                let pair = right.len() == 2 && right.iter().all(|s| !terminals.contains(s));
+                if !word && !pair {
+                    return Err(ReadError::new(
+                        "a rule needs two non-terminals or only terminals",
+                        offset,
+                    ));
+                }
+                alternatives.push((left, right, equations));
+            }
+        }
+
+        let names = defined.iter().map(|name| table.intern(name)).collect();
+        let start = table.intern(&start);
+        let mut binarizer = Binarizer::new(table, names, terminals.clone());
+        let mut equations: HashMap<Production, Vec<Vec<FeatureEquation>>> = HashMap::new();
+        for (left, right, rule) in alternatives {
+            let production = match right[..] {
+                [terminal] => {
+                    binarizer.terminal(left, terminal);
+                    Production::Terminal(left, terminal)
+                }
+                _ => {
+                    let (first, second) = binarizer.pair(left, &right);
+                    binarizer.binary(left, first, second);
+                    Production::Binary(left, first, second)
+                }
+            };
+            equations.entry(production).or_default().push(rule);
+        }
+
+        Ok(FeatureGrammar {
+            cnf: binarizer.build(start),
+            equations,
+        })
+    }
+}
+
+impl Grammar for FeatureGrammar {
+    fn start_symbol(&self) -> Symbol {
+        self.cnf.start_symbol()
+    }
+
+    fn exist(&self, symbol: Symbol) -> bool {
+        self.cnf.exist(symbol)
+    }
+
+    fn first(&self, symbol: Symbol) -> Option<HashSet<Symbol>> {
+        self.cnf.first(symbol)
+    }
+
+    fn follow(&self, symbol: Symbol) -> Option<HashSet<Symbol>> {
+        self.cnf.follow(symbol)
+    }
+
+    fn derive(&self, base: Symbol, suffix: Symbol) -> Option<HashSet<Symbol>> {
+        self.cnf.derive(base, suffix)
+    }
+
+    fn derive_single(&self, base: Symbol) -> Option<HashSet<Symbol>> {
+        self.cnf.derive_single(base)
+    }
+
+    fn is_terminal(&self, input: Symbol) -> bool {
+        self.cnf.is_terminal(input)
+    }
+
+    fn is_non_terminal(&self, input: Symbol) -> bool {
+        self.cnf.is_non_terminal(input)
+    }
+
+    fn terminals(&self) -> &HashSet<Symbol> {
+        self.cnf.terminals()
+    }
+
+    fn non_terminals(&self) -> &HashSet<Symbol> {
+        self.cnf.non_terminals()
+    }
+
+    fn rules(&self) -> &Rules {
+        self.cnf.rules()
+    }
+
+    fn terminal_rules(&self) -> &TerminalRules {
+        self.cnf.terminal_rules()
+    }
+
+    fn symbols(&self) -> &SymbolTable {
+        self.cnf.symbols()
+    }
+
+    fn feature_equations(&self, production: Production) -> Option<&[Vec<FeatureEquation>]> {
+        self.equations.get(&production).map(Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn unify_features() {
+        let mut singular = Features::new();
+        singular.insert("num", FeatureValue::Atom("sg".to_string()));
+        let mut agreement = Features::new();
+        agreement.insert("agr", FeatureValue::Structure(singular.clone()));
+        agreement.insert("case", FeatureValue::Atom("nom".to_string()));
+
+        let mut third = Features::new();
+        third.insert("per", FeatureValue::Atom("3".to_string()));
+        let mut other = Features::new();
+        other.insert("agr", FeatureValue::Structure(third));
+
+        let unified = agreement.unify(&other).unwrap();
+        assert_eq!(unified.to_string(), "[agr=[num=sg, per=3], case=nom]");
+        assert_eq!(unified.atom("agr.num"), Some("sg"));
+        assert_eq!(unified.get("agr.gender"), None);
+
+        let mut plural = Features::new();
+        plural.insert("num", FeatureValue::Atom("pl".to_string()));
+        assert_eq!(singular.unify(&plural), None);
+    }
+
+    #[test]
+    fn enforce_agreement() {
+        let grammar = FeatureGrammar::parse(
+            "S -> NP[num=?n] VP[num=?n];
+             NP[num=?n] -> Det[num=?n] N[num=?n];
+             Det[num=sg] -> 'this ';
+             Det[num=pl] -> 'these ';
+             Det -> 'the ';
+             N[num=sg] -> 'dog';
+             N[num=pl] -> 'dogs';
+             N -> 'sheep';
+             VP[num=sg] -> ' runs';
+             VP[num=pl] -> ' run';",
+        )
+        .unwrap();
+        let mut parser = Parser::new(&grammar);
+        for sentence in [
+            "this dog runs",
+            "these dogs run",
+            "the sheep runs",
+            "these sheep run",
+        ] {
+            assert!(parser.parse(sentence).is_ok(), "{}", sentence);
+        }
+        for sentence in [
+            "this dogs run",
+            "these dog runs",
+            "this sheep run",
+            "the dogs runs",
+        ] {
+            assert!(parser.parse(sentence).is_err(), "{}", sentence);
+        }
+
+        let tree = parser.parse("the sheep run").unwrap();
+        let features: Vec<String> = tree
+            .iter()
+            .filter(|(_, node)| {
+                ["NP", "Det", "N"].contains(&grammar.symbols().resolve(node.kind()).unwrap())
+            })
+            .map(|(_, node)| node.features().unwrap().to_string())
+            .collect();
+        assert_eq!(features, vec!["[num=pl]"; 3]);
+
+        let error = FeatureGrammar::parse("S -> A 'a'; A -> 'a';").unwrap_err();
+        assert_eq!(
+            (error.message(), error.offset()),
+            ("a rule needs two non-terminals or only terminals", 5)
+        );
+        assert!(Cfg::parse("S -> A[num=sg] A; A -> 'a';").is_err());
+    }
+
+    #[test]
+    fn pick_agreeing_children() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Parser<'static, FeatureGrammar>>();
+
+        let grammar = FeatureGrammar::parse(
+            "S -> NP[num=?n] VP[num=?n];
+             NP[num=?n] -> Det N[num=?n];
+             Det -> 'the ';
+             N[num=sg] -> 'fish';
+             N[num=pl] -> 'fish';
+             VP[num=pl] -> ' swim';",
+        )
+        .unwrap();
+        let tree = Parser::new(&grammar).parse("the fish swim").unwrap();
+        let n = grammar.symbols().get("N").unwrap();
+        let node = &tree[tree.find_all(n)[0]];
+        assert_eq!(node.features().unwrap().to_string(), "[num=pl]");
+    }
+}
@@ -0,0 +1,694 @@
+use super::{Cfg, Grammar, Symbol};
+
+use std::collections::HashMap;
+
+/// A small seedable random number generator (SplitMix64), so that the same
+/// seed gives the same sentences on every platform.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`, which must not be zero.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Returns a number in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns an index picked in proportion to the exponentials of
+    /// `log_weights`, or `None` if they are all zero.
+    fn pick_log(&mut self, log_weights: &[f64]) -> Option<usize> {
+        let max = log_weights
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if max == f64::NEG_INFINITY {
+            return None;
+        }
+
+        let weights: Vec<f64> = log_weights.iter().map(|w| (w - max).exp()).collect();
+        let mut target = self.next_f64() * weights.iter().sum::<f64>();
+        for (index, weight) in weights.iter().enumerate() {
+            if target < *weight {
+                return Some(index);
+            }
+            target -= weight;
+        }
+        weights.iter().rposition(|&weight| weight > 0.0)
+    }
+}
+
+/// How a `Generator` chooses between the productions of a symbol.
+///
+/// Every mode takes the weights given with `Generator::weight` into account,
+/// which default to 1.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Sampling {
+    /// Expands the start symbol top-down, picking each production in
+    /// proportion to its weight, as a PCFG does. Past `max_depth` only the
+    /// productions that finish the derivation soonest are picked; sentences
+    /// longer than `max_length` are drawn again.
+    Productions,
+    /// Picks a derivation tree among all those no deeper than `max_depth`
+    /// and no longer than `max_length`, in proportion to the product of its
+    /// weights: uniformly when the grammar is unweighted.
+    Derivations,
+    /// Picks a sentence of exactly this many terminals, uniformly when the
+    /// grammar is unweighted, however many trees each sentence has.
+    ///
+    /// A tree is drawn as for `Derivations` and its sentence is kept with
+    /// probability one over the number of its trees, so that weighted
+    /// sentences come in proportion to the mean weight of their trees. A
+    /// grammar so ambiguous that a thousand draws in a row are turned down
+    /// gets the last sentence drawn instead.
+    Length(usize),
+}
+
+/// Samples sentences of a grammar by expanding its start symbol with
+/// productions picked at random.
+///
+/// The limits are counted in derivation tree height and in terminals. The
+/// tables behind `Sampling::Derivations` and `Sampling::Length` are counted
+/// in log space, so they do not overflow on deep trees.
+#[derive(Clone, Debug)]
+pub struct Generator {
+    start: Symbol,
+    terminals: HashMap<Symbol, String>,
+    productions: Vec<(Symbol, Vec<Symbol>)>,
+    weights: Vec<f64>,
+    rules: HashMap<Symbol, Vec<usize>>,
+    heights: HashMap<Symbol, usize>,
+    rng: Rng,
+    sampling: Sampling,
+    max_depth: usize,
+    max_length: Option<usize>,
+    tables: Option<Tables>,
+}
+
+/// Log counts of derivation trees, indexed by the height bound.
+#[derive(Clone, Debug)]
+enum Tables {
+    ByHeight(Vec<HashMap<Symbol, f64>>),
+    ByLength(Vec<HashMap<Symbol, Vec<f64>>>),
+}
+
+/// The number of times `Sampling::Productions` draws a sentence before
+/// giving up on `max_length` and counting instead.
+const DRAWS: usize = 100;
+
+/// The number of sentences `Sampling::Length` turns down before keeping one
+/// whatever its number of trees.
+const REJECTIONS: usize = 1000;
+
+fn log_add(a: f64, b: f64) -> f64 {
+    if a == f64::NEG_INFINITY {
+        return b;
+    }
+    if b == f64::NEG_INFINITY {
+        return a;
+    }
+    let max = a.max(b);
+    max + ((a - max).exp() + (b - max).exp()).ln()
+}
+
+/// Returns the log counts of pairs of trees, by total length, given those
+/// of the first and of the second tree.
+fn convolve(first: &[f64], second: &[f64]) -> Vec<f64> {
+    let mut result = vec![f64::NEG_INFINITY; first.len()];
+    for (m, total) in result.iter_mut().enumerate() {
+        for j in 0..=m {
+            *total = log_add(*total, first[m - j] + second[j]);
+        }
+    }
+    result
+}
+
+impl Generator {
+    pub fn new<G: Grammar>(grammar: &G, seed: u64) -> Self {
+        Generator::from_cfg(&Cfg::from_grammar(grammar), seed)
+    }
+
+    pub fn from_cfg(cfg: &Cfg, seed: u64) -> Self {
+        let mut rules: HashMap<Symbol, Vec<usize>> = HashMap::new();
+        for (index, (left, _)) in cfg.productions().iter().enumerate() {
+            rules.entry(*left).or_default().push(index);
+        }
+
+        let mut generator = Generator {
+            start: cfg.start(),
+            terminals: cfg
+                .terminals()
+                .iter()
+                .map(|&terminal| {
+                    let name = cfg.symbols().resolve(terminal).unwrap_or("");
+                    (terminal, name.to_string())
+                })
+                .collect(),
+            productions: cfg.productions().to_vec(),
+            weights: vec![1.0; cfg.productions().len()],
+            rules,
+            heights: HashMap::new(),
+            rng: Rng::new(seed),
+            sampling: Sampling::Productions,
+            max_depth: 32,
+            max_length: None,
+            tables: None,
+        };
+        generator.heights = generator.min_heights();
+        generator
+    }
+
+    pub fn sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self.tables = None;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self.tables = None;
+        self
+    }
+
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self.tables = None;
+        self
+    }
+
+    /// Sets the weight of the production `left -> right`, if the grammar
+    /// has it. Weights need not sum to one, and a weight of zero keeps the
+    /// production out of every sentence.
+    pub fn weight(mut self, left: Symbol, right: &[Symbol], weight: f64) -> Self {
+        for (index, production) in self.productions.iter().enumerate() {
+            if production.0 == left && production.1 == right {
+                self.weights[index] = weight.max(0.0);
+            }
+        }
+        self.heights = self.min_heights();
+        self.tables = None;
+        self
+    }
+
+    fn is_terminal(&self, symbol: Symbol) -> bool {
+        self.terminals.contains_key(&symbol)
+    }
+
+    fn rules(&self, symbol: Symbol) -> &[usize] {
+        self.rules
+            .get(&symbol)
+            .map_or(&[], |rules| rules.as_slice())
+    }
+
+    /// Returns, for every productive non-terminal, the height of its
+    /// shallowest derivation tree.
+    fn min_heights(&self) -> HashMap<Symbol, usize> {
+        let mut heights: HashMap<Symbol, usize> = HashMap::new();
+
+        loop {
+            let mut changed = false;
+
+            for (index, (left, right)) in self.productions.iter().enumerate() {
+                if self.weights[index] == 0.0 {
+                    continue;
+                }
+                let height = match self.height(&heights, right) {
+                    Some(height) => height,
+                    None => continue,
+                };
+                if heights.get(left).is_none_or(|&known| height < known) {
+                    heights.insert(*left, height);
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                return heights;
+            }
+        }
+    }
+
+    fn height(&self, heights: &HashMap<Symbol, usize>, right: &[Symbol]) -> Option<usize> {
+        let mut height = 0;
+        for symbol in right {
+            if !self.is_terminal(*symbol) {
+                height = height.max(*heights.get(symbol)?);
+            }
+        }
+        Some(height + 1)
+    }
+
+    /// Counts the trees of every non-terminal of height at most `h`, for
+    /// every `h` up to `max_depth`.
+    fn count_by_height(&self) -> Vec<HashMap<Symbol, f64>> {
+        let mut tables: Vec<HashMap<Symbol, f64>> = vec![HashMap::new()];
+        for _ in 0..self.max_depth {
+            let below = tables.last().unwrap();
+            let mut table: HashMap<Symbol, f64> = HashMap::new();
+            for (index, (left, right)) in self.productions.iter().enumerate() {
+                let count = self.weights[index].ln() + self.log_product(below, right);
+                let total = table.entry(*left).or_insert(f64::NEG_INFINITY);
+                *total = log_add(*total, count);
+            }
+            tables.push(table);
+        }
+        tables
+    }
+
+    fn log_product(&self, below: &HashMap<Symbol, f64>, right: &[Symbol]) -> f64 {
+        right
+            .iter()
+            .filter(|symbol| !self.is_terminal(**symbol))
+            .map(|symbol| below.get(symbol).copied().unwrap_or(f64::NEG_INFINITY))
+            .sum()
+    }
+
+    /// Counts the trees of every non-terminal of height at most `h` by the
+    /// number of terminals they yield, up to `max_length`.
+    fn count_by_length(&self, max_length: usize) -> Vec<HashMap<Symbol, Vec<f64>>> {
+        let mut tables: Vec<HashMap<Symbol, Vec<f64>>> = vec![HashMap::new()];
+        for _ in 0..self.max_depth {
+            let below = tables.last().unwrap();
+            let mut table: HashMap<Symbol, Vec<f64>> = HashMap::new();
+            for (index, (left, right)) in self.productions.iter().enumerate() {
+                let counts = self.sequence_counts(below, right, max_length);
+                let total = table
+                    .entry(*left)
+                    .or_insert_with(|| vec![f64::NEG_INFINITY; max_length + 1]);
+                for (total, count) in total.iter_mut().zip(&counts[0]) {
+                    *total = log_add(*total, self.weights[index].ln() + count);
+                }
+            }
+            tables.push(table);
+        }
+        tables
+    }
+
+    /// The log counts, by length, of the trees of a symbol under `below`.
+    fn symbol_counts(
+        &self,
+        below: &HashMap<Symbol, Vec<f64>>,
+        symbol: Symbol,
+        max_length: usize,
+    ) -> Vec<f64> {
+        let mut counts = vec![f64::NEG_INFINITY; max_length + 1];
+        if self.is_terminal(symbol) {
+            if max_length > 0 {
+                counts[1] = 0.0;
+            }
+        } else if let Some(known) = below.get(&symbol) {
+            counts.copy_from_slice(known);
+        }
+        counts
+    }
+
+    /// Returns, for every `i`, the log counts by length of the trees of
+    /// `right[i..]` side by side.
+    fn sequence_counts(
+        &self,
+        below: &HashMap<Symbol, Vec<f64>>,
+        right: &[Symbol],
+        max_length: usize,
+    ) -> Vec<Vec<f64>> {
+        let mut empty = vec![f64::NEG_INFINITY; max_length + 1];
+        empty[0] = 0.0;
+
+        let mut suffixes = vec![empty];
+        for &symbol in right.iter().rev() {
+            let counts = self.symbol_counts(below, symbol, max_length);
+            suffixes.push(convolve(suffixes.last().unwrap(), &counts));
+        }
+        suffixes.reverse();
+        suffixes
+    }
+
+    fn take_tables(&mut self) -> Tables {
+        if self.tables.is_none() {
+            let tables = match (self.sampling, self.max_length) {
+                (Sampling::Length(length), _) => Tables::ByLength(self.count_by_length(length)),
+                (_, Some(max_length)) => Tables::ByLength(self.count_by_length(max_length)),
+                _ => Tables::ByHeight(self.count_by_height()),
+            };
+            self.tables = Some(tables);
+        }
+        self.tables.take().unwrap()
+    }
+
+    /// Returns a random sentence, or `None` when no sentence of the grammar
+    /// fits the limits.
+    pub fn sentence(&mut self) -> Option<String> {
+        let symbols = match self.sampling {
+            Sampling::Productions => {
+                self.heights.get(&self.start)?;
+                let max_length = match self.max_length {
+                    Some(max_length) => max_length,
+                    None => return Some(self.expand_top_down()),
+                };
+                for _ in 0..DRAWS {
+                    let sentence = self.expand_top_down();
+                    if sentence.chars().count() <= max_length {
+                        return Some(sentence);
+                    }
+                }
+                self.expand_counted(Some(max_length))?
+            }
+            Sampling::Derivations => self.expand_counted(self.max_length)?,
+            Sampling::Length(length) => {
+                let mut symbols = self.expand_counted(Some(length))?;
+                for _ in 1..REJECTIONS {
+                    if self.rng.next_f64().ln() < -self.count_trees(&symbols) {
+                        break;
+                    }
+                    symbols = self.expand_counted(Some(length))?;
+                }
+                symbols
+            }
+        };
+
+        Some(
+            symbols
+                .iter()
+                .map(|symbol| self.terminals[symbol].as_str())
+                .collect(),
+        )
+    }
+
+    /// Returns the log number of trees no deeper than `max_depth` that
+    /// yield exactly `word`, leaving out the productions of weight zero.
+    fn count_trees(&self, word: &[Symbol]) -> f64 {
+        let n = word.len();
+        let mut below: HashMap<Symbol, Vec<Vec<f64>>> = HashMap::new();
+        for _ in 0..self.max_depth {
+            let mut table: HashMap<Symbol, Vec<Vec<f64>>> = HashMap::new();
+            for (index, (left, right)) in self.productions.iter().enumerate() {
+                if self.weights[index] == 0.0 {
+                    continue;
+                }
+                let total = table
+                    .entry(*left)
+                    .or_insert_with(|| vec![vec![f64::NEG_INFINITY; n + 1]; n + 1]);
+                for (start, total) in total.iter_mut().enumerate() {
+                    // The log counts of `right[..k]` yielding `word[start..end]`,
+                    // by `end`, one symbol of `right` at a time.
+                    let mut reach = vec![f64::NEG_INFINITY; n + 1];
+                    reach[start] = 0.0;
+                    for &symbol in right {
+                        let mut next = vec![f64::NEG_INFINITY; n + 1];
+                        for middle in start..=n {
+                            if reach[middle] == f64::NEG_INFINITY {
+                                continue;
+                            }
+                            if self.is_terminal(symbol) {
+                                if word.get(middle) == Some(&symbol) {
+                                    next[middle + 1] = log_add(next[middle + 1], reach[middle]);
+                                }
+                            } else if let Some(counts) = below.get(&symbol) {
+                                for end in middle..=n {
+                                    next[end] =
+                                        log_add(next[end], reach[middle] + counts[middle][end]);
+                                }
+                            }
+                        }
+                        reach = next;
+                    }
+                    for (total, count) in total.iter_mut().zip(&reach) {
+                        *total = log_add(*total, *count);
+                    }
+                }
+            }
+            // Past the height of the tallest tree of `word` nothing changes.
+            if table == below {
+                break;
+            }
+            below = table;
+        }
+        below
+            .get(&self.start)
+            .map_or(f64::NEG_INFINITY, |counts| counts[0][n])
+    }
+
+    fn expand_top_down(&mut self) -> String {
+        let mut sentence = String::new();
+        let mut stack = vec![(self.start, 0)];
+        while let Some((symbol, depth)) = stack.pop() {
+            if let Some(name) = self.terminals.get(&symbol) {
+                sentence.push_str(name);
+                continue;
+            }
+
+            let candidates: Vec<(usize, usize)> = self
+                .rules(symbol)
+                .iter()
+                .filter(|&&index| self.weights[index] > 0.0)
+                .filter_map(|&index| {
+                    let height = self.height(&self.heights, &self.productions[index].1)?;
+                    Some((index, height))
+                })
+                .collect();
+            let fitting: Vec<usize> = candidates
+                .iter()
+                .filter(|(_, height)| depth + height <= self.max_depth)
+                .map(|(index, _)| *index)
+                .collect();
+            let choices = if !fitting.is_empty() {
+                fitting
+            } else {
+                let lowest = candidates.iter().map(|(_, height)| *height).min().unwrap();
+                candidates
+                    .iter()
+                    .filter(|(_, height)| *height == lowest)
+                    .map(|(index, _)| *index)
+                    .collect()
+            };
+
+            let log_weights: Vec<f64> = choices.iter().map(|&i| self.weights[i].ln()).collect();
+            let index = choices[self.rng.pick_log(&log_weights).unwrap()];
+            let right = &self.productions[index].1;
+            stack.extend(right.iter().rev().map(|&symbol| (symbol, depth + 1)));
+        }
+
+        sentence
+    }
+
+    /// Draws a derivation tree from the counting tables, of any length when
+    /// `length` is `None`, of at most `max_length` terminals for
+    /// `Sampling::Derivations`, or of exactly `length` ones otherwise.
+    fn expand_counted(&mut self, length: Option<usize>) -> Option<Vec<Symbol>> {
+        let exact = matches!(self.sampling, Sampling::Length(_));
+        let tables = self.take_tables();
+        let mut symbols: Vec<Symbol> = Vec::new();
+        let found = self.expand_tables(&tables, length, exact, &mut symbols);
+        self.tables = Some(tables);
+
+        found?;
+        Some(symbols)
+    }
+
+    fn expand_tables(
+        &mut self,
+        tables: &Tables,
+        length: Option<usize>,
+        exact: bool,
+        symbols: &mut Vec<Symbol>,
+    ) -> Option<()> {
+        let depth = self.max_depth;
+        match (tables, length) {
+            (Tables::ByHeight(tables), _) => {
+                tables[depth].get(&self.start)?;
+                self.expand_by_height(tables, self.start, depth, symbols)
+            }
+            (Tables::ByLength(tables), Some(length)) => {
+                let counts = tables[depth].get(&self.start)?;
+                let length = if exact {
+                    length
+                } else {
+                    self.rng.pick_log(counts)?
+                };
+                if counts[length] == f64::NEG_INFINITY {
+                    return None;
+                }
+                self.expand_by_length(tables, self.start, depth, length, symbols);
+                Some(())
+            }
+            (Tables::ByLength(_), None) => unreachable!("length tables need a length"),
+        }
+    }
+
+    fn expand_by_height(
+        &mut self,
+        tables: &[HashMap<Symbol, f64>],
+        symbol: Symbol,
+        height: usize,
+        out: &mut Vec<Symbol>,
+    ) -> Option<()> {
+        if self.is_terminal(symbol) {
+            out.push(symbol);
+            return Some(());
+        }
+
+        let below = &tables[height - 1];
+        let rules = self.rules(symbol).to_vec();
+        let log_weights: Vec<f64> = rules
+            .iter()
+            .map(|&index| {
+                self.weights[index].ln() + self.log_product(below, &self.productions[index].1)
+            })
+            .collect();
+        let index = rules[self.rng.pick_log(&log_weights)?];
+
+        for symbol in self.productions[index].1.clone() {
+            self.expand_by_height(tables, symbol, height - 1, out)?;
+        }
+        Some(())
+    }
+
+    fn expand_by_length(
+        &mut self,
+        tables: &[HashMap<Symbol, Vec<f64>>],
+        symbol: Symbol,
+        height: usize,
+        length: usize,
+        out: &mut Vec<Symbol>,
+    ) {
+        if self.is_terminal(symbol) {
+            out.push(symbol);
+            return;
+        }
+
+        let below = &tables[height - 1];
+        let max_length = tables[height][&symbol].len() - 1;
+        let rules = self.rules(symbol).to_vec();
+        let sequences: Vec<Vec<Vec<f64>>> = rules
+            .iter()
+            .map(|&index| self.sequence_counts(below, &self.productions[index].1, max_length))
+            .collect();
+        let log_weights: Vec<f64> = rules
+            .iter()
+            .zip(&sequences)
+            .map(|(&index, sequence)| self.weights[index].ln() + sequence[0][length])
+            .collect();
+        let chosen = self.rng.pick_log(&log_weights).unwrap();
+        let (right, suffixes) = (
+            self.productions[rules[chosen]].1.clone(),
+            &sequences[chosen],
+        );
+
+        // Splits the length between the symbols one at a time, each share
+        // weighted by the trees of the symbol and of the rest.
+        let mut rest = length;
+        for (position, &child) in right.iter().enumerate() {
+            let counts = self.symbol_counts(below, child, max_length);
+            let log_weights: Vec<f64> = (0..=rest)
+                .map(|share| counts[share] + suffixes[position + 1][rest - share])
+                .collect();
+            let share = self.rng.pick_log(&log_weights).unwrap();
+            self.expand_by_length(tables, child, height - 1, share, out);
+            rest -= share;
+        }
+    }
+}
+
+impl Iterator for Generator {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.sentence()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn generate_sentences() {
+        let grammar = Cfg::parse("Sum -> Sum '+' Digit | Digit; Digit -> '0' | '1';")
+            .unwrap()
+            .to_cnf()
+            .unwrap();
+        let mut parser = Parser::new(&grammar);
+
+        let sentences: Vec<String> = Generator::new(&grammar, 7).max_depth(6).take(50).collect();
+        for sentence in &sentences {
+            assert!(parser.parse(sentence).is_ok(), "{}", sentence);
+            assert!(sentence.len() <= 2usize.pow(5));
+        }
+        assert!(sentences.iter().any(|sentence| sentence.contains('+')));
+
+        let again: Vec<String> = Generator::new(&grammar, 7).max_depth(6).take(50).collect();
+        assert_eq!(sentences, again);
+
+        let empty = Cfg::parse("S -> S S;").unwrap();
+        assert_eq!(Generator::from_cfg(&empty, 0).sentence(), None);
+    }
+
+    #[test]
+    fn sample_by_counting() {
+        let words = Cfg::parse("S -> 'a' S | 'b' S | ;").unwrap();
+
+        let sentences: HashSet<String> = Generator::from_cfg(&words, 3)
+            .sampling(Sampling::Length(4))
+            .take(400)
+            .collect();
+        assert_eq!(sentences.len(), 16);
+        assert!(sentences.iter().all(|sentence| sentence.len() == 4));
+
+        let symbol = |name| words.symbols().get(name).unwrap();
+        let no_b = Generator::from_cfg(&words, 3)
+            .weight(symbol("S"), &[symbol("b"), symbol("S")], 0.0)
+            .sampling(Sampling::Derivations)
+            .max_length(5);
+        for sentence in no_b.take(100) {
+            assert!(
+                sentence.len() <= 5 && !sentence.contains('b'),
+                "{}",
+                sentence
+            );
+        }
+
+        let shallow = Generator::from_cfg(&words, 3)
+            .sampling(Sampling::Derivations)
+            .max_depth(3);
+        assert!(shallow.take(100).all(|sentence| sentence.len() <= 2));
+
+        let even = Cfg::parse("S -> 'a' 'a' S | 'a' 'a';").unwrap();
+        let mut odd = Generator::from_cfg(&even, 0).sampling(Sampling::Length(3));
+        assert_eq!(odd.sentence(), None);
+        let mut capped = Generator::from_cfg(&even, 0).max_length(1);
+        assert_eq!(capped.sentence(), None);
+    }
+
+    #[test]
+    fn sample_ambiguous_sentences() {
+        // "aa" has two trees and the three other sentences one each.
+        let grammar = Cfg::parse("S -> 'a' 'a' | A A; A -> 'a' | 'b';").unwrap();
+        let count = |sampling: Sampling| {
+            Generator::from_cfg(&grammar, 5)
+                .sampling(sampling)
+                .max_length(2)
+                .take(4000)
+                .filter(|sentence| sentence == "aa")
+                .count()
+        };
+
+        let by_sentence = count(Sampling::Length(2));
+        assert!((850..1150).contains(&by_sentence), "{}", by_sentence);
+        let by_tree = count(Sampling::Derivations);
+        assert!((1450..1750).contains(&by_tree), "{}", by_tree);
+    }
+}
@@ -0,0 +1,204 @@
+use super::{Grammar, HashSet, Production, Symbol};
+
+use std::collections::HashMap;
+
+/// The sentences of every non-terminal of one length.
+type Sentences = HashMap<Symbol, HashSet<Vec<Symbol>>>;
+
+/// The sentences of a grammar in shortlex order, up to a length counted in
+/// terminals: shorter sentences first, and sentences of the same length
+/// sorted by their text.
+///
+/// The sentences of every non-terminal are built one length at a time from
+/// those of the shorter lengths, so only the lengths reached so far are
+/// computed. The number of sentences and of derivations of each length are
+/// available as well.
+#[derive(Clone, Debug)]
+pub struct Enumeration {
+    start: Symbol,
+    names: HashMap<Symbol, String>,
+    binary: Vec<(Symbol, Symbol, Symbol)>,
+    single: Vec<(Symbol, Symbol)>,
+    max_length: usize,
+    /// The sentences of every non-terminal, by length minus one.
+    sentences: Vec<Sentences>,
+    /// The number of derivations of every non-terminal, by length minus one.
+    derivations: Vec<HashMap<Symbol, u64>>,
+    pending: std::vec::IntoIter<String>,
+    /// The length of the sentences the iterator lists after `pending`.
+    next_length: usize,
+}
+
+impl Enumeration {
+    pub fn new<G: Grammar>(grammar: &G, max_length: usize) -> Self {
+        let productions: HashSet<Production> = grammar.productions().into_iter().collect();
+        let mut binary: Vec<(Symbol, Symbol, Symbol)> = Vec::new();
+        let mut single: Vec<(Symbol, Symbol)> = Vec::new();
+        for production in productions {
+            match production {
+                Production::Binary(left, first, second) => binary.push((left, first, second)),
+                Production::Terminal(left, terminal) => single.push((left, terminal)),
+            }
+        }
+
+        let names = grammar
+            .terminals()
+            .iter()
+            .map(|&terminal| {
+                let name = grammar.symbols().resolve(terminal).unwrap_or("");
+                (terminal, name.to_string())
+            })
+            .collect();
+
+        Enumeration {
+            start: grammar.start_symbol(),
+            names,
+            binary,
+            single,
+            max_length,
+            sentences: Vec::new(),
+            derivations: Vec::new(),
+            pending: Vec::new().into_iter(),
+            next_length: 1,
+        }
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// The number of distinct sentences of `length` terminals, or zero
+    /// past `max_length`.
+    pub fn strings(&mut self, length: usize) -> usize {
+        if !self.reach(length) {
+            return 0;
+        }
+        self.sentences[length - 1]
+            .get(&self.start)
+            .map_or(0, |sentences| sentences.len())
+    }
+
+    /// The number of derivation trees of the sentences of `length`
+    /// terminals, saturating at `u64::MAX`. It is larger than the number of
+    /// sentences when some of them are ambiguous.
+    pub fn derivations(&mut self, length: usize) -> u64 {
+        if !self.reach(length) {
+            return 0;
+        }
+        self.derivations[length - 1]
+            .get(&self.start)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns the sentences of `length` terminals in shortlex order.
+    pub fn sentences_of(&mut self, length: usize) -> Vec<String> {
+        if !self.reach(length) {
+            return Vec::new();
+        }
+        let mut result: Vec<String> = self.sentences[length - 1]
+            .get(&self.start)
+            .into_iter()
+            .flatten()
+            .map(|sentence| sentence.iter().map(|t| self.names[t].as_str()).collect())
+            .collect();
+        result.sort_unstable();
+        result
+    }
+
+    /// Computes the tables up to `length`, if it is within `max_length`.
+    fn reach(&mut self, length: usize) -> bool {
+        if length == 0 || length > self.max_length {
+            return false;
+        }
+        while self.sentences.len() < length {
+            self.grow();
+        }
+        true
+    }
+
+    /// Computes the sentences and derivations of the next length.
+    fn grow(&mut self) {
+        let length = self.sentences.len() + 1;
+        let mut sentences = Sentences::new();
+        let mut derivations: HashMap<Symbol, u64> = HashMap::new();
+
+        if length == 1 {
+            for &(left, terminal) in &self.single {
+                sentences.entry(left).or_default().insert(vec![terminal]);
+                *derivations.entry(left).or_insert(0) += 1;
+            }
+        }
+
+        for &(left, first, second) in &self.binary {
+            for split in 1..length {
+                let (before, after) = (
+                    &self.sentences[split - 1],
+                    &self.sentences[length - split - 1],
+                );
+                let (heads, tails) = match (before.get(&first), after.get(&second)) {
+                    (Some(heads), Some(tails)) => (heads, tails),
+                    _ => continue,
+                };
+
+                let found = sentences.entry(left).or_default();
+                for head in heads {
+                    for tail in tails {
+                        found.insert(head.iter().chain(tail).copied().collect());
+                    }
+                }
+
+                let count =
+                    |table: &HashMap<Symbol, u64>, symbol| table.get(&symbol).copied().unwrap_or(0);
+                let count = count(&self.derivations[split - 1], first)
+                    .saturating_mul(count(&self.derivations[length - split - 1], second));
+                let total = derivations.entry(left).or_insert(0);
+                *total = total.saturating_add(count);
+            }
+        }
+
+        self.sentences.push(sentences);
+        self.derivations.push(derivations);
+    }
+}
+
+impl Iterator for Enumeration {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            if let Some(sentence) = self.pending.next() {
+                return Some(sentence);
+            }
+            if self.next_length > self.max_length {
+                return None;
+            }
+            self.pending = self.sentences_of(self.next_length).into_iter();
+            self.next_length += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn enumerate_in_shortlex_order() {
+        let grammar = Cfg::parse("S -> S S | 'b' | 'a';")
+            .unwrap()
+            .as_cnf()
+            .unwrap();
+        let mut language = Enumeration::new(&grammar, 3);
+
+        let first: Vec<String> = language.clone().take(7).collect();
+        assert_eq!(first, vec!["a", "b", "aa", "ab", "ba", "bb", "aaa"]);
+        assert_eq!(language.clone().count(), 2 + 4 + 8);
+
+        assert_eq!(language.strings(3), 8);
+        assert_eq!(language.next().as_deref(), Some("a"));
+        assert_eq!(language.derivations(3), 16);
+        assert_eq!(language.derivations(4), 0);
+        assert_eq!(language.strings(0), 0);
+    }
+}
@@ -1,31 +1,70 @@
+mod action;
+mod ambiguity;
+mod analysis;
+mod attribute;
+mod boolean;
+mod builder;
+mod cfg;
+mod chart;
 mod cnf;
+mod compare;
+mod compiled;
 mod error;
+mod export;
+mod features;
+mod generate;
+mod language;
+mod observer;
+mod prefix;
+mod properties;
+mod sets;
+mod source;
 mod symbol;
 mod tree;
+mod visit;
 
+#[cfg(feature = "macros")]
+pub use cyk_macros::grammar;
+#[doc(hidden)]
+pub use lazy_static::lazy_static;
+
+pub use action::*;
+pub use ambiguity::*;
+pub use analysis::*;
+pub use attribute::*;
+pub use boolean::*;
+pub use builder::*;
+pub use cfg::*;
+pub use chart::*;
 pub use cnf::*;
+pub use compare::*;
+pub use compiled::*;
 pub use error::*;
+pub use features::*;
+pub use generate::*;
+pub use language::*;
+pub use observer::*;
+pub use properties::*;
+pub use sets::*;
+pub use source::*;
 pub use symbol::*;
 pub use tree::*;
+pub use visit::*;
 
 use std::collections::HashMap;
 use std::fmt::Debug;
 
-fn rest_span(span: Span, len: usize) -> Option<Span> {
-    if span.len() >= len {
-        None
-    } else {
-        Some(Span::new(span.start() + span.len(), len - span.len()))
-    }
-}
-
 pub trait Grammar {
     fn start_symbol(&self) -> Symbol;
 
     fn exist(&self, symbol: Symbol) -> bool;
 
+    /// Returns the `B` of every rule `symbol -> B C`. This is one step of a
+    /// derivation, not the FIRST set; see `GrammarSets::first_set`.
     fn first(&self, symbol: Symbol) -> Option<HashSet<Symbol>>;
 
+    /// Returns the `C` of every rule `A -> symbol C`. This is one step of a
+    /// derivation, not the FOLLOW set; see `GrammarSets::follow_set`.
     fn follow(&self, symbol: Symbol) -> Option<HashSet<Symbol>>;
 
     fn derive(&self, base: Symbol, suffix: Symbol) -> Option<HashSet<Symbol>>;
@@ -35,32 +74,253 @@ pub trait Grammar {
     fn is_terminal(&self, input: Symbol) -> bool;
 
     fn is_non_terminal(&self, input: Symbol) -> bool;
+
+    fn terminals(&self) -> &HashSet<Symbol>;
+
+    fn non_terminals(&self) -> &HashSet<Symbol>;
+
+    fn rules(&self) -> &Rules;
+
+    fn terminal_rules(&self) -> &TerminalRules;
+
+    fn symbols(&self) -> &SymbolTable;
+
+    /// Returns the symbol spelled by the single char `c`.
+    fn terminal(&self, c: char) -> Option<Symbol> {
+        self.symbols().from_char(c)
+    }
+
+    /// Returns the other conjuncts of every Boolean rule whose first positive
+    /// conjunct is the binary `production`, or `None` if `production` is an
+    /// ordinary context-free rule. When some are returned, the parser keeps
+    /// an entry for `production` only if all the conjuncts of one of them
+    /// hold over its span; see `BooleanGrammar`.
+    fn conjuncts(&self, _production: Production) -> Option<&[Vec<Conjunct>]> {
+        None
+    }
+
+    /// Returns the unification equations of `production`, one list for
+    /// each rule it was written in, or `None` if its symbols carry no
+    /// features. When some are returned, the parser keeps an entry for
+    /// `production` with the features of every list that holds; see
+    /// `FeatureGrammar`.
+    fn feature_equations(&self, _production: Production) -> Option<&[Vec<FeatureEquation>]> {
+        None
+    }
+
+    fn productions(&self) -> Vec<Production> {
+        let mut productions: Vec<Production> = Vec::new();
+
+        for rule in self.rules().iter() {
+            for branch in rule.branches() {
+                productions.push(Production::Binary(
+                    rule.start(),
+                    branch.left(),
+                    branch.right(),
+                ))
+            }
+        }
+        for rule in self.terminal_rules().iter() {
+            for &symbol in rule.symbols() {
+                productions.push(Production::Terminal(rule.start(), symbol))
+            }
+        }
+
+        productions
+    }
+}
+
+/// A child entry picked while building a tree, with its features.
+type Child<'c> = (&'c Entry, Option<Features>);
+
+fn featured(entry: Entry, features: Option<Features>) -> Entry {
+    match features {
+        Some(features) => entry.with_features(features),
+        None => entry,
+    }
 }
 
 #[derive(Clone, Debug)]
-pub struct Parser<'a, G> {
+pub struct Parser<'a, G, O = NoObserver> {
     grammar: &'a G,
     src: &'a str,
     chars: Vec<char>,
+    offsets: Vec<usize>,
     slices: HashMap<Span, Cell>,
 
     unknowns: Vec<Diagnostic>,
+    observer: O,
 }
 
 impl<'a, G: Grammar + Debug + Clone> Parser<'a, G> {
     pub fn new(grammar: &'a G) -> Self {
+        Parser::with_observer(grammar, NoObserver)
+    }
+}
+
+impl<'a, G: Grammar + Debug + Clone, O: ParseObserver> Parser<'a, G, O> {
+    /// Builds a parser that reports every step of the chart filling to
+    /// `observer`.
+    pub fn with_observer(grammar: &'a G, observer: O) -> Self {
         Parser {
             grammar,
             src: "",
             chars: "".chars().collect(),
+            offsets: vec![0],
             slices: HashMap::new(),
             unknowns: vec![],
+            observer,
+        }
+    }
+
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    pub fn into_observer(self) -> O {
+        self.observer
+    }
+
+    pub fn parse(&mut self, string: &'a str) -> Result<Tree, Vec<Diagnostic>> {
+        self.fill(string);
+
+        let span = self.span(0, self.src_len());
+        let start = self.grammar.start_symbol();
+        match self.get_cell(span).and_then(|cell| cell.has(start)) {
+            Some(_) => Ok(self.build_tree(span, start)),
+            None => Err(self.unknowns.clone()),
+        }
+    }
+
+    /// Follows the chart's back-pointers from `symbol` over `span` to build
+    /// the preferred parse tree.
+    fn build_tree(&self, span: Span, symbol: Symbol) -> Tree {
+        let mut tree = Tree::new(symbol, span);
+        let entry = self
+            .get_cell(span)
+            .and_then(|cell| cell.has(symbol))
+            .expect("back-pointer to a missing chart entry");
+        let mut stack = vec![(tree.root(), entry, entry.features().cloned())];
+
+        while let Some((id, entry, features)) = stack.pop() {
+            let span = tree[id].span();
+
+            match entry.back_pointer() {
+                BackPointer::Terminal(terminal) => {
+                    tree.push(id, terminal, span);
+                }
+                BackPointer::Binary(left_span, left, right_span, right) => {
+                    let [first, second] = self.children(entry, features.as_ref());
+                    let left = tree.push(id, left, left_span);
+                    let right = tree.push(id, right, right_span);
+                    stack.push((right, second.0, second.1));
+                    stack.push((left, first.0, first.1));
+                }
+            }
+            tree[id].set_features(features);
+        }
+
+        tree
+    }
+
+    /// Picks the entries of the children `entry` was derived from, with
+    /// their features once unified with `features`, those of `entry` in the
+    /// whole tree.
+    fn children(&self, entry: &Entry, features: Option<&Features>) -> [Child<'_>; 2] {
+        let missing = "back-pointer to a missing chart entry";
+        let (left_span, left, right_span, right) = match entry.back_pointer() {
+            BackPointer::Binary(left_span, left, right_span, right) => {
+                (left_span, left, right_span, right)
+            }
+            BackPointer::Terminal(_) => panic!("{}", missing),
+        };
+        let (lefts, rights) = (
+            self.get_cell(left_span).expect(missing),
+            self.get_cell(right_span).expect(missing),
+        );
+
+        let production = Production::Binary(entry.kind(), left, right);
+        let rules = match self.grammar.feature_equations(production) {
+            Some(rules) => rules,
+            None => {
+                let first = lefts.has(left).expect(missing);
+                let second = rights.has(right).expect(missing);
+                return [
+                    (first, first.features().cloned()),
+                    (second, second.features().cloned()),
+                ];
+            }
+        };
+
+        // The children the entry was derived from, should none of them
+        // also agree with the features of `entry` in the whole tree.
+        let mut fallback: Option<[Child<'_>; 2]> = None;
+        for first in lefts.iter().filter(|first| first.kind() == left) {
+            for second in rights.iter().filter(|second| second.kind() == right) {
+                for equations in rules {
+                    let unify = |parent: Option<&Features>| {
+                        let nodes = [parent, first.features(), second.features()]
+                            .map(|features| features.cloned().unwrap_or_default());
+                        unify_production(equations, nodes)
+                    };
+                    let (left, right) = match unify(None) {
+                        Some([found, left, right]) if Some(&found) == entry.features() => {
+                            (left, right)
+                        }
+                        _ => continue,
+                    };
+
+                    if let Some([_, left, right]) = unify(features) {
+                        return [(first, Some(left)), (second, Some(right))];
+                    }
+                    if fallback.is_none() {
+                        fallback = Some([(first, Some(left)), (second, Some(right))]);
+                    }
+                }
+            }
         }
+
+        fallback.expect(missing)
     }
 
-    pub fn parse(&mut self, string: &'a str) -> Result<&Node, Vec<Diagnostic>> {
+    /// Returns the features of the left side of `production` for every rule
+    /// it was written in whose equations hold with children of `first` and
+    /// `second` features, or a single `None` if it carries no features.
+    fn unify(
+        &self,
+        production: Production,
+        first: Option<&Features>,
+        second: Option<&Features>,
+    ) -> Vec<Option<Features>> {
+        let rules = match self.grammar.feature_equations(production) {
+            Some(rules) => rules,
+            None => return vec![None],
+        };
+
+        let mut result: Vec<Option<Features>> = Vec::new();
+        for equations in rules {
+            let nodes = [None, first, second].map(|features| features.cloned().unwrap_or_default());
+            if let Some([left, _, _]) = unify_production(equations, nodes) {
+                if !result.contains(&Some(left.clone())) {
+                    result.push(Some(left));
+                }
+            }
+        }
+        result
+    }
+
+    fn fill(&mut self, string: &'a str) {
         self.src = string;
         self.chars = string.chars().collect();
+        self.offsets = string
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(Some(string.len()))
+            .collect();
         self.slices = HashMap::new();
         self.unknowns = vec![];
 
@@ -70,23 +330,24 @@ impl<'a, G: Grammar + Debug + Clone> Parser<'a, G> {
                 self.parse_span(span);
             }
         }
-
-        match self.get_cell(Span::new(0, src_len)) {
-            Some(cell) => match cell.has(self.grammar.start_symbol()) {
-                Some(node) => Ok(node),
-                None => Err(self.unknowns.clone()),
-            },
-            None => Err(self.unknowns.clone()),
-        }
     }
 
     fn parse_span(&mut self, span: Span) {
+        self.observer.cell_started(span);
+
         match span.len() {
             1 => {
                 let start = span.start();
                 let c = *self.chars.get(start).unwrap();
                 let cell = self.derive_char(span, c);
+                for entry in cell.iter() {
+                    if let BackPointer::Terminal(terminal) = entry.back_pointer() {
+                        self.observer.terminal_matched(span, entry.kind(), terminal);
+                    }
+                }
+
                 if cell.is_empty() {
+                    self.observer.unknown_char(span, c);
                     self.add_unknown(c, span)
                 } else {
                     self.add_cell(cell, span)
@@ -95,13 +356,12 @@ impl<'a, G: Grammar + Debug + Clone> Parser<'a, G> {
             _ => {
                 let mut cell_list: Vec<Cell> = Vec::new();
                 for len in 1..span.len() {
-                    let base_span = Span::new(span.start(), len);
+                    let base_span = self.span(span.start(), len);
                     if let Some(base_cell) = self.get_cell(base_span) {
                         if let Some(base_span) = base_cell.span() {
-                            if let Some(rest_span) = rest_span(base_span, span.len()) {
+                            if let Some(rest_span) = self.rest_span(base_span, span.len()) {
                                 if let Some(rest_cell) = self.get_cell(rest_span) {
-                                    if let Some(next_cell) =
-                                        self.derive(span, &base_cell, &rest_cell)
+                                    if let Some(next_cell) = self.derive(span, base_cell, rest_cell)
                                     {
                                         cell_list.push(next_cell);
                                     }
@@ -111,26 +371,51 @@ impl<'a, G: Grammar + Debug + Clone> Parser<'a, G> {
                     }
                 }
 
-                cell_list.sort();
+                let mut pairs: HashMap<(Symbol, Symbol), bool> = HashMap::new();
+                for next_cell in cell_list.iter_mut() {
+                    next_cell
+                        .0
+                        .retain(|entry| self.conjoin(span, entry, &mut pairs));
+                }
+
+                for entry in cell_list.iter().flat_map(|cell| cell.iter()) {
+                    if let BackPointer::Binary(_, left, right_span, right) = entry.back_pointer() {
+                        self.observer.rule_applied(
+                            span,
+                            Production::Binary(entry.kind(), left, right),
+                            right_span.start(),
+                        );
+                    }
+                }
 
-                if let Some(cell) = cell_list.last() {
-                    self.add_cell((*cell).clone(), span)
+                // Every split contributes its back-pointers to the cell, the
+                // longest base span first so that it is preferred by `has`.
+                let mut cell = cell![;span];
+                for next_cell in cell_list.into_iter().rev() {
+                    cell.append(next_cell);
+                }
+
+                if !cell.is_empty() {
+                    self.add_cell(cell, span)
                 }
             }
         }
+
+        let found = self.get_cell(span).map_or(0, |cell| cell.len());
+        self.observer.cell_finished(span, found);
     }
 
     fn derive_char(&self, span: Span, c: char) -> Cell {
         let mut next_cell = cell![;span];
 
-        if let Some(symbol) = Symbol::from_char(c) {
+        if let Some(symbol) = self.grammar.terminal(c) {
             if let Some(symbols) = self.grammar.derive_single(symbol) {
                 for sym in symbols {
-                    next_cell.push_nodes(Node::new(
-                        sym,
-                        span,
-                        NodeChildren::Single(Box::new(Node::new(symbol, span, NodeChildren::None))),
-                    ))
+                    let production = Production::Terminal(sym, symbol);
+                    for features in self.unify(production, None, None) {
+                        let entry = Entry::new(sym, BackPointer::Terminal(symbol));
+                        next_cell.push_entry(featured(entry, features))
+                    }
                 }
             }
         }
@@ -140,21 +425,30 @@ impl<'a, G: Grammar + Debug + Clone> Parser<'a, G> {
 
     fn derive(&self, span: Span, base: &Cell, suffix: &Cell) -> Option<Cell> {
         let mut next_cell = cell![;span];
+        let (base_span, suffix_span) = (base.span()?, suffix.span()?);
 
-        for cur in &base.0 {
-            for suffix in &suffix.0 {
-                if let Some(symbols) = self.grammar.follow(cur.kind()) {
-                    if symbols.iter().any(|&sym| sym.eq(&suffix.kind())) {
-                        if let Some(symbols) = self.grammar.derive(cur.kind(), suffix.kind()) {
+        for cur in base.symbols() {
+            for next in suffix.symbols() {
+                if let Some(symbols) = self.grammar.follow(cur) {
+                    if symbols.iter().any(|&sym| sym.eq(&next)) {
+                        if let Some(symbols) = self.grammar.derive(cur, next) {
+                            let back_pointer =
+                                BackPointer::Binary(base_span, cur, suffix_span, next);
                             for symbol in symbols {
-                                next_cell.push_nodes(Node::new(
-                                    symbol,
-                                    span,
-                                    NodeChildren::Double(
-                                        Box::new(cur.clone()),
-                                        Box::new(suffix.clone()),
-                                    ),
-                                ))
+                                let production = Production::Binary(symbol, cur, next);
+                                for first in base.features_of(cur) {
+                                    for second in suffix.features_of(next) {
+                                        for features in self.unify(production, first, second) {
+                                            let entry = Entry::new(symbol, back_pointer);
+                                            let entry = featured(entry, features);
+                                            if entry.features().is_none()
+                                                || !next_cell.0.contains(&entry)
+                                            {
+                                                next_cell.push_entry(entry)
+                                            }
+                                        }
+                                    }
+                                }
                             }
                         }
                     }
@@ -169,6 +463,51 @@ impl<'a, G: Grammar + Debug + Clone> Parser<'a, G> {
         }
     }
 
+    /// Whether the rest of a Boolean rule holds for `entry`: all the
+    /// conjuncts of one of the rules it was derived by, the positive ones
+    /// found over some split of `span` and the negative ones over none.
+    /// `pairs` caches which pairs `span` was found to split into.
+    fn conjoin(
+        &self,
+        span: Span,
+        entry: &Entry,
+        pairs: &mut HashMap<(Symbol, Symbol), bool>,
+    ) -> bool {
+        let (first, second) = match entry.back_pointer() {
+            BackPointer::Binary(_, first, _, second) => (first, second),
+            BackPointer::Terminal(_) => return true,
+        };
+        let production = Production::Binary(entry.kind(), first, second);
+
+        match self.grammar.conjuncts(production) {
+            Some(rules) => rules.iter().any(|conjuncts| {
+                conjuncts.iter().all(|conjunct| {
+                    let (pair, negated) = match *conjunct {
+                        Conjunct::Positive(first, second) => ((first, second), false),
+                        Conjunct::Negative(first, second) => ((first, second), true),
+                    };
+                    let found = *pairs
+                        .entry(pair)
+                        .or_insert_with(|| self.concatenates(span, pair.0, pair.1));
+                    found != negated
+                })
+            }),
+            None => true,
+        }
+    }
+
+    /// Whether `span` splits into a `first` followed by a `second`.
+    fn concatenates(&self, span: Span, first: Symbol, second: Symbol) -> bool {
+        (1..span.len()).any(|len| {
+            let rest = self.span(span.start() + len, span.len() - len);
+            self.get_cell(self.span(span.start(), len))
+                .is_some_and(|cell| cell.has(first).is_some())
+                && self
+                    .get_cell(rest)
+                    .is_some_and(|cell| cell.has(second).is_some())
+        })
+    }
+
     fn get_cell(&self, span: Span) -> Option<&Cell> {
         self.slices.get(&span)
     }
@@ -180,12 +519,32 @@ impl<'a, G: Grammar + Debug + Clone> Parser<'a, G> {
         debug_assert!(len <= src_len);
 
         for i in 0..(src_len - len + 1) {
-            spans.push(Span::new(i, len))
+            spans.push(self.span(i, len))
         }
 
         spans
     }
 
+    /// Builds the span of `len` chars starting at char `start`, together with
+    /// its byte offsets into the source.
+    fn span(&self, start: usize, len: usize) -> Span {
+        let byte_start = self.offsets[start];
+        Span::with_bytes(
+            start,
+            len,
+            byte_start,
+            self.offsets[start + len] - byte_start,
+        )
+    }
+
+    fn rest_span(&self, span: Span, len: usize) -> Option<Span> {
+        if span.len() >= len {
+            None
+        } else {
+            Some(self.span(span.start() + span.len(), len - span.len()))
+        }
+    }
+
     fn add_cell(&mut self, cell: Cell, span: Span) {
         self.slices.insert(span, cell);
     }
@@ -203,6 +562,235 @@ impl<'a, G: Grammar + Debug + Clone> Parser<'a, G> {
 mod test {
     use super::*;
 
+    fn owned_grammar(order: &[&str]) -> CNF {
+        let mut symbols = SymbolTable::new();
+        for name in order {
+            symbols.intern(name);
+        }
+        let get = |name| symbols.get(name).unwrap();
+
+        let mut rules = Rules::new();
+        rules.insert(
+            get("S"),
+            vec![RuleRight::new(get("A"), get("B"))]
+                .into_iter()
+                .collect(),
+        );
+        let mut terminal_rules = TerminalRules::new();
+        terminal_rules.insert(get("A"), vec![get("a")].into_iter().collect());
+        terminal_rules.insert(get("B"), vec![get("b")].into_iter().collect());
+
+        CNF::new(
+            get("S"),
+            vec![get("a"), get("b")].into_iter().collect(),
+            vec![get("S"), get("A"), get("B")].into_iter().collect(),
+            rules,
+            terminal_rules,
+        )
+        .with_symbols(symbols)
+    }
+
+    #[test]
+    fn it_works_with_owned_symbols() {
+        let first = owned_grammar(&["S", "A", "B", "a", "b"]);
+        let second = owned_grammar(&["b", "a", "B", "A", "S"]);
+        assert_ne!(first.symbols().get("S"), second.symbols().get("S"));
+
+        for grammar in &[first, second] {
+            let mut parser = Parser::new(grammar);
+            let tree = parser.parse("ab").unwrap();
+            assert_eq!(
+                grammar.symbols().resolve(tree[tree.root()].kind()),
+                Some("S")
+            );
+            assert!(parser.parse("ba").is_err());
+        }
+    }
+
+    #[test]
+    fn it_displays_owned_symbols() {
+        let grammar = owned_grammar(&["b", "a", "B", "A", "S"]);
+
+        let tree = Parser::new(&grammar).parse("ab").unwrap();
+        assert_eq!(tree.display(grammar.symbols()).to_string(), "ab");
+        let production = tree.production(tree.root()).unwrap();
+        assert_eq!(
+            production.display(grammar.symbols()).to_string(),
+            "S -> A B"
+        );
+        assert_eq!(
+            Cfg::from_grammar(&grammar).to_string(),
+            "start S;\nS -> A B;\nA -> 'a';\nB -> 'b';\n"
+        );
+        let root = tree[tree.root()].kind();
+        assert_eq!(root.display(grammar.symbols()).to_string(), "S");
+        assert_eq!(root.to_string(), "#4");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_round_trips_through_serde() {
+        let json = r#"{
+            "start": "Pair",
+            "terminals": ["ſ"],
+            "non_terminals": ["Pair", "Long"],
+            "rules": [["Pair", [["Long", "Long"]]]],
+            "terminal_rules": [["Long", ["ſ"]]]
+        }"#;
+        let grammar: CNF = serde_json::from_str(json).unwrap();
+        assert_eq!(grammar.start_symbol(), Symbol::intern("Pair"));
+
+        let mut parser = Parser::new(&grammar);
+        let tree = parser.parse("ſſ").unwrap();
+        let text = serde_json::to_string(&tree).unwrap();
+        assert!(text.contains(r#""kind":"Long""#));
+
+        let back: Tree = serde_json::from_str(&text).unwrap();
+        assert_eq!(back.len(), tree.len());
+        assert_eq!(back[back.root()].span(), tree[tree.root()].span());
+        assert_eq!(
+            serde_json::to_string(&grammar.rules()).unwrap(),
+            r#"[["Pair",[["Long","Long"]]]]"#
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_round_trips_owned_symbols_through_serde() {
+        let grammar = owned_grammar(&["b", "a", "B", "A", "S"]);
+        let text = serde_json::to_string(&grammar).unwrap();
+        assert!(text.contains(r#""symbols":["b","a","B","A","S"]"#));
+
+        let back: CNF = serde_json::from_str(&text).unwrap();
+        assert!(!back.symbols().is_global());
+        assert_eq!(back.start_symbol(), grammar.start_symbol());
+        assert_eq!(back.symbols().get("a"), grammar.symbols().get("a"));
+        let tree = Parser::new(&back).parse("ab").unwrap();
+        assert_eq!(tree.to_bracketed(back.symbols()), "(S (A a) (B b))");
+        assert_eq!(serde_json::to_string(&back).unwrap(), text);
+    }
+
+    #[test]
+    fn it_works_with_multibyte_chars() {
+        let grammar = cnf_grammar! {
+            Start("Word");
+            NonTerminals["Word", "Accent", "Letter"];
+            Terminals["é", "b"];
+            Rules [
+                "Word" => [["Accent", "Letter"]]
+            ];
+            TerminalRules [
+                "Accent" => ["é"],
+                "Letter" => ["b"]
+            ]
+        };
+        let src = "éb";
+        let mut parser = Parser::new(&grammar);
+        let tree = parser.parse(src).unwrap();
+        let root = &tree[tree.root()];
+
+        assert_eq!(tree.text(src), "éb");
+        assert_eq!(root.span().len(), 2);
+        assert_eq!(root.span().byte_len(), 3);
+        match root.children() {
+            NodeChildren::Double(left, right) => {
+                assert_eq!(tree[left].text(src), "é");
+                assert_eq!(tree[right].text(src), "b");
+                assert_eq!(tree[right].span().byte_range(), 2..3);
+            }
+            _ => panic!("Error"),
+        }
+    }
+
+    #[test]
+    fn it_merges_every_split() {
+        // "abc" is an S split after "a" and a Y split after "ab"; a cell
+        // used to keep only the symbols of its longest base span.
+        let grammar = cnf_grammar! {
+            Start("S");
+            NonTerminals["S", "X", "Y", "P", "A", "B", "C"];
+            Terminals["a", "b", "c"];
+            Rules [
+                "S" => [["A", "X"]],
+                "X" => [["B", "C"]],
+                "Y" => [["P", "C"]],
+                "P" => [["A", "B"]]
+            ];
+            TerminalRules [
+                "A" => ["a"],
+                "B" => ["b"],
+                "C" => ["c"]
+            ]
+        };
+        let symbol = |name: &str| grammar.symbols().get(name).unwrap();
+        let mut parser = Parser::new(&grammar);
+        let tree = parser.parse("abc").unwrap();
+        assert_eq!(
+            tree.to_bracketed(grammar.symbols()),
+            "(S (A a) (X (B b) (C c)))"
+        );
+
+        let cell = parser.chart().cell(0, 3).unwrap();
+        let mut symbols = cell.symbols();
+        symbols.sort();
+        let mut expected = vec![symbol("S"), symbol("Y")];
+        expected.sort();
+        assert_eq!(symbols, expected);
+    }
+
+    #[test]
+    fn it_follows_back_pointers() {
+        let grammar = cnf_grammar! {
+            Start("S");
+            NonTerminals["S", "X", "A", "B", "C"];
+            Terminals["a", "b", "c"];
+            Rules [
+                "S" => [["A", "X"]],
+                "X" => [["B", "C"]]
+            ];
+            TerminalRules [
+                "A" => ["a"],
+                "B" => ["b"],
+                "C" => ["c"]
+            ]
+        };
+        let symbol = |name: &str| grammar.symbols().get(name).unwrap();
+        let mut parser = Parser::new(&grammar);
+        let tree = parser.parse("abc").unwrap();
+
+        let chart = parser.chart();
+        let entry = chart.cell(0, 3).unwrap().has(symbol("S")).unwrap();
+        assert_eq!(
+            entry.back_pointer(),
+            BackPointer::Binary(chart.span(0, 1), symbol("A"), chart.span(1, 2), symbol("X"))
+        );
+        let entry = chart.cell(2, 1).unwrap().has(symbol("C")).unwrap();
+        assert_eq!(entry.back_pointer(), BackPointer::Terminal(symbol("c")));
+
+        let root = tree.root();
+        assert_eq!(tree.len(), 8);
+        assert_eq!(tree.parent(root), None);
+        let children: Vec<NodeId> = tree.children(root).collect();
+        let (a, x) = (children[0], children[1]);
+        assert_eq!((tree[a].kind(), tree[x].kind()), (symbol("A"), symbol("X")));
+        assert_eq!(tree[x].span(), chart.span(1, 2));
+        assert_eq!(tree.next_sibling(a), Some(x));
+        assert_eq!(tree.prev_sibling(x), Some(a));
+        assert_eq!(tree.next_sibling(x), None);
+        assert_eq!(tree.siblings(x).collect::<Vec<_>>(), vec![a]);
+
+        let leaf = tree.children(a).next().unwrap();
+        assert!(tree[leaf].is_leaf());
+        assert_eq!(tree[leaf].kind(), symbol("a"));
+        assert_eq!(tree.parent(leaf), Some(a));
+        let c = tree.children(x).nth(1).unwrap();
+        assert_eq!(tree.parent(c), Some(x));
+        match tree[c].children() {
+            NodeChildren::Single(leaf) => assert_eq!(tree[leaf].text("abc"), "c"),
+            _ => panic!("Error"),
+        }
+    }
+
     #[test]
     fn it_works() {
         let grammar = cnf_grammar! {
@@ -277,8 +865,8 @@ mod test {
         };
         let mut reader = Parser::new(&grammar);
         let result = reader.parse("3.51e+1");
-        if let Ok(node) = result {
-            assert_eq!(format!("{}", node), "3.51e+1");
+        if let Ok(tree) = result {
+            assert_eq!(tree.display(grammar.symbols()).to_string(), "3.51e+1");
         } else {
             panic!("Error")
         }
@@ -358,8 +946,11 @@ mod test {
         };
         let mut reader = Parser::new(&grammar);
         let result = reader.parse("3800909090.590900901e+1231231321");
-        if let Ok(node) = result {
-            assert_eq!(format!("{}", node), "3800909090.590900901e+1231231321");
+        if let Ok(tree) = result {
+            assert_eq!(
+                tree.display(grammar.symbols()).to_string(),
+                "3800909090.590900901e+1231231321"
+            );
         } else {
             panic!("Error")
         }
@@ -440,10 +1031,10 @@ mod test {
         println!("{:?}", grammar);
         let mut reader = Parser::new(&grammar);
         match reader.parse("12345678901234567890123456789012345678901234567890123456789012345678901234567890.12345678901234567890123456789012345678901234567890123456789012345678901234567890e+12345678901234567890123456789012345678901234567890123456789012345678901234567890") {
-            Ok(node) => {
-                assert_eq!(format!("{}", node), "12345678901234567890123456789012345678901234567890123456789012345678901234567890.12345678901234567890123456789012345678901234567890123456789012345678901234567890e+12345678901234567890123456789012345678901234567890123456789012345678901234567890");
+            Ok(tree) => {
+                assert_eq!(tree.display(grammar.symbols()).to_string(), "12345678901234567890123456789012345678901234567890123456789012345678901234567890.12345678901234567890123456789012345678901234567890123456789012345678901234567890e+12345678901234567890123456789012345678901234567890123456789012345678901234567890");
             }
-            Err(unknowns) => {
+            Err(_) => {
                 panic!("Error")
             },
         }
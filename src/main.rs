@@ -1,87 +1,768 @@
 extern crate cyk;
 
-use cyk::cnf_grammar;
+mod repl;
+
+use cyk::{
+    AmbiguityReport, Cfg, Comparison, CompiledGrammar, Diagnostic, Enumeration, Generator, Grammar,
+    GrammarAnalysis, LanguageProperties, Parser, Sampling, SourceMap, Symbol, CNF,
+};
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const USAGE: &str = "\
+usage: cyk <command> [options]
+
+commands:
+    parse GRAMMAR [INPUT]     parse every line of INPUT and print its tree
+        --text STRING             parse STRING instead of INPUT
+        --format FORMAT           sexpr (default), bracketed, json, dot or yield
+    check GRAMMAR             report symbols that take no part in any sentence
+    convert GRAMMAR           convert a grammar to Chomsky normal form
+        -o, --output FILE         write to FILE instead of stdout
+        --binary                  write the compiled binary format
+    generate GRAMMAR          print random sentences of the grammar
+        -n, --count N             number of sentences (default 10)
+        --seed SEED               seed of the random generator
+        --max-depth DEPTH         depth after which derivations are closed
+        --max-length N            longest sentence, in terminals
+        --uniform                 pick uniformly among derivation trees
+        --length N                pick uniformly among sentences of N terminals
+        --weights FILE            weigh productions as in a PCFG, one
+                                  `WEIGHT LEFT -> RIGHT...` per line
+    chart GRAMMAR [INPUT]     print the CYK table of every line of INPUT
+        --text STRING             use STRING instead of INPUT
+        --format FORMAT           text (default), html or dot
+    enumerate GRAMMAR         list the sentences in shortlex order
+        --max-length N            longest sentence, in terminals (default 8)
+        --counts                  print the number of sentences and
+                                  derivations of each length instead
+    ambiguity GRAMMAR         look for the shortest sentence with two trees
+        --max-length N            longest sentence checked (default 8)
+    compare GRAMMAR OTHER     look for a sentence only one grammar accepts
+        --max-length N            check every sentence up to N terminals (default 8)
+        --max-sentences N         sample the lengths with more derivations than N
+                                  instead of listing them (default 10000)
+        --samples N               then check N random sentences of each grammar
+        --sample-length N         longest random sentence (default twice --max-length)
+        --seed SEED               seed of the random generator
+        --inclusion               only look for sentences of GRAMMAR missing from OTHER
+    bench GRAMMAR [INPUT]     time parsing every line of INPUT
+        --text STRING             use STRING instead of INPUT
+        --iterations N            number of runs (default 10)
+    repl [GRAMMAR]            try sentences and edit rules interactively
+    help                      print this message
+
+GRAMMAR is a grammar in text form or compiled with `convert --binary`.
+Only check, convert, generate, compare and repl take grammars that are
+not in Chomsky normal form; run `convert` first for the other commands.
+INPUT defaults to stdin, which can also be given as `-`.
+
+exit status:
+    0   success
+    1   some input was rejected, `check` found problems, `ambiguity` found an
+        ambiguous sentence or `compare` found a counterexample
+    2   invalid command line
+    3   the grammar could not be read
+    4   a file could not be read or written
+";
+
+/// Why a command stopped, which decides the exit status.
+enum Failure {
+    Rejected,
+    Usage(String),
+    Grammar(String),
+    Io(String),
+}
+
+impl Failure {
+    fn code(&self) -> i32 {
+        match self {
+            Failure::Rejected => 1,
+            Failure::Usage(_) => 2,
+            Failure::Grammar(_) => 3,
+            Failure::Io(_) => 4,
+        }
+    }
+}
+
+type Outcome = Result<(), Failure>;
 
 fn main() {
-    let grammar = cnf_grammar! {
-        // 3.51e+1
-        // Number -> 0 | 1 | 2 | 3 | 4 | 5 | 6 | 7 | 8 | 9
-        // Number -> Integer Digit
-        // Number -> N1 Scale’ | Integer Fraction
-        // N1 -> Integer Fraction
-        // Integer -> 0 | 1 | 2 | 3 | 4 | 5 | 6 | 7 | 8 | 9
-        // Integer -> Integer Digit
-        // Fraction -> T1 Integer
-        // T1 -> .
-        // Scale’ -> N2 Integer
-        // N2 -> T2 Sign
-        // T2 -> e
-        // Digit -> 0 | 1 | 2 | 3 | 4 | 5 | 6 | 7 | 8 | 9
-        // Sign -> + | -
-        Start("Number");
-        NonTerminals [
-            "Number", "N1", "Integer", "Fraction",
-            "T1", "Scale", "N2", "T2", "Digit", "Sign"
-        ];
-        Terminals [
-            "0", "1", "2", "3", "4", "5", "6",
-            "7", "8", "9", ".", "e", "+", "-"
-        ];
-        Rules [
-            "Number" => [
-                ["Integer", "Digit"],
-                ["N1", "Scale"],
-                ["Integer", "Fraction"]
-            ],
-            "N1" => [
-                ["Integer", "Fraction"]
-            ],
-            "Integer" => [
-                ["Integer", "Digit"]
-            ],
-            "Fraction" => [
-                ["T1", "Integer"]
-            ],
-            "Scale" => [
-                ["N2", "Integer"]
-            ],
-            "N2" => [
-                ["T2", "Sign"]
-            ],
-        ];
-        TerminalRules [
-            "Number" => [
-                "0", "1", "2","3", "4", "5",
-                "6", "7", "8", "9",
-            ],
-            "Integer" => [
-                "0", "1", "2","3", "4", "5",
-                "6", "7", "8", "9",
-            ],
-            "T1" => [
-                "."
-            ],
-            "T2" => [
-                "e"
-            ],
-            "Digit" => [
-                "0", "1", "2","3", "4", "5",
-                "6", "7", "8", "9",
-            ],
-            "Sign" => [
-                "+", "-"
-            ]
-        ]
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let outcome = match args.first().map(String::as_str) {
+        None | Some("help") | Some("-h") | Some("--help") => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        Some("-V") | Some("--version") => {
+            println!("cyk {}", env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+        Some(command) => run(command, &args[1..]),
     };
-    println!("{:?}", grammar);
-    let mut parser = cyk::Parser::new(&grammar);
-    match parser.parse("3.51e+1") {
-        Ok(node) => {
-            println!("Ok: {:?}", node);
-            println!("Ok: {}", node);
+
+    if let Err(failure) = outcome {
+        match &failure {
+            Failure::Rejected => {}
+            Failure::Usage(message) => {
+                eprintln!("error: {}", message);
+                eprintln!("run `cyk help` for usage");
+            }
+            Failure::Grammar(message) | Failure::Io(message) => eprintln!("error: {}", message),
+        }
+        process::exit(failure.code());
+    }
+}
+
+fn run(command: &str, args: &[String]) -> Outcome {
+    match command {
+        "parse" => {
+            let args = Args::new(args, &["--text", "--format"], &[])?;
+            let format = args.value("--format").unwrap_or("sexpr");
+            if !["sexpr", "bracketed", "json", "dot", "yield"].contains(&format) {
+                return Err(Failure::Usage(format!("unknown tree format `{}`", format)));
+            }
+            let bytes = args.grammar()?;
+            let input = args.input()?;
+            match load(&args.positional[0], &bytes)? {
+                Loaded::Text(grammar) => parse(&grammar, &input, format),
+                Loaded::Compiled(grammar) => parse(&grammar, &input, format),
+            }
+        }
+        "check" => {
+            let args = Args::new(args, &[], &[])?;
+            args.expect_positional(1)?;
+            check(&args.positional[0], &args.grammar()?)
+        }
+        "convert" => {
+            let args = Args::new(args, &["-o", "--output"], &["--binary"])?;
+            args.expect_positional(1)?;
+            let bytes = args.grammar()?;
+            let output = args.value("-o").or_else(|| args.value("--output"));
+            convert(&args.positional[0], &bytes, output, args.flag("--binary"))
+        }
+        "generate" => {
+            let valued = [
+                "-n",
+                "--count",
+                "--seed",
+                "--max-depth",
+                "--max-length",
+                "--length",
+                "--weights",
+            ];
+            let args = Args::new(args, &valued, &["--uniform"])?;
+            args.expect_positional(1)?;
+            let count = args.number("-n")?.or(args.number("--count")?).unwrap_or(10);
+            let seed = match args.number("--seed")? {
+                Some(seed) => seed,
+                None => SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |time| time.as_nanos() as u64),
+            };
+            let max_depth = args.number("--max-depth")?;
+            let bytes = args.grammar()?;
+            let cfg = match load_cfg(&args.positional[0], &bytes)? {
+                LoadedCfg::Text(cfg) => cfg,
+                LoadedCfg::Compiled(grammar) => Cfg::from_grammar(&*grammar),
+            };
+            let generator = Generator::from_cfg(&cfg, seed);
+            let generator = match args.value("--weights") {
+                Some(path) => weigh(generator, &cfg, path)?,
+                None => generator,
+            };
+            let generator = match max_depth {
+                Some(max_depth) => generator.max_depth(max_depth as usize),
+                None => generator,
+            };
+            let generator = match args.number("--max-length")? {
+                Some(max_length) => generator.max_length(max_length as usize),
+                None => generator,
+            };
+            let generator = match (args.number("--length")?, args.flag("--uniform")) {
+                (Some(length), _) => generator.sampling(Sampling::Length(length as usize)),
+                (None, true) => generator.sampling(Sampling::Derivations),
+                (None, false) => generator,
+            };
+            generate(generator, count)
+        }
+        "chart" => {
+            let args = Args::new(args, &["--text", "--format"], &[])?;
+            let format = args.value("--format").unwrap_or("text");
+            if !["text", "html", "dot"].contains(&format) {
+                return Err(Failure::Usage(format!("unknown chart format `{}`", format)));
+            }
+            let bytes = args.grammar()?;
+            let input = args.input()?;
+            match load(&args.positional[0], &bytes)? {
+                Loaded::Text(grammar) => chart(&grammar, &input, format),
+                Loaded::Compiled(grammar) => chart(&grammar, &input, format),
+            }
+        }
+        "repl" => match args {
+            [] => repl::run(None),
+            [path] if !path.starts_with('-') => repl::run(Some(path)),
+            [arg] => Err(Failure::Usage(format!("unknown option `{}`", arg))),
+            [_, extra, ..] => Err(Failure::Usage(format!("unexpected argument `{}`", extra))),
+        },
+        "enumerate" => {
+            let args = Args::new(args, &["--max-length"], &["--counts"])?;
+            args.expect_positional(1)?;
+            let max_length = args.number("--max-length")?.unwrap_or(8) as usize;
+            let bytes = args.grammar()?;
+            let mut language = match load(&args.positional[0], &bytes)? {
+                Loaded::Text(grammar) => Enumeration::new(&grammar, max_length),
+                Loaded::Compiled(grammar) => Enumeration::new(&grammar, max_length),
+            };
+            if args.flag("--counts") {
+                println!("length\tstrings\tderivations");
+                for length in 1..=max_length {
+                    let (strings, derivations) =
+                        (language.strings(length), language.derivations(length));
+                    println!("{}\t{}\t{}", length, strings, derivations);
+                }
+                Ok(())
+            } else {
+                let mut out = String::new();
+                for sentence in language {
+                    out.push_str(&sentence);
+                    out.push('\n');
+                }
+                write_output(None, out.as_bytes())
+            }
+        }
+        "ambiguity" => {
+            let args = Args::new(args, &["--max-length"], &[])?;
+            args.expect_positional(1)?;
+            let max_length = args.number("--max-length")?.unwrap_or(8) as usize;
+            let bytes = args.grammar()?;
+            let report = match load(&args.positional[0], &bytes)? {
+                Loaded::Text(grammar) => AmbiguityReport::new(&grammar, max_length),
+                Loaded::Compiled(grammar) => AmbiguityReport::new(&grammar, max_length),
+            };
+            print!("{}", report);
+            if report.is_ambiguous() {
+                Err(Failure::Rejected)
+            } else {
+                Ok(())
+            }
+        }
+        "compare" => {
+            let valued = [
+                "--max-length",
+                "--max-sentences",
+                "--samples",
+                "--sample-length",
+                "--seed",
+            ];
+            let args = Args::new(args, &valued, &["--inclusion"])?;
+            if args.positional.len() < 2 {
+                return Err(Failure::Usage("missing second grammar file".to_string()));
+            }
+            args.expect_positional(2)?;
+            let max_length = args.number("--max-length")?.unwrap_or(8) as usize;
+            let samples = args.number("--samples")?.unwrap_or(0) as usize;
+            let sample_length = args
+                .number("--sample-length")?
+                .unwrap_or(2 * max_length as u64);
+            let options = CompareOptions {
+                max_length,
+                max_sentences: args.number("--max-sentences")?.unwrap_or(10_000),
+                samples,
+                sample_length: sample_length as usize,
+                seed: args.number("--seed")?.unwrap_or(0),
+                inclusion: args.flag("--inclusion"),
+            };
+
+            let (first, second) = (
+                read_file(&args.positional[0])?,
+                read_file(&args.positional[1])?,
+            );
+            let first = load_converted(&args.positional[0], &first)?;
+            let second = load_converted(&args.positional[1], &second)?;
+            match (&first, &second) {
+                (Loaded::Text(a), Loaded::Text(b)) => compare(a, b, &options),
+                (Loaded::Text(a), Loaded::Compiled(b)) => compare(a, b, &options),
+                (Loaded::Compiled(a), Loaded::Text(b)) => compare(a, b, &options),
+                (Loaded::Compiled(a), Loaded::Compiled(b)) => compare(a, b, &options),
+            }
+        }
+        "bench" => {
+            let args = Args::new(args, &["--text", "--iterations"], &[])?;
+            let iterations = args.number("--iterations")?.unwrap_or(10);
+            if iterations == 0 {
+                return Err(Failure::Usage("--iterations must be positive".to_string()));
+            }
+            let bytes = args.grammar()?;
+            let input = args.input()?;
+            match load(&args.positional[0], &bytes)? {
+                Loaded::Text(grammar) => bench(&grammar, &input, iterations as usize),
+                Loaded::Compiled(grammar) => bench(&grammar, &input, iterations as usize),
+            }
+        }
+        _ => Err(Failure::Usage(format!("unknown command `{}`", command))),
+    }
+}
+
+/// The positional arguments and options of a command.
+struct Args {
+    positional: Vec<String>,
+    values: HashMap<String, String>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    fn new(args: &[String], valued: &[&str], flags: &[&str]) -> Result<Args, Failure> {
+        let mut result = Args {
+            positional: vec![],
+            values: HashMap::new(),
+            flags: vec![],
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if valued.contains(&arg.as_str()) {
+                let value = args
+                    .next()
+                    .ok_or_else(|| Failure::Usage(format!("`{}` needs a value", arg)))?;
+                result.values.insert(arg.clone(), value.clone());
+            } else if flags.contains(&arg.as_str()) {
+                result.flags.push(arg.clone());
+            } else if arg.starts_with('-') && arg != "-" {
+                return Err(Failure::Usage(format!("unknown option `{}`", arg)));
+            } else {
+                result.positional.push(arg.clone());
+            }
+        }
+
+        if result.positional.is_empty() {
+            return Err(Failure::Usage("missing grammar file".to_string()));
+        }
+        Ok(result)
+    }
+
+    fn expect_positional(&self, max: usize) -> Outcome {
+        match self.positional.get(max) {
+            Some(extra) => Err(Failure::Usage(format!("unexpected argument `{}`", extra))),
+            None => Ok(()),
         }
-        Err(unknowns) => unknowns.iter().for_each(|item| {
-            println!("Err: {:?}", item);
-        }),
     }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+
+    fn number(&self, name: &str) -> Result<Option<u64>, Failure> {
+        match self.value(name) {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| Failure::Usage(format!("`{}` needs a number, not `{}`", name, value))),
+            None => Ok(None),
+        }
+    }
+
+    fn grammar(&self) -> Result<Vec<u8>, Failure> {
+        read_file(&self.positional[0])
+    }
+
+    /// Reads the text given by `--text`, or else the input file or stdin.
+    fn input(&self) -> Result<String, Failure> {
+        self.expect_positional(2)?;
+        if let Some(text) = self.value("--text") {
+            if self.positional.len() > 1 {
+                return Err(Failure::Usage(
+                    "give either an input file or `--text`".to_string(),
+                ));
+            }
+            return Ok(text.to_string());
+        }
+
+        match self.positional.get(1).map(String::as_str) {
+            None | Some("-") => {
+                let mut input = String::new();
+                io::stdin()
+                    .read_to_string(&mut input)
+                    .map_err(|error| Failure::Io(format!("stdin: {}", error)))?;
+                Ok(input)
+            }
+            Some(path) => String::from_utf8(read_file(path)?)
+                .map_err(|_| Failure::Io(format!("{}: not valid UTF-8", path))),
+        }
+    }
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, Failure> {
+    fs::read(path).map_err(|error| Failure::Io(format!("{}: {}", path, error)))
+}
+
+fn write_output(path: Option<&str>, bytes: &[u8]) -> Outcome {
+    let result = match path {
+        Some(path) => fs::write(path, bytes).map_err(|error| (path, error)),
+        None => io::stdout()
+            .write_all(bytes)
+            .map_err(|error| ("stdout", error)),
+    };
+    result.map_err(|(path, error)| Failure::Io(format!("{}: {}", path, error)))
+}
+
+enum Loaded<'b> {
+    Text(CNF),
+    Compiled(CompiledGrammar<'b>),
+}
+
+enum LoadedCfg<'b> {
+    Text(Cfg),
+    Compiled(Box<CompiledGrammar<'b>>),
+}
+
+/// Reads a grammar that is already in Chomsky normal form.
+fn load<'b>(path: &str, bytes: &'b [u8]) -> Result<Loaded<'b>, Failure> {
+    match load_cfg(path, bytes)? {
+        LoadedCfg::Text(cfg) if !cfg.is_cnf() => Err(Failure::Grammar(format!(
+            "{}: not in Chomsky normal form; run `cyk convert` first",
+            path
+        ))),
+        LoadedCfg::Text(cfg) => cfg
+            .as_cnf()
+            .map(Loaded::Text)
+            .map_err(|error| Failure::Grammar(format!("{}: {}", path, error))),
+        LoadedCfg::Compiled(grammar) => Ok(Loaded::Compiled(*grammar)),
+    }
+}
+
+/// Reads a grammar, converting one in text form to Chomsky normal form.
+fn load_converted<'b>(path: &str, bytes: &'b [u8]) -> Result<Loaded<'b>, Failure> {
+    match load_cfg(path, bytes)? {
+        LoadedCfg::Text(cfg) => cfg
+            .to_cnf()
+            .map(Loaded::Text)
+            .map_err(|error| Failure::Grammar(format!("{}: {}", path, error))),
+        LoadedCfg::Compiled(grammar) => Ok(Loaded::Compiled(*grammar)),
+    }
+}
+
+fn load_cfg<'b>(path: &str, bytes: &'b [u8]) -> Result<LoadedCfg<'b>, Failure> {
+    if CompiledGrammar::is_compiled(bytes) {
+        // A compiled grammar keeps its own symbol table, so it is only ever
+        // printed through it.
+        let grammar = CompiledGrammar::from_bytes(bytes)
+            .map_err(|error| Failure::Grammar(format!("{}: {}", path, error)))?;
+        return Ok(LoadedCfg::Compiled(Box::new(grammar)));
+    }
+
+    let src = std::str::from_utf8(bytes)
+        .map_err(|_| Failure::Grammar(format!("{}: not valid UTF-8", path)))?;
+    Cfg::parse(src).map(LoadedCfg::Text).map_err(|error| {
+        let position = SourceMap::new(src).line_col(error.offset());
+        Failure::Grammar(format!("{}:{}: {}", path, position, error.message()))
+    })
+}
+
+/// The non-empty lines of `input` with their 1-based line numbers.
+fn sentences(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim_end_matches('\r')))
+        .filter(|(_, line)| !line.is_empty())
+}
+
+fn parse<G: Grammar + Debug + Clone>(grammar: &G, input: &str, format: &str) -> Outcome {
+    let mut parser = Parser::new(grammar);
+    let mut out = String::new();
+    let mut rejected = false;
+
+    for (line, sentence) in sentences(input) {
+        match parser.parse(sentence) {
+            Ok(tree) => {
+                let symbols = grammar.symbols();
+                match format {
+                    "bracketed" => out.push_str(&tree.to_bracketed(symbols)),
+                    "json" => out.push_str(&tree.to_json(symbols)),
+                    "dot" => out.push_str(&tree.to_dot(symbols)),
+                    "yield" => out.push_str(tree.text(sentence)),
+                    _ => out.push_str(&tree.to_sexpr(symbols)),
+                }
+                if !out.ends_with('\n') {
+                    out.push('\n');
+                }
+            }
+            Err(unknowns) => {
+                rejected = true;
+                report(line, sentence, &unknowns);
+            }
+        }
+    }
+
+    write_output(None, out.as_bytes())?;
+    if rejected {
+        Err(Failure::Rejected)
+    } else {
+        Ok(())
+    }
+}
+
+fn report(line: usize, sentence: &str, unknowns: &[Diagnostic]) {
+    if unknowns.is_empty() {
+        eprintln!("{}: rejected: {}", line, sentence);
+    }
+    for unknown in unknowns {
+        eprintln!(
+            "{}:{}: unknown char {:?}",
+            line,
+            unknown.span().start() + 1,
+            unknown.char()
+        );
+    }
+}
+
+/// Analyzes a text grammar as written, and the language of its Chomsky
+/// normal form.
+fn check(path: &str, bytes: &[u8]) -> Outcome {
+    let (analysis, properties) = match load_cfg(path, bytes)? {
+        LoadedCfg::Text(cfg) if !cfg.is_cnf() => match cfg.to_cnf() {
+            Ok(grammar) => (cfg.analyze(), grammar.properties()),
+            Err(error) => {
+                println!("{}: {}", path, error);
+                return Err(Failure::Rejected);
+            }
+        },
+        LoadedCfg::Text(cfg) => match cfg.as_cnf() {
+            Ok(grammar) => (cfg.analyze(), grammar.properties()),
+            Err(error) => {
+                println!("{}: {}", path, error);
+                return Err(Failure::Rejected);
+            }
+        },
+        LoadedCfg::Compiled(grammar) => (
+            GrammarAnalysis::new(&*grammar),
+            LanguageProperties::new(&*grammar),
+        ),
+    };
+
+    print!("{}{}", analysis, properties);
+    if analysis.is_reduced() {
+        Ok(())
+    } else {
+        Err(Failure::Rejected)
+    }
+}
+
+fn convert(path: &str, bytes: &[u8], output: Option<&str>, binary: bool) -> Outcome {
+    let cfg = match load_cfg(path, bytes)? {
+        LoadedCfg::Text(cfg) => cfg,
+        LoadedCfg::Compiled(grammar) => Cfg::from_grammar(&*grammar),
+    };
+    let grammar = cfg
+        .to_cnf()
+        .map_err(|error| Failure::Grammar(format!("{}: {}", path, error)))?;
+
+    if binary {
+        write_output(output, &CompiledGrammar::compile(&grammar))
+    } else {
+        write_output(output, Cfg::from_grammar(&grammar).to_string().as_bytes())
+    }
+}
+
+/// Reads a weight for some productions of `cfg` from every non-empty line
+/// of the file at `path`, written as `0.5 S -> 'a' S`.
+fn weigh(mut generator: Generator, cfg: &Cfg, path: &str) -> Result<Generator, Failure> {
+    let src = String::from_utf8(read_file(path)?)
+        .map_err(|_| Failure::Io(format!("{}: not valid UTF-8", path)))?;
+
+    for (line, text) in sentences(&src) {
+        let failure = |message: &str| Failure::Grammar(format!("{}:{}: {}", path, line, message));
+        let (weight, production) = text
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| failure("expected a weight and a production"))?;
+        let weight: f64 = match weight.parse() {
+            Ok(weight) if weight >= 0.0 => weight,
+            _ => return Err(failure(&format!("invalid weight `{}`", weight))),
+        };
+        let parsed =
+            Cfg::parse(&format!("{};", production)).map_err(|error| failure(error.message()))?;
+        let (left, right) = match parsed.productions() {
+            [production] => production,
+            _ => return Err(failure("expected a single production")),
+        };
+        // The production was read into a table of its own, so its symbols
+        // are looked up by name in the grammar's.
+        let symbol = |symbol: &Symbol| {
+            let name = parsed.symbols().resolve(*symbol)?;
+            cfg.symbols().get(name)
+        };
+        let found = symbol(left).and_then(|left| {
+            let right: Option<Vec<Symbol>> = right.iter().map(symbol).collect();
+            Some((left, right?))
+        });
+        let (left, right) = match found {
+            Some(production) if cfg.productions().contains(&production) => production,
+            _ => {
+                return Err(failure(&format!(
+                    "the grammar has no production {}",
+                    production.trim()
+                )))
+            }
+        };
+        generator = generator.weight(left, &right, weight);
+    }
+
+    Ok(generator)
+}
+
+fn generate(mut generator: Generator, count: u64) -> Outcome {
+    let mut out = String::new();
+    for _ in 0..count {
+        match generator.sentence() {
+            Some(sentence) => {
+                out.push_str(&sentence);
+                out.push('\n');
+            }
+            None => {
+                eprintln!("the grammar generates no sentence within the limits");
+                return Err(Failure::Rejected);
+            }
+        }
+    }
+    write_output(None, out.as_bytes())
+}
+
+fn chart<G: Grammar + Debug + Clone>(grammar: &G, input: &str, format: &str) -> Outcome {
+    let mut parser = Parser::new(grammar);
+    let mut out = String::new();
+    let mut rejected = false;
+
+    for (line, sentence) in sentences(input) {
+        if let Err(unknowns) = parser.parse(sentence) {
+            rejected = true;
+            report(line, sentence, &unknowns);
+        }
+        let chart = parser.chart();
+        match format {
+            "html" => out.push_str(&chart.to_html()),
+            "dot" => out.push_str(&chart.to_dot()),
+            _ => out.push_str(&chart.to_text()),
+        }
+        out.push('\n');
+    }
+
+    write_output(None, out.as_bytes())?;
+    if rejected {
+        Err(Failure::Rejected)
+    } else {
+        Ok(())
+    }
+}
+
+struct CompareOptions {
+    max_length: usize,
+    max_sentences: u64,
+    samples: usize,
+    sample_length: usize,
+    seed: u64,
+    inclusion: bool,
+}
+
+fn compare<A, B>(first: &A, second: &B, options: &CompareOptions) -> Outcome
+where
+    A: Grammar + Debug + Clone,
+    B: Grammar + Debug + Clone,
+{
+    let comparison = Comparison::new(first, second)
+        .max_length(options.max_length)
+        .max_sentences(options.max_sentences)
+        .samples(options.samples, options.sample_length)
+        .seed(options.seed);
+    let counterexample = if options.inclusion {
+        comparison.included()
+    } else {
+        comparison.equivalent()
+    };
+
+    match counterexample {
+        Some(counterexample) => {
+            println!("{}", counterexample);
+            Err(Failure::Rejected)
+        }
+        None => {
+            let what = if options.inclusion {
+                "included"
+            } else {
+                "equal"
+            };
+            let enumerated = comparison.enumerated_length();
+            let sampled = format!("on samples of each length up to {}", options.max_length);
+            match enumerated {
+                0 => print!("{} {}", what, sampled),
+                _ if enumerated < options.max_length => {
+                    print!("{} up to length {}, and {}", what, enumerated, sampled)
+                }
+                _ => print!("{} up to length {}", what, enumerated),
+            }
+            if options.samples > 0 {
+                print!(
+                    ", and on {} samples up to length {}",
+                    options.samples, options.sample_length
+                );
+            }
+            println!();
+            Ok(())
+        }
+    }
+}
+
+fn bench<G: Grammar + Debug + Clone>(grammar: &G, input: &str, iterations: usize) -> Outcome {
+    let sentences: Vec<(usize, &str)> = sentences(input).collect();
+    let chars: usize = sentences
+        .iter()
+        .map(|(_, sentence)| sentence.chars().count())
+        .sum();
+    let mut parser = Parser::new(grammar);
+    let mut times: Vec<Duration> = Vec::with_capacity(iterations);
+    let mut rejected = 0;
+
+    for iteration in 0..iterations {
+        let started = Instant::now();
+        for &(line, sentence) in &sentences {
+            if let Err(unknowns) = parser.parse(sentence) {
+                if iteration == 0 {
+                    rejected += 1;
+                    report(line, sentence, &unknowns);
+                }
+            }
+        }
+        times.push(started.elapsed());
+    }
+
+    let total: Duration = times.iter().sum();
+    let mean = Duration::from_secs_f64(total.as_secs_f64() / iterations as f64);
+    println!(
+        "{} sentences, {} chars, {} rejected",
+        sentences.len(),
+        chars,
+        rejected
+    );
+    println!(
+        "{} iterations: mean {:.3?}, min {:.3?}, max {:.3?}",
+        iterations,
+        mean,
+        times.iter().min().unwrap(),
+        times.iter().max().unwrap()
+    );
+    if !mean.is_zero() {
+        println!("{:.0} chars/s", chars as f64 / mean.as_secs_f64());
+    }
+    Ok(())
 }
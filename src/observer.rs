@@ -0,0 +1,134 @@
+use super::{Production, Span, Symbol};
+
+/// Receives the steps the parser takes while it fills the chart.
+///
+/// Every method does nothing by default, and a parser built with `Parser::new`
+/// uses `NoObserver`, whose calls compile away.
+pub trait ParseObserver {
+    /// The parser starts looking for the symbols of `span`.
+    fn cell_started(&mut self, _span: Span) {}
+
+    /// `symbol -> terminal` matched the single char of `span`.
+    fn terminal_matched(&mut self, _span: Span, _symbol: Symbol, _terminal: Symbol) {}
+
+    /// No terminal rule matched the char of `span`.
+    fn unknown_char(&mut self, _span: Span, _c: char) {}
+
+    /// `production` derived `span`, its second child starting at char `split`.
+    fn rule_applied(&mut self, _span: Span, _production: Production, _split: usize) {}
+
+    /// The parser is done with `span`, having found `entries` derivations.
+    fn cell_finished(&mut self, _span: Span, _entries: usize) {}
+}
+
+/// The observer of parsers that were not given one.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NoObserver;
+
+impl ParseObserver for NoObserver {}
+
+impl<O: ParseObserver + ?Sized> ParseObserver for &mut O {
+    fn cell_started(&mut self, span: Span) {
+        (**self).cell_started(span)
+    }
+
+    fn terminal_matched(&mut self, span: Span, symbol: Symbol, terminal: Symbol) {
+        (**self).terminal_matched(span, symbol, terminal)
+    }
+
+    fn unknown_char(&mut self, span: Span, c: char) {
+        (**self).unknown_char(span, c)
+    }
+
+    fn rule_applied(&mut self, span: Span, production: Production, split: usize) {
+        (**self).rule_applied(span, production, split)
+    }
+
+    fn cell_finished(&mut self, span: Span, entries: usize) {
+        (**self).cell_finished(span, entries)
+    }
+}
+
+/// One step of the parser, as recorded by `Vec<ParseEvent>`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ParseEvent {
+    CellStarted(Span),
+    TerminalMatched(Span, Symbol, Symbol),
+    UnknownChar(Span, char),
+    RuleApplied(Span, Production, usize),
+    CellFinished(Span, usize),
+}
+
+impl ParseObserver for Vec<ParseEvent> {
+    fn cell_started(&mut self, span: Span) {
+        self.push(ParseEvent::CellStarted(span))
+    }
+
+    fn terminal_matched(&mut self, span: Span, symbol: Symbol, terminal: Symbol) {
+        self.push(ParseEvent::TerminalMatched(span, symbol, terminal))
+    }
+
+    fn unknown_char(&mut self, span: Span, c: char) {
+        self.push(ParseEvent::UnknownChar(span, c))
+    }
+
+    fn rule_applied(&mut self, span: Span, production: Production, split: usize) {
+        self.push(ParseEvent::RuleApplied(span, production, split))
+    }
+
+    fn cell_finished(&mut self, span: Span, entries: usize) {
+        self.push(ParseEvent::CellFinished(span, entries))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn record_events() {
+        let grammar = cnf_grammar! {
+            Start("S");
+            NonTerminals["S", "A", "B"];
+            Terminals["a", "b"];
+            Rules [
+                "S" => [["A", "B"]]
+            ];
+            TerminalRules [
+                "A" => ["a"],
+                "B" => ["b"]
+            ]
+        };
+        let s = Symbol::intern;
+        let mut events: Vec<ParseEvent> = Vec::new();
+
+        let mut parser = Parser::with_observer(&grammar, &mut events);
+        assert!(parser.parse("ab").is_ok());
+        assert!(parser.parse("a?").is_err());
+        drop(parser);
+
+        let whole = Span::new(0, 2);
+        assert_eq!(
+            events,
+            vec![
+                ParseEvent::CellStarted(Span::new(0, 1)),
+                ParseEvent::TerminalMatched(Span::new(0, 1), s("A"), s("a")),
+                ParseEvent::CellFinished(Span::new(0, 1), 1),
+                ParseEvent::CellStarted(Span::new(1, 1)),
+                ParseEvent::TerminalMatched(Span::new(1, 1), s("B"), s("b")),
+                ParseEvent::CellFinished(Span::new(1, 1), 1),
+                ParseEvent::CellStarted(whole),
+                ParseEvent::RuleApplied(whole, Production::Binary(s("S"), s("A"), s("B")), 1),
+                ParseEvent::CellFinished(whole, 1),
+                ParseEvent::CellStarted(Span::new(0, 1)),
+                ParseEvent::TerminalMatched(Span::new(0, 1), s("A"), s("a")),
+                ParseEvent::CellFinished(Span::new(0, 1), 1),
+                ParseEvent::CellStarted(Span::new(1, 1)),
+                ParseEvent::UnknownChar(Span::new(1, 1), '?'),
+                ParseEvent::CellFinished(Span::new(1, 1), 0),
+                ParseEvent::CellStarted(whole),
+                ParseEvent::CellFinished(whole, 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use super::analysis::productive_symbols;
+use super::{Grammar, HashSet, ParseObserver, Parser, Span, Symbol};
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+fn first_terminals<G: Grammar>(
+    grammar: &G,
+    productive: &HashSet<Symbol>,
+) -> HashMap<Symbol, HashSet<Symbol>> {
+    let mut result: HashMap<Symbol, HashSet<Symbol>> = HashMap::new();
+
+    for rule in grammar.terminal_rules().iter() {
+        result
+            .entry(rule.start())
+            .or_default()
+            .extend(rule.symbols());
+    }
+
+    loop {
+        let mut changed = false;
+
+        for rule in grammar.rules().iter() {
+            for branch in rule.branches() {
+                if !productive.contains(&branch.left()) || !productive.contains(&branch.right()) {
+                    continue;
+                }
+
+                let found: HashSet<Symbol> = match result.get(&branch.left()) {
+                    Some(symbols) => symbols.clone(),
+                    None => continue,
+                };
+
+                let entry = result.entry(rule.start()).or_default();
+                let before = entry.len();
+                entry.extend(found);
+                changed |= entry.len() != before;
+            }
+        }
+
+        if !changed {
+            return result;
+        }
+    }
+}
+
+impl<'a, G: Grammar + Debug + Clone, O: ParseObserver> Parser<'a, G, O> {
+    /// Returns the terminals `t` for which `prefix t` is still a prefix of
+    /// some sentence of the start symbol.
+    pub fn next_terminals(&mut self, prefix: &'a str) -> HashSet<Symbol> {
+        self.fill(prefix);
+
+        let productive = productive_symbols(self.grammar);
+        let len = self.src_len();
+
+        // next[i][A] holds the terminals t such that A derives
+        // `prefix[i..] t ...`, where next[len] is the plain FIRST set.
+        let mut next: Vec<HashMap<Symbol, HashSet<Symbol>>> = vec![HashMap::new(); len + 1];
+        next[len] = first_terminals(self.grammar, &productive);
+
+        for i in (0..len).rev() {
+            let mut sets: HashMap<Symbol, HashSet<Symbol>> = HashMap::new();
+
+            loop {
+                let mut changed = false;
+
+                for rule in self.grammar.rules().iter() {
+                    for branch in rule.branches() {
+                        if !productive.contains(&branch.left())
+                            || !productive.contains(&branch.right())
+                        {
+                            continue;
+                        }
+
+                        let mut found: HashSet<Symbol> = HashSet::new();
+
+                        // The base symbol covers the rest of the prefix and more.
+                        if let Some(symbols) = sets.get(&branch.left()) {
+                            found.extend(symbols);
+                        }
+
+                        // The base symbol covers exactly `prefix[i..k]`.
+                        for (k, suffix) in next.iter().enumerate().skip(i + 1) {
+                            if self.chart_contains(self.span(i, k - i), branch.left()) {
+                                if let Some(symbols) = suffix.get(&branch.right()) {
+                                    found.extend(symbols);
+                                }
+                            }
+                        }
+
+                        if !found.is_empty() {
+                            let entry = sets.entry(rule.start()).or_default();
+                            let before = entry.len();
+                            entry.extend(found);
+                            changed |= entry.len() != before;
+                        }
+                    }
+                }
+
+                if !changed {
+                    break;
+                }
+            }
+
+            next[i] = sets;
+        }
+
+        next.swap_remove(0)
+            .remove(&self.grammar.start_symbol())
+            .unwrap_or_default()
+    }
+
+    /// Returns whether `prefix` can be extended to a sentence of the start
+    /// symbol, including the case where it already is one.
+    pub fn is_viable_prefix(&mut self, prefix: &'a str) -> bool {
+        if prefix.is_empty() {
+            return productive_symbols(self.grammar).contains(&self.grammar.start_symbol());
+        }
+
+        !self.next_terminals(prefix).is_empty()
+            || self.chart_contains(self.span(0, self.src_len()), self.grammar.start_symbol())
+    }
+
+    fn chart_contains(&self, span: Span, symbol: Symbol) -> bool {
+        self.get_cell(span)
+            .and_then(|cell| cell.has(symbol))
+            .is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    fn number_grammar() -> CNF {
+        cnf_grammar! {
+            Start("Number");
+            NonTerminals[
+                "Number", "N1", "Integer", "Fraction",
+                "T1", "Scale", "N2", "T2", "Digit", "Sign"
+            ];
+            Terminals[
+                "0", "1", "2", "3", "4", "5", "6",
+                "7", "8", "9", ".", "e", "+", "-"
+            ];
+            Rules [
+                "Number" => [
+                    ["Integer", "Digit"],
+                    ["N1", "Scale"],
+                    ["Integer", "Fraction"]
+                ],
+                "N1" => [
+                    ["Integer", "Fraction"]
+                ],
+                "Integer" => [
+                    ["Integer", "Digit"]
+                ],
+                "Fraction" => [
+                    ["T1", "Integer"]
+                ],
+                "Scale" => [
+                    ["N2", "Integer"]
+                ],
+                "N2" => [
+                    ["T2", "Sign"]
+                ],
+            ];
+            TerminalRules [
+                "Number" => [
+                    "0", "1", "2","3", "4", "5",
+                    "6", "7", "8", "9",
+                ],
+                "Integer" => [
+                    "0", "1", "2","3", "4", "5",
+                    "6", "7", "8", "9",
+                ],
+                "T1" => [
+                    "."
+                ],
+                "T2" => [
+                    "e"
+                ],
+                "Digit" => [
+                    "0", "1", "2","3", "4", "5",
+                    "6", "7", "8", "9",
+                ],
+                "Sign" => [
+                    "+", "-"
+                ]
+            ]
+        }
+    }
+
+    fn symbols(names: &[&str]) -> HashSet<Symbol> {
+        names.iter().map(|name| Symbol::intern(name)).collect()
+    }
+
+    #[test]
+    fn next_terminals_of_prefix() {
+        let grammar = number_grammar();
+        let digits = symbols(&["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"]);
+        let mut parser = Parser::new(&grammar);
+
+        assert_eq!(parser.next_terminals("3.5e"), symbols(&["+", "-"]));
+        assert_eq!(parser.next_terminals("3."), digits);
+        assert_eq!(parser.next_terminals("3.5e+"), digits);
+        assert!(parser.next_terminals("e").is_empty());
+    }
+
+    #[test]
+    fn viable_prefix() {
+        let grammar = number_grammar();
+        let mut parser = Parser::new(&grammar);
+
+        assert!(parser.is_viable_prefix(""));
+        assert!(parser.is_viable_prefix("3.5e"));
+        assert!(parser.is_viable_prefix("3.51e+1"));
+        assert!(!parser.is_viable_prefix("3.5.1"));
+        assert!(!parser.is_viable_prefix("3x"));
+    }
+}
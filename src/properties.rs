@@ -0,0 +1,362 @@
+use super::analysis::productive_symbols;
+use super::{Enumeration, Grammar, HashSet, Symbol, SymbolTable, CNF};
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+
+/// Decidable properties of the language of a CNF grammar: emptiness,
+/// finiteness, the shortest sentence of every non-terminal and which
+/// non-terminals are left or right recursive.
+///
+/// Only the rules whose symbols all derive some sentence are taken into
+/// account, since the others take part in no derivation.
+#[derive(Clone, Debug)]
+pub struct LanguageProperties {
+    empty: bool,
+    /// The number of sentences and the longest one with its length in
+    /// terminals, when the language is finite.
+    finite: Option<(usize, usize, String)>,
+    shortest: HashMap<Symbol, usize>,
+    left_recursive: Vec<Symbol>,
+    right_recursive: Vec<Symbol>,
+    symbols: SymbolTable,
+}
+
+impl LanguageProperties {
+    pub fn new<G: Grammar + Debug + Clone>(grammar: &G) -> Self {
+        let productive = productive_symbols(grammar);
+        let mut binary: Vec<(Symbol, Symbol, Symbol)> = Vec::new();
+        for rule in grammar.rules().iter() {
+            for branch in rule.branches() {
+                let symbols = [rule.start(), branch.left(), branch.right()];
+                if symbols.iter().all(|symbol| productive.contains(symbol)) {
+                    binary.push((rule.start(), branch.left(), branch.right()));
+                }
+            }
+        }
+
+        let start = grammar.start_symbol();
+        let empty = !productive.contains(&start);
+        let left = |&(left, first, _): &(Symbol, Symbol, Symbol)| (left, first);
+        let right = |&(left, _, second): &(Symbol, Symbol, Symbol)| (left, second);
+        let table = grammar.symbols();
+
+        LanguageProperties {
+            empty,
+            finite: finiteness(grammar, &binary, empty),
+            shortest: shortest(grammar, &binary),
+            left_recursive: recursive(&binary.iter().map(left).collect::<Vec<_>>(), table),
+            right_recursive: recursive(&binary.iter().map(right).collect::<Vec<_>>(), table),
+            symbols: table.clone(),
+        }
+    }
+
+    /// Whether the grammar derives no sentence at all.
+    pub fn is_empty(&self) -> bool {
+        self.empty
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.finite.is_some()
+    }
+
+    /// The number of sentences of a finite language.
+    pub fn size(&self) -> Option<usize> {
+        self.finite.as_ref().map(|(size, _, _)| *size)
+    }
+
+    /// The length of the longest sentence of a finite, non-empty language.
+    pub fn longest(&self) -> Option<usize> {
+        self.finite
+            .as_ref()
+            .filter(|_| !self.empty)
+            .map(|(_, longest, _)| *longest)
+    }
+
+    /// A longest sentence of a finite, non-empty language. Where several
+    /// terminals would do, the one first by name is used.
+    pub fn longest_sentence(&self) -> Option<&str> {
+        self.finite
+            .as_ref()
+            .filter(|_| !self.empty)
+            .map(|(_, _, sentence)| sentence.as_str())
+    }
+
+    /// The length of the shortest sentence `symbol` derives, or `None` if
+    /// it derives none.
+    pub fn shortest(&self, symbol: Symbol) -> Option<usize> {
+        self.shortest.get(&symbol).copied()
+    }
+
+    /// Whether `symbol` derives a sentential form starting with itself.
+    pub fn is_left_recursive(&self, symbol: Symbol) -> bool {
+        self.left_recursive.contains(&symbol)
+    }
+
+    /// Whether `symbol` derives a sentential form ending with itself.
+    pub fn is_right_recursive(&self, symbol: Symbol) -> bool {
+        self.right_recursive.contains(&symbol)
+    }
+
+    /// The left-recursive non-terminals, sorted by name.
+    pub fn left_recursive(&self) -> &[Symbol] {
+        &self.left_recursive
+    }
+
+    /// The right-recursive non-terminals, sorted by name.
+    pub fn right_recursive(&self) -> &[Symbol] {
+        &self.right_recursive
+    }
+
+    fn name(&self, symbol: Symbol) -> &str {
+        self.symbols.resolve(symbol).unwrap_or("")
+    }
+}
+
+impl fmt::Display for LanguageProperties {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.finite {
+            _ if self.empty => writeln!(f, "language: empty")?,
+            Some((size, longest, sentence)) => writeln!(
+                f,
+                "language: finite, {} sentences, the longest of length {}: {}",
+                size, longest, sentence
+            )?,
+            None => writeln!(f, "language: infinite")?,
+        }
+
+        let mut shortest: Vec<(&str, usize)> = self
+            .shortest
+            .iter()
+            .map(|(&symbol, &length)| (self.name(symbol), length))
+            .collect();
+        shortest.sort_unstable();
+        write!(f, "shortest:")?;
+        for (name, length) in shortest {
+            write!(f, " {}={}", name, length)?;
+        }
+        writeln!(f)?;
+
+        for (label, symbols) in [
+            ("left-recursive", &self.left_recursive),
+            ("right-recursive", &self.right_recursive),
+        ] {
+            write!(f, "{}:", label)?;
+            for &symbol in symbols {
+                write!(f, " {}", self.name(symbol))?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the length of the shortest sentence of every productive
+/// non-terminal.
+fn shortest<G: Grammar>(
+    grammar: &G,
+    binary: &[(Symbol, Symbol, Symbol)],
+) -> HashMap<Symbol, usize> {
+    let mut result: HashMap<Symbol, usize> = HashMap::new();
+    for rule in grammar.terminal_rules().iter() {
+        if rule.symbols().next().is_some() {
+            result.insert(rule.start(), 1);
+        }
+    }
+
+    loop {
+        let mut changed = false;
+
+        for &(left, first, second) in binary {
+            let length = match (result.get(&first), result.get(&second)) {
+                (Some(first), Some(second)) => first + second,
+                _ => continue,
+            };
+            if result.get(&left).is_none_or(|&known| length < known) {
+                result.insert(left, length);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return result;
+        }
+    }
+}
+
+/// Returns the size and longest sentence of the language, with its length,
+/// if it is finite, which is when no non-terminal reachable from the start
+/// symbol takes part in its own derivations.
+fn finiteness<G: Grammar + Debug + Clone>(
+    grammar: &G,
+    binary: &[(Symbol, Symbol, Symbol)],
+    empty: bool,
+) -> Option<(usize, usize, String)> {
+    if empty {
+        return Some((0, 0, String::new()));
+    }
+
+    let mut children: HashMap<Symbol, Vec<(Symbol, Symbol)>> = HashMap::new();
+    for &(left, first, second) in binary {
+        children.entry(left).or_default().push((first, second));
+    }
+    let table = grammar.symbols();
+    let name = |symbol: &Symbol| table.resolve(*symbol).unwrap_or("");
+    let terminal: HashMap<Symbol, &str> = grammar
+        .terminal_rules()
+        .iter()
+        .filter_map(|rule| Some((rule.start(), rule.symbols().map(name).min()?)))
+        .collect();
+
+    // Finds the longest sentence of every symbol reachable from the start
+    // symbol, giving up as soon as one is met again on its own path, and the
+    // rule it is derived with, if any.
+    let mut longest: HashMap<Symbol, usize> = HashMap::new();
+    let mut best: HashMap<Symbol, (Symbol, Symbol)> = HashMap::new();
+    let mut path: HashSet<Symbol> = HashSet::new();
+    let mut stack: Vec<(Symbol, bool)> = vec![(grammar.start_symbol(), false)];
+    while let Some((symbol, done)) = stack.pop() {
+        let rules = children
+            .get(&symbol)
+            .map_or(&[][..], |rules| rules.as_slice());
+        if done {
+            let mut length = if terminal.contains_key(&symbol) { 1 } else { 0 };
+            for &(first, second) in rules {
+                if longest[&first] + longest[&second] > length {
+                    length = longest[&first] + longest[&second];
+                    best.insert(symbol, (first, second));
+                }
+            }
+            longest.insert(symbol, length);
+            path.remove(&symbol);
+            continue;
+        }
+        if longest.contains_key(&symbol) {
+            continue;
+        }
+        if !path.insert(symbol) {
+            return None;
+        }
+
+        stack.push((symbol, true));
+        for (first, second) in rules {
+            for child in [first, second] {
+                if path.contains(child) {
+                    return None;
+                }
+                if !longest.contains_key(child) {
+                    stack.push((*child, false));
+                }
+            }
+        }
+    }
+
+    let mut sentence = String::new();
+    let mut stack = vec![grammar.start_symbol()];
+    while let Some(symbol) = stack.pop() {
+        match best.get(&symbol) {
+            Some(&(first, second)) => stack.extend([second, first]),
+            None => sentence.push_str(terminal[&symbol]),
+        }
+    }
+
+    let max_length = longest[&grammar.start_symbol()];
+    let mut language = Enumeration::new(grammar, max_length);
+    let size = (1..=max_length)
+        .map(|length| language.strings(length))
+        .sum();
+    Some((size, max_length, sentence))
+}
+
+/// Returns the symbols that reach themselves through `edges`, sorted by
+/// name.
+fn recursive(edges: &[(Symbol, Symbol)], table: &SymbolTable) -> Vec<Symbol> {
+    let mut next: HashMap<Symbol, Vec<Symbol>> = HashMap::new();
+    for &(from, to) in edges {
+        next.entry(from).or_default().push(to);
+    }
+
+    let mut result: Vec<Symbol> = Vec::new();
+    for &symbol in next.keys() {
+        let mut seen: HashSet<Symbol> = HashSet::new();
+        let mut stack: Vec<Symbol> = next[&symbol].clone();
+        while let Some(current) = stack.pop() {
+            if current == symbol {
+                result.push(symbol);
+                break;
+            }
+            if seen.insert(current) {
+                stack.extend(next.get(&current).into_iter().flatten());
+            }
+        }
+    }
+
+    result.sort_by_key(|symbol| table.resolve(*symbol).map(str::to_string));
+    result
+}
+
+impl CNF {
+    pub fn properties(&self) -> LanguageProperties {
+        LanguageProperties::new(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn language_properties() {
+        let sums = Cfg::parse("Sum -> Sum '+' Digit | Digit; Digit -> '0' | '1';")
+            .unwrap()
+            .to_cnf()
+            .unwrap();
+        let properties = sums.properties();
+        assert!(!properties.is_empty() && !properties.is_finite());
+        let symbol = |name| sums.symbols().get(name).unwrap();
+        assert_eq!(properties.shortest(symbol("Sum")), Some(1));
+        assert_eq!(properties.shortest(symbol("Sum_1")), Some(2));
+        assert!(properties.is_left_recursive(symbol("Sum")));
+        assert!(!properties.is_right_recursive(symbol("Sum")));
+
+        let pairs = Cfg::parse("S -> D D | D; D -> '0' | '1';")
+            .unwrap()
+            .to_cnf()
+            .unwrap();
+        let properties = pairs.properties();
+        assert_eq!(properties.size(), Some(6));
+        assert_eq!(properties.longest(), Some(2));
+        assert_eq!(properties.longest_sentence(), Some("00"));
+        assert_eq!(
+            properties.to_string(),
+            "language: finite, 6 sentences, the longest of length 2: 00\n\
+             shortest: D=1 S=1\n\
+             left-recursive:\n\
+             right-recursive:\n"
+        );
+
+        let unproductive = GrammarBuilder::new()
+            .start("S")
+            .rule("S", &["A", "B"])
+            .rule("A", &["A", "A"])
+            .terminal_rule("B", "b")
+            .build()
+            .unwrap();
+        let properties = unproductive.properties();
+        assert!(properties.is_empty() && properties.is_finite());
+        assert_eq!((properties.size(), properties.longest()), (Some(0), None));
+        assert_eq!(properties.longest_sentence(), None);
+        let start = unproductive.start_symbol();
+        assert_eq!(properties.shortest(start), None);
+        assert!(properties.to_string().starts_with("language: empty\n"));
+
+        let nested = Cfg::parse("S -> A B | 'c'; A -> 'a' 'a'; B -> 'b';")
+            .unwrap()
+            .to_cnf()
+            .unwrap()
+            .properties();
+        assert_eq!(nested.longest(), Some(3));
+        assert_eq!(nested.longest_sentence(), Some("aab"));
+    }
+}
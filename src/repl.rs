@@ -0,0 +1,312 @@
+use super::{load_cfg, read_file, Failure, LoadedCfg, Outcome};
+
+use cyk::{Cfg, DisplaySymbols, Grammar, GrammarSets, Parser, CNF};
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+const HELP: &str = "\
+Type a sentence to parse it with the current grammar, or a command:
+
+    :rule A -> B C | 'x';   add productions (the `;` may be left out)
+    :unrule A -> B C;       remove productions
+    :start A                make A the start symbol
+    :load FILE              read a grammar file
+    :reload                 read the last file again, dropping edits
+    :grammar                print the grammar
+    :cnf                    print the grammar the sentences are parsed with
+    :first A                print the FIRST set of A
+    :follow A               print the FOLLOW set of A
+    :ambig [SENTENCE]       count the parse trees of SENTENCE or of the last one
+    :tree                   toggle printing the tree (on)
+    :chart                  toggle printing the chart (off)
+    :help                   print this message
+    :quit                   leave
+";
+
+/// The state of an interactive session.
+struct Repl {
+    path: Option<String>,
+    cfg: Option<Cfg>,
+    grammar: Option<CNF>,
+    last: Option<String>,
+    show_tree: bool,
+    show_chart: bool,
+}
+
+/// Reads commands and sentences from stdin until it ends or `:quit`.
+pub fn run(path: Option<&str>) -> Outcome {
+    let mut repl = Repl {
+        path: None,
+        cfg: None,
+        grammar: None,
+        last: None,
+        show_tree: true,
+        show_chart: false,
+    };
+    if let Some(path) = path {
+        repl.load(path)?;
+    }
+
+    let interactive = io::stdin().is_terminal();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if interactive {
+            print!("cyk> ");
+            io::stdout().flush().ok();
+        }
+        let line = match lines.next() {
+            Some(line) => line.map_err(|error| Failure::Io(format!("stdin: {}", error)))?,
+            None => return Ok(()),
+        };
+
+        let line = line.trim_end_matches('\r');
+        if line.trim() == ":quit" || line.trim() == ":q" {
+            return Ok(());
+        }
+        repl.line(line);
+    }
+}
+
+impl Repl {
+    fn load(&mut self, path: &str) -> Outcome {
+        let bytes = read_file(path)?;
+        let cfg = match load_cfg(path, &bytes)? {
+            LoadedCfg::Text(cfg) => cfg,
+            LoadedCfg::Compiled(grammar) => Cfg::from_grammar(&*grammar),
+        };
+
+        println!(
+            "loaded {}: {}, start {}",
+            path,
+            productions(cfg.productions().len()),
+            cfg.start().display(cfg.symbols())
+        );
+        self.path = Some(path.to_string());
+        self.cfg = Some(cfg);
+        self.rebuild();
+        Ok(())
+    }
+
+    /// Recomputes the grammar sentences are parsed with after `cfg` changed.
+    /// A grammar that cannot be converted leaves none to parse with.
+    fn rebuild(&mut self) {
+        self.grammar = self.cfg.as_ref().and_then(|cfg| {
+            if cfg.is_cnf() {
+                if let Ok(grammar) = cfg.as_cnf() {
+                    return Some(grammar);
+                }
+            }
+            match cfg.to_cnf() {
+                Ok(grammar) => Some(grammar),
+                Err(error) => {
+                    println!("error: {}", error);
+                    None
+                }
+            }
+        });
+    }
+
+    fn line(&mut self, line: &str) {
+        let (command, argument) = match line.trim_start().strip_prefix(':') {
+            Some(command) => match command.split_once(char::is_whitespace) {
+                Some((command, argument)) => (command, argument.trim()),
+                None => (command.trim_end(), ""),
+            },
+            None if line.is_empty() => return,
+            None => return self.sentence(line),
+        };
+
+        match (command, argument) {
+            ("help", _) => print!("{}", HELP),
+            ("rule", rules) => self.edit(rules, true),
+            ("unrule", rules) => self.edit(rules, false),
+            ("start", name) if !name.is_empty() => self.start(name),
+            ("load", path) if !path.is_empty() => self.reload(Some(path.to_string())),
+            ("reload", "") => self.reload(self.path.clone()),
+            ("grammar", "") => match &self.cfg {
+                Some(cfg) => print!("{}", cfg),
+                None => no_grammar(),
+            },
+            ("cnf", "") => match &self.grammar {
+                Some(grammar) => print!("{}", Cfg::from_grammar(grammar)),
+                None => no_grammar(),
+            },
+            ("first", name) if !name.is_empty() => self.set(name, true),
+            ("follow", name) if !name.is_empty() => self.set(name, false),
+            ("ambig", sentence) => self.ambig(sentence),
+            ("tree", "") => {
+                self.show_tree = !self.show_tree;
+                println!("tree {}", if self.show_tree { "on" } else { "off" });
+            }
+            ("chart", "") => {
+                self.show_chart = !self.show_chart;
+                println!("chart {}", if self.show_chart { "on" } else { "off" });
+            }
+            _ => println!("unknown command `{}`; type :help for the list", line.trim()),
+        }
+    }
+
+    fn reload(&mut self, path: Option<String>) {
+        match path {
+            Some(path) => {
+                if let Err(Failure::Grammar(message) | Failure::Io(message)) = self.load(&path) {
+                    println!("error: {}", message);
+                }
+            }
+            None => println!("no file was loaded"),
+        }
+    }
+
+    fn edit(&mut self, rules: &str, add: bool) {
+        let mut src = rules.to_string();
+        if !src.trim_end().ends_with(';') {
+            src.push(';');
+        }
+
+        let cfg = match &mut self.cfg {
+            Some(cfg) => cfg,
+            None if add => {
+                match Cfg::parse(&src) {
+                    Ok(cfg) => {
+                        println!("added {}", productions(cfg.productions().len()));
+                        self.cfg = Some(cfg);
+                        self.rebuild();
+                    }
+                    Err(error) => report_error(&src, error.offset(), error.message()),
+                }
+                return;
+            }
+            None => return no_grammar(),
+        };
+
+        let result = if add {
+            cfg.add_rules(&src)
+        } else {
+            cfg.remove_rules(&src)
+        };
+        match result {
+            Ok(count) => {
+                let what = if add { "added" } else { "removed" };
+                println!("{} {}", what, productions(count));
+                self.rebuild();
+            }
+            Err(error) => report_error(&src, error.offset(), error.message()),
+        }
+    }
+
+    fn start(&mut self, name: &str) {
+        let cfg = match &mut self.cfg {
+            Some(cfg) => cfg,
+            None => return no_grammar(),
+        };
+        match cfg.add_rules(&format!("start {};", name)) {
+            Ok(_) => {
+                println!("start {}", name);
+                self.rebuild();
+            }
+            Err(error) => println!("error: {}", error.message()),
+        }
+    }
+
+    fn set(&self, name: &str, first: bool) {
+        let cfg = match &self.cfg {
+            Some(cfg) => cfg,
+            None => return no_grammar(),
+        };
+        let symbol = match cfg.symbols().get(name) {
+            Some(symbol) if cfg.non_terminals().contains(&symbol) => symbol,
+            _ => return println!("`{}` is not a non-terminal", name),
+        };
+
+        let sets: GrammarSets = cfg.sets();
+        let (label, set, extra) = if first {
+            let extra = if sets.is_nullable(symbol) { " ε" } else { "" };
+            ("FIRST", sets.first_set(symbol), extra)
+        } else {
+            let extra = if sets.can_end(symbol) { " $" } else { "" };
+            ("FOLLOW", sets.follow_set(symbol), extra)
+        };
+
+        let mut names: Vec<&str> = set
+            .into_iter()
+            .flatten()
+            .map(|&symbol| cfg.symbols().resolve(symbol).unwrap_or(""))
+            .collect();
+        names.sort_unstable();
+        let names: Vec<String> = names.iter().map(|name| format!("{:?}", name)).collect();
+        println!("{}({}) = {{ {}{} }}", label, name, names.join(" "), extra);
+    }
+
+    fn ambig(&mut self, sentence: &str) {
+        let grammar = match &self.grammar {
+            Some(grammar) => grammar,
+            None => return no_grammar(),
+        };
+        let sentence = match (sentence, &self.last) {
+            ("", Some(last)) => last.clone(),
+            ("", None) => return println!("no sentence was parsed yet"),
+            (sentence, _) => sentence.to_string(),
+        };
+
+        let mut parser = Parser::new(grammar);
+        let _ = parser.parse(&sentence);
+        match parser.chart().count_trees() {
+            0 => println!("rejected"),
+            1 => println!("unambiguous: 1 parse tree"),
+            count => {
+                println!("ambiguous: {} parse trees", count);
+                if self.show_tree {
+                    for tree in parser.chart().trees(2) {
+                        println!("  {}", tree.to_sexpr(grammar.symbols()));
+                    }
+                }
+            }
+        }
+        self.last = Some(sentence);
+    }
+
+    fn sentence(&mut self, sentence: &str) {
+        let grammar = match &self.grammar {
+            Some(grammar) => grammar,
+            None => return no_grammar(),
+        };
+
+        let mut parser = Parser::new(grammar);
+        match parser.parse(sentence) {
+            Ok(tree) => {
+                println!("accepted");
+                if self.show_tree {
+                    println!("{}", tree.to_sexpr(grammar.symbols()));
+                }
+            }
+            Err(unknowns) => {
+                println!("rejected");
+                for unknown in &unknowns {
+                    report_error(sentence, unknown.span().byte_start(), "unknown char");
+                }
+            }
+        }
+        if self.show_chart {
+            print!("{}", parser.chart().to_text());
+        }
+        self.last = Some(sentence.to_string());
+    }
+}
+
+fn productions(count: usize) -> String {
+    match count {
+        1 => "1 production".to_string(),
+        _ => format!("{} productions", count),
+    }
+}
+
+fn no_grammar() {
+    println!("no grammar; use :load FILE or :rule to start one");
+}
+
+/// Prints `line` with a caret under the byte `offset`.
+fn report_error(line: &str, offset: usize, message: &str) {
+    let column = line[..offset.min(line.len())].chars().count();
+    println!("  {}", line);
+    println!("  {}^ {}", " ".repeat(column), message);
+}
@@ -0,0 +1,258 @@
+use super::{Grammar, HashSet, Production, Symbol, CNF};
+
+use std::collections::HashMap;
+
+/// The textbook FIRST, FOLLOW, LAST and nullable sets of a grammar.
+///
+/// FIRST, FOLLOW and LAST hold terminals only. Whether the end of input may
+/// follow a symbol is answered by `can_end` rather than by an end marker in
+/// its FOLLOW set.
+#[derive(Clone, Debug, Default)]
+pub struct GrammarSets {
+    terminals: HashSet<Symbol>,
+    nullable: HashSet<Symbol>,
+    first: HashMap<Symbol, HashSet<Symbol>>,
+    last: HashMap<Symbol, HashSet<Symbol>>,
+    follow: HashMap<Symbol, HashSet<Symbol>>,
+    ends: HashSet<Symbol>,
+}
+
+fn union(
+    sets: &mut HashMap<Symbol, HashSet<Symbol>>,
+    into: Symbol,
+    from: &HashSet<Symbol>,
+) -> bool {
+    let entry = sets.entry(into).or_default();
+    let before = entry.len();
+    entry.extend(from);
+    entry.len() != before
+}
+
+impl GrammarSets {
+    pub fn new<G: Grammar>(grammar: &G) -> Self {
+        let productions = grammar
+            .productions()
+            .into_iter()
+            .map(|production| match production {
+                Production::Binary(left, first, second) => (left, vec![first, second]),
+                Production::Terminal(left, terminal) => (left, vec![terminal]),
+            });
+
+        GrammarSets::from_productions(grammar.start_symbol(), grammar.terminals(), productions)
+    }
+
+    /// Computes the sets of a general context-free grammar, given as
+    /// `A -> X1 ... Xn` pairs. An empty right side is an ε-production.
+    pub fn from_productions<I>(start: Symbol, terminals: &HashSet<Symbol>, productions: I) -> Self
+    where
+        I: IntoIterator<Item = (Symbol, Vec<Symbol>)>,
+    {
+        let productions: Vec<(Symbol, Vec<Symbol>)> = productions.into_iter().collect();
+        let mut sets = GrammarSets {
+            terminals: terminals.clone(),
+            ..GrammarSets::default()
+        };
+
+        for &terminal in terminals {
+            let single: HashSet<Symbol> = Some(terminal).into_iter().collect();
+            sets.first.insert(terminal, single.clone());
+            sets.last.insert(terminal, single);
+        }
+
+        loop {
+            let mut changed = false;
+
+            for (left, right) in &productions {
+                if !sets.nullable.contains(left) && sets.is_nullable_sequence(right) {
+                    sets.nullable.insert(*left);
+                    changed = true;
+                }
+
+                let first = sets.first_of(right);
+                changed |= union(&mut sets.first, *left, &first);
+
+                let last = sets.last_of(right);
+                changed |= union(&mut sets.last, *left, &last);
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        sets.ends.insert(start);
+        loop {
+            let mut changed = false;
+
+            for (left, right) in &productions {
+                for (index, &symbol) in right.iter().enumerate() {
+                    let rest = &right[index + 1..];
+
+                    let first = sets.first_of(rest);
+                    changed |= union(&mut sets.follow, symbol, &first);
+
+                    if sets.is_nullable_sequence(rest) {
+                        if let Some(follow) = sets.follow.get(left).cloned() {
+                            changed |= union(&mut sets.follow, symbol, &follow);
+                        }
+                        if sets.ends.contains(left) {
+                            changed |= sets.ends.insert(symbol);
+                        }
+                    }
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        sets.first.retain(|_, set| !set.is_empty());
+        sets.last.retain(|_, set| !set.is_empty());
+        sets.follow.retain(|_, set| !set.is_empty());
+        sets
+    }
+
+    /// Whether `symbol` derives the empty string.
+    pub fn is_nullable(&self, symbol: Symbol) -> bool {
+        self.nullable.contains(&symbol)
+    }
+
+    /// Whether every symbol of `symbols` derives the empty string.
+    pub fn is_nullable_sequence(&self, symbols: &[Symbol]) -> bool {
+        symbols.iter().all(|symbol| self.is_nullable(*symbol))
+    }
+
+    /// The terminals that begin some string derived from `symbol`.
+    pub fn first_set(&self, symbol: Symbol) -> Option<&HashSet<Symbol>> {
+        self.first.get(&symbol)
+    }
+
+    /// The terminals that end some string derived from `symbol`.
+    pub fn last_set(&self, symbol: Symbol) -> Option<&HashSet<Symbol>> {
+        self.last.get(&symbol)
+    }
+
+    /// The terminals that can come right after `symbol` in a sentential form
+    /// of the start symbol.
+    pub fn follow_set(&self, symbol: Symbol) -> Option<&HashSet<Symbol>> {
+        self.follow.get(&symbol)
+    }
+
+    /// Whether `symbol` can come last in a sentential form of the start
+    /// symbol, that is, whether the end of input is in its FOLLOW set.
+    pub fn can_end(&self, symbol: Symbol) -> bool {
+        self.ends.contains(&symbol)
+    }
+
+    /// The terminals that begin some string derived from `symbols`.
+    pub fn first_of(&self, symbols: &[Symbol]) -> HashSet<Symbol> {
+        let mut result: HashSet<Symbol> = HashSet::new();
+
+        for symbol in symbols {
+            if let Some(first) = self.first.get(symbol) {
+                result.extend(first);
+            }
+            if !self.is_nullable(*symbol) {
+                break;
+            }
+        }
+
+        result
+    }
+
+    /// The terminals that end some string derived from `symbols`.
+    pub fn last_of(&self, symbols: &[Symbol]) -> HashSet<Symbol> {
+        let mut result: HashSet<Symbol> = HashSet::new();
+
+        for symbol in symbols.iter().rev() {
+            if let Some(last) = self.last.get(symbol) {
+                result.extend(last);
+            }
+            if !self.is_nullable(*symbol) {
+                break;
+            }
+        }
+
+        result
+    }
+
+    pub fn is_terminal(&self, symbol: Symbol) -> bool {
+        self.terminals.contains(&symbol)
+    }
+}
+
+impl CNF {
+    pub fn sets(&self) -> GrammarSets {
+        GrammarSets::new(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    fn set(symbols: &[&str]) -> HashSet<Symbol> {
+        symbols
+            .iter()
+            .map(|symbol| Symbol::intern(symbol))
+            .collect()
+    }
+
+    #[test]
+    fn general_grammar_sets() {
+        // S -> A b | c, A -> a A | ε
+        let s = Symbol::intern;
+        let sets = GrammarSets::from_productions(
+            s("S"),
+            &set(&["a", "b", "c"]),
+            vec![
+                (s("S"), vec![s("A"), s("b")]),
+                (s("S"), vec![s("c")]),
+                (s("A"), vec![s("a"), s("A")]),
+                (s("A"), vec![]),
+            ],
+        );
+
+        assert!(sets.is_nullable(s("A")));
+        assert!(!sets.is_nullable(s("S")));
+        assert_eq!(sets.first_set(s("S")), Some(&set(&["a", "b", "c"])));
+        assert_eq!(sets.first_set(s("A")), Some(&set(&["a"])));
+        assert_eq!(sets.last_set(s("S")), Some(&set(&["b", "c"])));
+        assert_eq!(sets.follow_set(s("A")), Some(&set(&["b"])));
+        assert_eq!(sets.follow_set(s("a")), Some(&set(&["a", "b"])));
+        assert_eq!(sets.follow_set(s("S")), None);
+        assert!(sets.can_end(s("S")) && sets.can_end(s("b")));
+        assert!(!sets.can_end(s("A")));
+    }
+
+    #[test]
+    fn cnf_grammar_sets() {
+        let grammar = cnf_grammar! {
+            Start("Number");
+            NonTerminals["Number", "Integer", "Fraction", "Digit", "Point"];
+            Terminals["0", "1", "."];
+            Rules [
+                "Number" => [["Integer", "Fraction"]],
+                "Integer" => [["Integer", "Digit"]],
+                "Fraction" => [["Point", "Integer"]]
+            ];
+            TerminalRules [
+                "Number" => ["0", "1"],
+                "Integer" => ["0", "1"],
+                "Digit" => ["0", "1"],
+                "Point" => ["."]
+            ]
+        };
+        let s = Symbol::intern;
+        let sets = grammar.sets();
+
+        assert!(!sets.is_nullable(s("Number")));
+        assert_eq!(sets.first_set(s("Number")), Some(&set(&["0", "1"])));
+        assert_eq!(sets.first_set(s("Fraction")), Some(&set(&["."])));
+        assert_eq!(sets.last_set(s("Fraction")), Some(&set(&["0", "1"])));
+        assert_eq!(sets.follow_set(s("Integer")), Some(&set(&["0", "1", "."])));
+        assert_eq!(sets.follow_set(s("Point")), Some(&set(&["0", "1"])));
+        assert!(sets.can_end(s("Integer")) && !sets.can_end(s("Point")));
+    }
+}
@@ -0,0 +1,112 @@
+use super::tree::Span;
+
+use std::fmt;
+
+/// A 1-based line and column, where the column counts chars.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for LineCol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Maps byte offsets of a source onto lines and columns.
+#[derive(Clone, Debug)]
+pub struct SourceMap<'a> {
+    src: &'a str,
+    lines: Vec<usize>,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(src: &'a str) -> Self {
+        let lines = Some(0)
+            .into_iter()
+            .chain(src.match_indices('\n').map(|(offset, _)| offset + 1))
+            .collect();
+
+        SourceMap { src, lines }
+    }
+
+    pub fn src(&self) -> &'a str {
+        self.src
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Returns the position of the byte `offset`, which is clamped to the
+    /// end of the source and to the char boundary it falls in.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let mut offset = offset.min(self.src.len());
+        while !self.src.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let line = match self.lines.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        let column = self.src[self.lines[line]..offset].chars().count();
+
+        LineCol {
+            line: line + 1,
+            column: column + 1,
+        }
+    }
+
+    /// Returns the positions of the first char of `span` and of the char
+    /// right after it.
+    pub fn span_location(&self, span: Span) -> (LineCol, LineCol) {
+        (
+            self.line_col(span.byte_start()),
+            self.line_col(span.byte_end()),
+        )
+    }
+
+    /// Returns the text of the 1-based `line`, without its line break.
+    pub fn line(&self, line: usize) -> Option<&'a str> {
+        let start = *self.lines.get(line.checked_sub(1)?)?;
+        let end = match self.lines.get(line) {
+            Some(&next) => next - 1,
+            None => self.src.len(),
+        };
+
+        Some(self.src[start..end].trim_end_matches('\r'))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn line_col_of_offsets() {
+        let src = "ab\nçd\n\nx";
+        let map = SourceMap::new(src);
+
+        assert_eq!(map.line_count(), 4);
+        assert_eq!(map.line_col(0), LineCol { line: 1, column: 1 });
+        assert_eq!(map.line_col(3), LineCol { line: 2, column: 1 });
+        assert_eq!(map.line_col(5), LineCol { line: 2, column: 2 });
+        assert_eq!(map.line_col(7), LineCol { line: 3, column: 1 });
+        assert_eq!(map.line_col(100), LineCol { line: 4, column: 2 });
+        assert_eq!(map.line(2), Some("çd"));
+        assert_eq!(map.line(3), Some(""));
+        assert_eq!(map.line(5), None);
+
+        let span = Span::with_bytes(3, 2, 3, 3);
+        assert_eq!(
+            map.span_location(span),
+            (
+                LineCol { line: 2, column: 1 },
+                LineCol { line: 2, column: 3 }
+            )
+        );
+    }
+}
@@ -1,16 +1,23 @@
 use std::fmt;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 use rustc_hash::FxHashMap;
 
 use lazy_static::lazy_static;
 
+/// A compact handle to a name stored in a `SymbolTable`.
+///
+/// A symbol is only meaningful together with the table that handed it out.
+/// `Symbol::intern` and `Symbol::as_str` use the process-wide table kept for
+/// compatibility; grammars that own their table resolve through
+/// `SymbolTable::resolve`. On their own, `Debug` and `Display` write only the
+/// id, as `#3`; write a name with `DisplaySymbols::display`.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Symbol(usize);
+pub struct Symbol(u32);
 
 impl Symbol {
-    const fn new(n: u32) -> Self {
-        Symbol(n as usize)
+    pub(crate) const fn new(n: u32) -> Self {
+        Symbol(n)
     }
 
     pub fn intern(string: &str) -> Self {
@@ -21,29 +28,222 @@ impl Symbol {
         with_interner(|interner| interner.get(c.to_string().as_str()))
     }
 
+    /// Returns the name of the symbol in the process-wide table.
+    ///
+    /// Panics if the symbol was handed out by another table; the lock on
+    /// the process-wide table is released first, so it stays usable.
     pub fn as_str(&self) -> &'static str {
-        with_interner(|interner| unsafe {
-            std::mem::transmute::<&str, &str>(interner.get_str(*self))
-        })
+        match self.try_as_str() {
+            Some(string) => string,
+            None => panic!("symbol #{} is not in the global table", self.0),
+        }
+    }
+
+    /// Returns the name of the symbol in the process-wide table, or `None`
+    /// if the symbol was handed out by another table.
+    pub fn try_as_str(&self) -> Option<&'static str> {
+        with_interner(|interner| interner.try_get_str(*self))
     }
 
     pub fn as_u32(&self) -> u32 {
-        self.0 as u32
+        self.0
+    }
+
+    fn index(&self) -> usize {
+        self.0 as usize
     }
 }
 
 impl fmt::Debug for Symbol {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Debug::fmt(&self.as_str(), f)
+        write!(f, "#{}", self.0)
     }
 }
 
 impl fmt::Display for Symbol {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Display::fmt(&self.as_str(), f)
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Symbols are written as their names in the process-wide table, since ids
+/// depend on interning order and mean nothing to another process. A symbol
+/// from another table cannot be written on its own; a `CNF` writes its
+/// symbols through its own table.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Symbol {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match with_interner(|interner| interner.try_get_str(*self)) {
+            Some(string) => serializer.serialize_str(string),
+            None => Err(serde::ser::Error::custom(format!(
+                "symbol #{} is not in the global table",
+                self.0
+            ))),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Symbol {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Ok(Symbol::intern(&string))
+    }
+}
+
+/// An owned set of names, each mapped to a dense `Symbol` id.
+///
+/// Every grammar keeps its own table, so two grammars may use the same names
+/// without their symbols colliding.
+#[derive(Clone, Default)]
+pub struct SymbolTable {
+    /// Whether this is a view of the process-wide table, in which case
+    /// `names` and `strings` stay empty.
+    global: bool,
+    names: FxHashMap<Arc<str>, Symbol>,
+    strings: Vec<Arc<str>>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A view of the process-wide table: it copies nothing, and interning
+    /// through it interns into the process-wide table.
+    pub fn global() -> Self {
+        SymbolTable {
+            global: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_global(&self) -> bool {
+        self.global
+    }
+
+    pub fn intern(&mut self, string: &str) -> Symbol {
+        if self.global {
+            return Symbol::intern(string);
+        }
+        if let Some(&symbol) = self.names.get(string) {
+            return symbol;
+        }
+
+        let symbol = Symbol::new(self.strings.len() as u32);
+        let string: Arc<str> = Arc::from(string);
+        self.strings.push(string.clone());
+        self.names.insert(string, symbol);
+        symbol
+    }
+
+    pub fn get(&self, string: &str) -> Option<Symbol> {
+        if self.global {
+            return with_interner(|interner| interner.get(string));
+        }
+        self.names.get(string).copied()
+    }
+
+    pub fn from_char(&self, c: char) -> Option<Symbol> {
+        self.get(c.encode_utf8(&mut [0; 4]))
+    }
+
+    pub fn contains(&self, symbol: Symbol) -> bool {
+        symbol.index() < self.len()
+    }
+
+    pub fn resolve(&self, symbol: Symbol) -> Option<&str> {
+        if self.global {
+            return symbol.try_as_str();
+        }
+        self.strings
+            .get(symbol.index())
+            .map(|string| string.as_ref())
+    }
+
+    /// Iterates over the names in id order. For a view of the process-wide
+    /// table, the names interned so far are taken once, up front.
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &str)> {
+        let global: Vec<&str> = if self.global {
+            with_interner(|interner| interner.strings.clone())
+        } else {
+            Vec::new()
+        };
+        let owned = self.strings.iter().map(|string| string.as_ref());
+        global
+            .into_iter()
+            .chain(owned)
+            .enumerate()
+            .map(|(index, string)| (Symbol::new(index as u32), string))
+    }
+
+    pub fn len(&self) -> usize {
+        if self.global {
+            return with_interner(|interner| interner.strings.len());
+        }
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Debug for SymbolTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.global {
+            return write!(f, "SymbolTable::global()");
+        }
+        f.debug_list().entries(self.strings.iter()).finish()
+    }
+}
+
+/// Writing a value whose text holds symbol names, resolved through the
+/// table its symbols came from.
+///
+/// The errors that hold symbols also implement `Display`, which resolves
+/// them through the process-wide table; use `display` for a grammar with a
+/// table of its own.
+pub trait DisplaySymbols {
+    fn fmt_symbols(&self, symbols: &SymbolTable, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+
+    /// Returns an adaptor that implements `Display` with `symbols`.
+    fn display<'a>(&'a self, symbols: &'a SymbolTable) -> WithSymbols<'a, Self> {
+        WithSymbols {
+            value: self,
+            symbols,
+        }
+    }
+}
+
+/// A value paired with the table its symbols are resolved through; see
+/// `DisplaySymbols::display`.
+pub struct WithSymbols<'a, T: ?Sized> {
+    value: &'a T,
+    symbols: &'a SymbolTable,
+}
+
+impl<T: DisplaySymbols + ?Sized> fmt::Display for WithSymbols<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt_symbols(self.symbols, f)
+    }
+}
+
+/// Symbols missing from the table are written as `#` and their id.
+impl DisplaySymbols for Symbol {
+    fn fmt_symbols(&self, symbols: &SymbolTable, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match symbols.resolve(*self) {
+            Some(string) => fmt::Display::fmt(string, f),
+            None => write!(f, "#{}", self.0),
+        }
     }
 }
 
+/// The process-wide table behind `Symbol::intern`.
+///
+/// Interned strings are leaked so that `Symbol::as_str` can hand out
+/// `&'static str`; prefer a grammar-owned `SymbolTable` for anything built
+/// at runtime.
 #[derive(Default)]
 pub struct Interner {
     names: FxHashMap<&'static str, Symbol>,
@@ -52,14 +252,11 @@ pub struct Interner {
 
 impl Interner {
     pub fn exist(&mut self, string: &str) -> bool {
-        self.names.get(string).is_some()
+        self.names.contains_key(string)
     }
 
     pub fn get(&mut self, string: &str) -> Option<Symbol> {
-        match self.names.get(string) {
-            Some(&name) => Some(name),
-            None => None,
-        }
+        self.names.get(string).copied()
     }
 
     #[inline]
@@ -70,14 +267,18 @@ impl Interner {
 
         let name = Symbol::new(self.strings.len() as u32);
 
-        let string: &'static str = unsafe { &*(string as *const str) };
+        let string: &'static str = Box::leak(string.to_owned().into_boxed_str());
         self.strings.push(string);
         self.names.insert(string, name);
         name
     }
 
-    pub fn get_str(&self, symbol: Symbol) -> &str {
-        self.strings[symbol.0]
+    pub fn get_str(&self, symbol: Symbol) -> Option<&'static str> {
+        self.try_get_str(symbol)
+    }
+
+    pub fn try_get_str(&self, symbol: Symbol) -> Option<&'static str> {
+        self.strings.get(symbol.index()).copied()
     }
 }
 
@@ -103,6 +304,56 @@ lazy_static! {
     static ref SESSION_GLOBALS: Mutex<SessionGlobals> = Mutex::new(SessionGlobals::new());
 }
 
+/// Runs `f` on the process-wide table. A panic while the lock was held
+/// cannot leave the table half-updated, since `Interner::intern` only
+/// pushes once the name is leaked, so a poisoned lock is recovered from.
 fn with_interner<T, F: FnOnce(&mut Interner) -> T>(f: F) -> T {
-    f(&mut *SESSION_GLOBALS.lock().unwrap().symbol_interner.borrow_mut())
+    let globals = SESSION_GLOBALS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let result = f(&mut globals.symbol_interner.borrow_mut());
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn tables_are_independent() {
+        assert_send_sync::<SymbolTable>();
+
+        let mut first = SymbolTable::new();
+        let mut second = SymbolTable::new();
+        let a = first.intern("A");
+        let b = first.intern("B");
+        let other_b = second.intern("B");
+
+        assert_eq!(first.intern(&String::from("A")), a);
+        assert_eq!(first.resolve(b), Some("B"));
+        assert_eq!(second.resolve(other_b), Some("B"));
+        assert_eq!(second.get("A"), None);
+        assert_eq!(second.len(), 1);
+    }
+
+    #[test]
+    fn display_through_tables() {
+        let mut table = SymbolTable::new();
+        table.intern("first");
+        let owned = table.intern("owned");
+
+        assert_eq!(owned.display(&table).to_string(), "owned");
+        assert_eq!(owned.display(&SymbolTable::new()).to_string(), "#1");
+        assert_eq!(owned.to_string(), "#1");
+        assert_eq!(format!("{:?}", owned), "#1");
+    }
+
+    #[test]
+    fn interning_temporaries() {
+        let symbol = Symbol::intern(&format!("{}-{}", "temporary", 1));
+        assert_eq!(symbol.as_str(), "temporary-1");
+        assert_eq!(SymbolTable::global().resolve(symbol), Some("temporary-1"));
+    }
 }
@@ -1,21 +1,24 @@
-use super::Symbol;
-
+use super::{DisplaySymbols, Features, Production, Symbol, SymbolTable};
 use std::cmp::{Ord, Ordering};
 use std::fmt;
-use std::fmt::Debug;
+use std::ops::{Index, IndexMut, Range};
 
+/// A range of the source, in chars and in UTF-8 bytes.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-pub struct Span(usize, usize);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span(usize, usize, usize, usize);
 
 impl Span {
-    pub fn new(start: usize, len: usize) -> Self {
-        Span(start, len)
+    /// Builds a span whose byte offsets equal its char offsets, which only
+    /// holds for ASCII sources, so it is left to the tests; use
+    /// `with_bytes` or `Chart::span` elsewhere.
+    #[cfg(test)]
+    pub(crate) fn new(start: usize, len: usize) -> Self {
+        Span(start, len, start, len)
     }
 
-    pub fn as_str(&self) -> &'static str {
-        let string: &'static str =
-            unsafe { &*(format!("{},{}", self.0, self.1).as_str() as *const str) };
-        string
+    pub fn with_bytes(start: usize, len: usize, byte_start: usize, byte_len: usize) -> Self {
+        Span(start, len, byte_start, byte_len)
     }
 
     pub fn start(&self) -> usize {
@@ -29,11 +32,31 @@ impl Span {
     pub fn len(&self) -> usize {
         self.1
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.1 == 0
+    }
+
+    pub fn byte_start(&self) -> usize {
+        self.2
+    }
+
+    pub fn byte_end(&self) -> usize {
+        self.2 + self.3
+    }
+
+    pub fn byte_len(&self) -> usize {
+        self.3
+    }
+
+    pub fn byte_range(&self) -> Range<usize> {
+        self.byte_start()..self.byte_end()
+    }
 }
 
 impl fmt::Display for Span {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Display::fmt(&self.as_str(), f)
+        write!(f, "{},{}", self.0, self.1)
     }
 }
 
@@ -48,19 +71,56 @@ impl PartialOrd for Span {
         Some(self.cmp(other))
     }
 }
+/// A handle to a node stored in a `Tree`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
 
-#[derive(Debug, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NodeChildren {
     None,
-    Single(Box<Node>),
-    Double(Box<Node>, Box<Node>),
+    Single(NodeId),
+    Double(NodeId, NodeId),
 }
 
-#[derive(Clone)]
+impl NodeChildren {
+    pub fn iter(&self) -> impl Iterator<Item = NodeId> {
+        let (first, second) = match *self {
+            NodeChildren::None => (None, None),
+            NodeChildren::Single(child) => (Some(child), None),
+            NodeChildren::Double(left, right) => (Some(left), Some(right)),
+        };
+        first.into_iter().chain(second)
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            NodeChildren::None => 0,
+            NodeChildren::Single(_) => 1,
+            NodeChildren::Double(_, _) => 2,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node {
     kind: Symbol,
     span: Span,
     children: NodeChildren,
+    parent: Option<NodeId>,
+    features: Option<Features>,
 }
 
 impl PartialEq for Node {
@@ -69,38 +129,14 @@ impl PartialEq for Node {
     }
 }
 
-impl fmt::Display for Node {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self.children() {
-                NodeChildren::Double(left, right) => {
-                    format!("{}{}", left, right)
-                }
-                NodeChildren::Single(child) => format!("{}", child.as_ref()),
-                NodeChildren::None => format!("{}", self.kind()),
-            }
-        )
-    }
-}
-
-impl Debug for Node {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Node")
-            .field("kind", &self.kind)
-            .field("span", &self.span)
-            .field("value", &self.to_string())
-            .finish()
-    }
-}
-
 impl Node {
-    pub fn new(kind: Symbol, span: Span, children: NodeChildren) -> Self {
+    fn new(kind: Symbol, span: Span, parent: Option<NodeId>) -> Self {
         Node {
             kind,
             span,
-            children,
+            children: NodeChildren::None,
+            parent,
+            features: None,
         }
     }
 
@@ -108,104 +144,182 @@ impl Node {
         self.kind
     }
 
+    pub fn set_kind(&mut self, kind: Symbol) {
+        self.kind = kind
+    }
+
     pub fn span(&self) -> Span {
         self.span
     }
 
+    /// The features of the node once unified with those of the whole tree,
+    /// for a tree parsed with a `FeatureGrammar`.
+    pub fn features(&self) -> Option<&Features> {
+        self.features.as_ref()
+    }
+
+    pub fn set_features(&mut self, features: Option<Features>) {
+        self.features = features
+    }
+
     pub fn children(&self) -> NodeChildren {
-        self.children.clone()
+        self.children
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct Cell(pub Vec<Node>, Span);
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
 
-impl Ord for Cell {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.span().unwrap().cmp(&other.span().unwrap())
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_empty()
     }
-}
 
-impl PartialOrd for Cell {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+    /// Slices the text this node covers out of the source it was parsed from.
+    pub fn text<'s>(&self, src: &'s str) -> &'s str {
+        &src[self.span.byte_range()]
     }
 }
 
-impl PartialEq for Cell {
-    fn eq(&self, other: &Self) -> bool {
-        if self.len() != other.len() {
-            return false;
-        }
+/// A parse tree whose nodes live in one arena and refer to each other by
+/// `NodeId`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tree {
+    nodes: Vec<Node>,
+    root: NodeId,
+}
 
-        for i in 0..self.len() {
-            if !self.nth(i).unwrap().eq(&other.nth(i).unwrap()) {
-                return false;
-            }
+impl Tree {
+    pub fn new(kind: Symbol, span: Span) -> Self {
+        Tree {
+            nodes: vec![Node::new(kind, span, None)],
+            root: NodeId(0),
         }
+    }
 
-        true
+    /// Appends a node as the next child of `parent`.
+    ///
+    /// Panics if `parent` already has two children.
+    pub fn push(&mut self, parent: NodeId, kind: Symbol, span: Span) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node::new(kind, span, Some(parent)));
+
+        let node = &mut self.nodes[parent.0];
+        node.children = match node.children {
+            NodeChildren::None => NodeChildren::Single(id),
+            NodeChildren::Single(first) => NodeChildren::Double(first, id),
+            NodeChildren::Double(_, _) => panic!("Node:{} already has two children", node.kind),
+        };
+
+        id
     }
-}
 
-impl Eq for Cell {}
-
-#[macro_export]
-macro_rules! cell {
-    (;$span:expr) => (
-        Cell::new(Vec::new(), $span)
-    );
-    ($elem:expr; $n:expr; $span:expr) => (
-        Cell::new(vec::from_elem($elem, $n))
-    );
-    ($($x:expr),+ $(,)?; $span:expr) => (
-        <Cell>::new([$($x),+].to_vec(), $span)
-    );
-}
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id.0)
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.nodes.get_mut(id.0)
+    }
 
-impl Cell {
-    pub fn new(nodes: Vec<Node>, span: Span) -> Self {
-        Cell(nodes, span)
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (NodeId(index), node))
+    }
+
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item = NodeId> {
+        self[id].children.iter()
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self[id].parent
     }
 
-    pub fn nth(&self, n: usize) -> Option<&Node> {
-        match self.0.get(n) {
-            Some(node) => Some(node),
-            None => None,
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        match self[self.parent(id)?].children {
+            NodeChildren::Double(left, right) if left == id => Some(right),
+            _ => None,
         }
     }
 
-    pub fn span(&self) -> Option<Span> {
-        match self.0.last() {
-            Some(node) => Some(node.span),
-            None => None,
+    pub fn prev_sibling(&self, id: NodeId) -> Option<NodeId> {
+        match self[self.parent(id)?].children {
+            NodeChildren::Double(left, right) if right == id => Some(left),
+            _ => None,
         }
     }
 
-    pub fn len(&self) -> usize {
-        self.0.len()
+    /// Iterates over the other children of `id`'s parent, in order.
+    pub fn siblings(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.parent(id)
+            .into_iter()
+            .flat_map(move |parent| self.children(parent))
+            .filter(move |&sibling| sibling != id)
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+    /// Returns the production applied at `id`, or `None` for a leaf.
+    pub fn production(&self, id: NodeId) -> Option<Production> {
+        let node = &self[id];
+        match node.children {
+            NodeChildren::None => None,
+            NodeChildren::Single(child) => Some(Production::Terminal(node.kind, self[child].kind)),
+            NodeChildren::Double(left, right) => Some(Production::Binary(
+                node.kind,
+                self[left].kind,
+                self[right].kind,
+            )),
+        }
     }
 
-    pub fn append(&mut self, mut another: Cell) {
-        self.0.append(another.0.as_mut())
+    /// Slices the text the whole tree covers out of its source.
+    pub fn text<'s>(&self, src: &'s str) -> &'s str {
+        self[self.root].text(src)
     }
+}
+
+impl Index<NodeId> for Tree {
+    type Output = Node;
 
-    pub fn append_nodes(&mut self, mut nodes: Vec<Node>) {
-        self.0.append(&mut nodes)
+    fn index(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
     }
+}
 
-    pub fn push_nodes(&mut self, node: Node) {
-        self.0.push(node)
+impl IndexMut<NodeId> for Tree {
+    fn index_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id.0]
     }
+}
 
-    pub fn has(&self, symbol: Symbol) -> Option<&Node> {
-        match self.0.iter().find(|node| node.kind.eq(&symbol)) {
-            Some(node) => Some(node),
-            None => None,
+/// Writes the leaves of the tree, that is the sentence it was built from.
+impl DisplaySymbols for Tree {
+    fn fmt_symbols(&self, symbols: &SymbolTable, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            let node = &self[id];
+            match node.children {
+                NodeChildren::None => node.kind.fmt_symbols(symbols, f)?,
+                NodeChildren::Single(child) => stack.push(child),
+                NodeChildren::Double(left, right) => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+            }
         }
+        Ok(())
     }
 }
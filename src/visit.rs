@@ -0,0 +1,352 @@
+use super::{Node, NodeId, Symbol, Tree};
+
+use std::collections::{HashMap, VecDeque};
+
+/// What a walk should do after entering a node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    SkipChildren,
+    Stop,
+}
+
+pub trait Visitor {
+    fn enter(&mut self, _tree: &Tree, _id: NodeId) -> Control {
+        Control::Continue
+    }
+
+    fn exit(&mut self, _tree: &Tree, _id: NodeId) {}
+}
+
+pub trait VisitorMut {
+    fn enter(&mut self, _tree: &mut Tree, _id: NodeId) -> Control {
+        Control::Continue
+    }
+
+    fn exit(&mut self, _tree: &mut Tree, _id: NodeId) {}
+}
+
+type Hook<'f> = Box<dyn FnMut(&Tree, NodeId) + 'f>;
+
+/// A `Visitor` built from closures registered for particular symbols.
+#[derive(Default)]
+pub struct Hooks<'f> {
+    enter: HashMap<Symbol, Vec<Hook<'f>>>,
+    exit: HashMap<Symbol, Vec<Hook<'f>>>,
+}
+
+impl<'f> Hooks<'f> {
+    pub fn new() -> Self {
+        Hooks {
+            enter: HashMap::new(),
+            exit: HashMap::new(),
+        }
+    }
+
+    pub fn on_enter<F: FnMut(&Tree, NodeId) + 'f>(mut self, symbol: Symbol, hook: F) -> Self {
+        self.enter.entry(symbol).or_default().push(Box::new(hook));
+        self
+    }
+
+    pub fn on_exit<F: FnMut(&Tree, NodeId) + 'f>(mut self, symbol: Symbol, hook: F) -> Self {
+        self.exit.entry(symbol).or_default().push(Box::new(hook));
+        self
+    }
+}
+
+impl<'f> Visitor for Hooks<'f> {
+    fn enter(&mut self, tree: &Tree, id: NodeId) -> Control {
+        if let Some(hooks) = self.enter.get_mut(&tree[id].kind()) {
+            hooks.iter_mut().for_each(|hook| hook(tree, id));
+        }
+        Control::Continue
+    }
+
+    fn exit(&mut self, tree: &Tree, id: NodeId) {
+        if let Some(hooks) = self.exit.get_mut(&tree[id].kind()) {
+            hooks.iter_mut().for_each(|hook| hook(tree, id));
+        }
+    }
+}
+
+pub struct PreOrder<'t> {
+    tree: &'t Tree,
+    stack: Vec<NodeId>,
+}
+
+impl<'t> Iterator for PreOrder<'t> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.stack.pop()?;
+        let len = self.stack.len();
+        self.stack.extend(self.tree.children(id));
+        self.stack[len..].reverse();
+        Some(id)
+    }
+}
+
+pub struct PostOrder<'t> {
+    tree: &'t Tree,
+    stack: Vec<(NodeId, bool)>,
+}
+
+impl<'t> Iterator for PostOrder<'t> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        loop {
+            let (id, expanded) = self.stack.pop()?;
+            if expanded {
+                return Some(id);
+            }
+
+            self.stack.push((id, true));
+            let len = self.stack.len();
+            self.stack
+                .extend(self.tree.children(id).map(|child| (child, false)));
+            self.stack[len..].reverse();
+        }
+    }
+}
+
+pub struct BreadthFirst<'t> {
+    tree: &'t Tree,
+    queue: VecDeque<NodeId>,
+}
+
+impl<'t> Iterator for BreadthFirst<'t> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.queue.pop_front()?;
+        self.queue.extend(self.tree.children(id));
+        Some(id)
+    }
+}
+
+pub struct Ancestors<'t> {
+    tree: &'t Tree,
+    next: Option<NodeId>,
+}
+
+impl<'t> Iterator for Ancestors<'t> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        self.next = self.tree.parent(id);
+        Some(id)
+    }
+}
+
+impl Tree {
+    /// Walks the subtree of `id`, calling `enter` before and `exit` after
+    /// the children of every node.
+    pub fn walk<V: Visitor>(&self, id: NodeId, visitor: &mut V) {
+        let mut stack = vec![(id, false)];
+
+        while let Some((id, entered)) = stack.pop() {
+            if entered {
+                visitor.exit(self, id);
+                continue;
+            }
+
+            let control = visitor.enter(self, id);
+            if control == Control::Stop {
+                return;
+            }
+
+            stack.push((id, true));
+            if control == Control::Continue {
+                let len = stack.len();
+                stack.extend(self.children(id).map(|child| (child, false)));
+                stack[len..].reverse();
+            }
+        }
+    }
+
+    /// Like `walk`, but lets the visitor modify the tree as it goes.
+    pub fn walk_mut<V: VisitorMut>(&mut self, id: NodeId, visitor: &mut V) {
+        let mut stack = vec![(id, false)];
+
+        while let Some((id, entered)) = stack.pop() {
+            if entered {
+                visitor.exit(self, id);
+                continue;
+            }
+
+            let control = visitor.enter(self, id);
+            if control == Control::Stop {
+                return;
+            }
+
+            stack.push((id, true));
+            if control == Control::Continue {
+                let len = stack.len();
+                stack.extend(self.children(id).map(|child| (child, false)));
+                stack[len..].reverse();
+            }
+        }
+    }
+
+    pub fn pre_order(&self, id: NodeId) -> PreOrder<'_> {
+        PreOrder {
+            tree: self,
+            stack: vec![id],
+        }
+    }
+
+    pub fn post_order(&self, id: NodeId) -> PostOrder<'_> {
+        PostOrder {
+            tree: self,
+            stack: vec![(id, false)],
+        }
+    }
+
+    pub fn breadth_first(&self, id: NodeId) -> BreadthFirst<'_> {
+        BreadthFirst {
+            tree: self,
+            queue: Some(id).into_iter().collect(),
+        }
+    }
+
+    /// Iterates from the parent of `id` up to the root.
+    pub fn ancestors(&self, id: NodeId) -> Ancestors<'_> {
+        Ancestors {
+            tree: self,
+            next: self.parent(id),
+        }
+    }
+
+    /// Returns the number of edges between `id` and the root.
+    pub fn depth(&self, id: NodeId) -> usize {
+        self.ancestors(id).count()
+    }
+
+    /// Iterates over the leaves of the whole tree, left to right.
+    pub fn leaves(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.pre_order(self.root())
+            .filter(move |&id| self[id].is_leaf())
+    }
+
+    /// Returns every node of `symbol` in the whole tree, in pre-order.
+    pub fn find_all(&self, symbol: Symbol) -> Vec<NodeId> {
+        self.pre_order(self.root())
+            .filter(|&id| self[id].kind() == symbol)
+            .collect()
+    }
+
+    /// Evaluates the subtree of `id` bottom-up, handing `f` each node with
+    /// the values of its children.
+    pub fn fold<T, F: FnMut(NodeId, &Node, Vec<T>) -> T>(&self, id: NodeId, mut f: F) -> T {
+        let mut values: Vec<T> = Vec::new();
+
+        for id in self.post_order(id) {
+            let node = &self[id];
+            let children = values.split_off(values.len() - node.children().len());
+            values.push(f(id, node, children));
+        }
+
+        values.pop().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    // Sum -> Add Num, Add -> Num Plus, over "1+2".
+    fn sum_tree() -> Tree {
+        let (sum, add, num, plus) = (
+            Symbol::intern("Sum"),
+            Symbol::intern("Add"),
+            Symbol::intern("Num"),
+            Symbol::intern("Plus"),
+        );
+        let mut tree = Tree::new(sum, Span::new(0, 3));
+        let left = tree.push(tree.root(), add, Span::new(0, 2));
+        let right = tree.push(tree.root(), num, Span::new(2, 1));
+        let one = tree.push(left, num, Span::new(0, 1));
+        tree.push(one, Symbol::intern("1"), Span::new(0, 1));
+        let op = tree.push(left, plus, Span::new(1, 1));
+        tree.push(op, Symbol::intern("+"), Span::new(1, 1));
+        tree.push(right, Symbol::intern("2"), Span::new(2, 1));
+        tree
+    }
+
+    fn kinds(tree: &Tree, ids: impl Iterator<Item = NodeId>) -> Vec<String> {
+        ids.map(|id| tree[id].kind().as_str().to_string()).collect()
+    }
+
+    #[test]
+    fn traversal_orders() {
+        let tree = sum_tree();
+        let root = tree.root();
+
+        assert_eq!(
+            kinds(&tree, tree.pre_order(root)),
+            ["Sum", "Add", "Num", "1", "Plus", "+", "Num", "2"]
+        );
+        assert_eq!(
+            kinds(&tree, tree.post_order(root)),
+            ["1", "Num", "+", "Plus", "Add", "2", "Num", "Sum"]
+        );
+        assert_eq!(
+            kinds(&tree, tree.breadth_first(root)),
+            ["Sum", "Add", "Num", "Num", "Plus", "2", "1", "+"]
+        );
+        assert_eq!(kinds(&tree, tree.leaves()), ["1", "+", "2"]);
+
+        let nums = tree.find_all(Symbol::intern("Num"));
+        assert_eq!(nums.len(), 2);
+        assert_eq!(tree.depth(nums[0]), 2);
+        assert_eq!(kinds(&tree, tree.ancestors(nums[0])), ["Add", "Sum"]);
+    }
+
+    #[test]
+    fn fold_and_visit() {
+        let tree = sum_tree();
+        let src = "1+2";
+
+        let value = tree.fold(tree.root(), |_, node, children: Vec<i64>| {
+            match node.kind().as_str() {
+                "Sum" | "Add" => children.iter().sum(),
+                "Plus" | "+" => 0,
+                _ if node.is_leaf() => node.text(src).parse().unwrap(),
+                _ => children[0],
+            }
+        });
+        assert_eq!(value, 3);
+
+        let mut entered = Vec::new();
+        let mut exited = 0;
+        let mut hooks = Hooks::new()
+            .on_enter(Symbol::intern("Num"), |tree, id| {
+                entered.push(tree[id].span().start())
+            })
+            .on_exit(Symbol::intern("Sum"), |_, _| exited += 1);
+        tree.walk(tree.root(), &mut hooks);
+        drop(hooks);
+
+        assert_eq!(entered, vec![0, 2]);
+        assert_eq!(exited, 1);
+
+        struct Rename(Symbol);
+        impl VisitorMut for Rename {
+            fn enter(&mut self, tree: &mut Tree, id: NodeId) -> Control {
+                if tree[id].kind().as_str() == "Add" {
+                    tree[id].set_kind(self.0);
+                    return Control::SkipChildren;
+                }
+                Control::Continue
+            }
+        }
+
+        let mut tree = tree;
+        let expr = Symbol::intern("Expr");
+        tree.walk_mut(tree.root(), &mut Rename(expr));
+        assert_eq!(tree.find_all(expr).len(), 1);
+        assert_eq!(tree.display(&SymbolTable::global()).to_string(), "1+2");
+    }
+}
@@ -0,0 +1,226 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn cyk(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cyk"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn grammar_file(name: &str, src: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cyk-cli-{}-{}", std::process::id(), name));
+    fs::write(&path, src).unwrap();
+    path
+}
+
+#[test]
+fn exit_codes() {
+    let cfg = grammar_file(
+        "sum.cfg",
+        "Sum -> Sum '+' Digit | Digit; Digit -> '0' | '1';",
+    );
+    let cnf = cfg.with_extension("cnf");
+    let (cfg, cnf_path) = (cfg.to_str().unwrap(), cnf.to_str().unwrap());
+
+    assert_eq!(
+        cyk(&["convert", cfg, "-o", cnf_path]).status.code(),
+        Some(0)
+    );
+
+    let accepted = cyk(&["parse", cnf_path, "--text", "1+0"]);
+    assert_eq!(accepted.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&accepted.stdout),
+        "(Sum (Sum \"1\") (Sum_1 (<+> \"+\") (Digit \"0\")))\n"
+    );
+
+    let rejected = cyk(&["parse", cnf_path, "--text", "1+"]);
+    assert_eq!(rejected.status.code(), Some(1));
+    assert_eq!(cyk(&["parse", cfg, "--text", "1"]).status.code(), Some(3));
+    assert_eq!(cyk(&["parse", cnf_path, "--frob"]).status.code(), Some(2));
+    assert_eq!(cyk(&["check", "no-such-grammar"]).status.code(), Some(4));
+
+    fs::remove_file(cfg).unwrap();
+    fs::remove_file(cnf).unwrap();
+}
+
+#[test]
+fn check_before_conversion() {
+    let cfg = grammar_file("loop.cfg", "S -> 'a' 'b'; X -> X 'x';");
+    let output = cyk(&["check", cfg.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("unreachable: X\nnon-productive: X\n"),
+        "{}",
+        stdout
+    );
+
+    let parsed = cyk(&["enumerate", cfg.to_str().unwrap()]);
+    assert_eq!(parsed.status.code(), Some(3));
+    fs::remove_file(cfg).unwrap();
+}
+
+#[test]
+fn repl_session() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cyk"))
+        .arg("repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b":rule S -> 'a'\n:tree\naa\n:rule S -> S S\n:ambig aaa\n:quit\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "added 1 production\ntree off\nrejected\nadded 1 production\nambiguous: 2 parse trees\n"
+    );
+}
+
+#[test]
+fn repl_sets() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cyk"))
+        .arg("repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b":rule S -> 'a' S | 'b'\n:first S\n:follow Nowhere\n:first a\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "added 2 productions\nFIRST(S) = { \"a\" \"b\" }\n\
+         `Nowhere` is not a non-terminal\n`a` is not a non-terminal\n"
+    );
+}
+
+#[test]
+fn compare_compiled_grammars() {
+    let first = grammar_file("right.cfg", "S -> A S | 'b'; A -> 'a';");
+    let second = grammar_file("left.cfg", "T -> A U | 'b'; U -> A U | 'b'; A -> 'a';");
+    let (first_bin, second_bin) = (first.with_extension("bin"), second.with_extension("bin"));
+    let paths = [&first, &second, &first_bin, &second_bin].map(|path| path.to_str().unwrap());
+
+    for (text, binary) in [(paths[0], paths[2]), (paths[1], paths[3])] {
+        let converted = cyk(&["convert", text, "--binary", "-o", binary]);
+        assert_eq!(converted.status.code(), Some(0));
+    }
+    for (a, b) in [
+        (paths[2], paths[3]),
+        (paths[0], paths[3]),
+        (paths[2], paths[1]),
+    ] {
+        let output = cyk(&["compare", a, b]);
+        assert_eq!(output.status.code(), Some(0), "{} {}", a, b);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "equal up to length 8\n"
+        );
+    }
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cyk"))
+        .arg("repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let session = format!(":load {}\n:load {}\nab\n:quit\n", paths[0], paths[3]);
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(session.as_bytes()).unwrap();
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.ends_with(&format!(
+            "loaded {}: 5 productions, start T\naccepted\n(T (A \"a\") (U \"b\"))\n",
+            paths[3]
+        )),
+        "{}",
+        stdout
+    );
+
+    for path in paths {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn compare_text_grammars() {
+    let nested = grammar_file("nested.cfg", "S -> 'a' S 'b' | 'a' 'b';");
+    let converted = nested.with_extension("cnf");
+    let longer = grammar_file(
+        "longer.cfg",
+        "S -> 'a' S 'b' | 'a' 'b' | 'a' 'a' 'b' 'b' 'b';",
+    );
+    let paths = [&nested, &converted, &longer].map(|path| path.to_str().unwrap());
+
+    let output = cyk(&["convert", paths[0], "-o", paths[1]]);
+    assert_eq!(output.status.code(), Some(0));
+    for (a, b) in [(paths[0], paths[1]), (paths[1], paths[0])] {
+        let output = cyk(&["compare", a, b]);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "equal up to length 8\n"
+        );
+    }
+
+    let output = cyk(&["compare", paths[0], paths[1], "--max-sentences", "0"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "equal on samples of each length up to 8\n"
+    );
+    let output = cyk(&["compare", paths[0], paths[2], "--max-sentences", "0"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "\"aabbb\" is accepted by the second grammar only\n"
+    );
+
+    for path in paths {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn generate_with_weights() {
+    let cfg = grammar_file("coin.cfg", "S -> 'a' | 'b';");
+    let weights = grammar_file("coin.weights", "0 S -> 'b'\n\n");
+    let (cfg, weights) = (cfg.to_str().unwrap(), weights.to_str().unwrap());
+
+    let output = cyk(&[
+        "generate",
+        cfg,
+        "-n",
+        "20",
+        "--seed",
+        "1",
+        "--weights",
+        weights,
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a\n".repeat(20));
+
+    fs::write(weights, "1 S -> 'c'\n").unwrap();
+    let output = cyk(&["generate", cfg, "--weights", weights]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(":1: the grammar has no production"));
+
+    fs::remove_file(cfg).unwrap();
+    fs::remove_file(weights).unwrap();
+}